@@ -0,0 +1,848 @@
+//! Copy of a static presentation, its MPD and segments, playable from a local server.
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::Duration;
+use url::Url;
+
+use crate::base_url::{BaseURLError, ByteRange, ResolvedBaseURL, SegmentLocation};
+use crate::download::{DownloadError, SegmentDownloader};
+use crate::error::Error;
+use crate::fetch::{FetchError, MPDFetcher};
+use crate::models::mpd::MPD;
+use crate::models::segments::{MediaTime, SegmentInformation, SegmentTemplate, SegmentUrl};
+use crate::template::{Template, TemplateError, TemplateValues};
+use crate::xlink::{XLinkError, XLinkResolver};
+use crate::xml::{self, Element, Node};
+
+/// Name of the MPD written in the directory by [`Archiver`] and [`Recorder`].
+///
+/// [`Recorder`]: crate::Recorder
+pub const ARCHIVED_MANIFEST: &str = "manifest.mpd";
+
+/// Children giving the segments of a level, replaced by those of each `Representation`.
+pub(crate) const SEGMENT_ELEMENTS: [&str; 4] =
+    ["BaseURL", "SegmentBase", "SegmentList", "SegmentTemplate"];
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    // Boxed, they are much larger than the other variants.
+    Fetch(Box<FetchError>),
+    XLink(Box<XLinkError>),
+    /// Only static MPDs can be archived, a dynamic one has no end.
+    Dynamic(String),
+    BaseURL(BaseURLError),
+    Template(TemplateError),
+    /// The segments of the `Representation` can't be listed: its `SegmentTemplate` has
+    /// neither `SegmentTimeline` nor `duration`, or its period lasts an unknown time.
+    UnlistableSegments(String),
+    Download(DownloadError),
+    /// A file of the `Representation` would be written outside of the directory.
+    UnsafePath(String),
+    Io(PathBuf, io::Error),
+    /// The rewritten MPD can't be written.
+    InvalidMPD(Error),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Fetch(error) => error.fmt(f),
+            ArchiveError::XLink(error) => error.fmt(f),
+            ArchiveError::Dynamic(url) => write!(f, "Can't archive a dynamic MPD: {}", url),
+            ArchiveError::BaseURL(error) => error.fmt(f),
+            ArchiveError::Template(error) => error.fmt(f),
+            ArchiveError::UnlistableSegments(id) => {
+                write!(f, "Can't list the segments of Representation {}", id)
+            }
+            ArchiveError::Download(error) => error.fmt(f),
+            ArchiveError::UnsafePath(path) => {
+                write!(f, "Path outside of the archive directory: {}", path)
+            }
+            ArchiveError::Io(path, error) => write!(f, "Can't write {}: {}", path.display(), error),
+            ArchiveError::InvalidMPD(error) => write!(f, "Invalid archived MPD: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ArchiveError::Fetch(error) => Some(error.as_ref()),
+            ArchiveError::XLink(error) => Some(error.as_ref()),
+            ArchiveError::BaseURL(error) => Some(error),
+            ArchiveError::Template(error) => Some(error),
+            ArchiveError::Download(error) => Some(error),
+            ArchiveError::Io(_, error) => Some(error),
+            ArchiveError::InvalidMPD(error) => Some(error),
+            ArchiveError::Dynamic(_)
+            | ArchiveError::UnlistableSegments(_)
+            | ArchiveError::UnsafePath(_) => None,
+        }
+    }
+}
+
+impl From<BaseURLError> for ArchiveError {
+    fn from(error: BaseURLError) -> Self {
+        ArchiveError::BaseURL(error)
+    }
+}
+
+impl From<TemplateError> for ArchiveError {
+    fn from(error: TemplateError) -> Self {
+        ArchiveError::Template(error)
+    }
+}
+
+/// What [`Archiver::archive`] wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveReport {
+    manifest: PathBuf,
+    files: usize,
+    bytes: u64,
+}
+
+impl ArchiveReport {
+    /// Path of the rewritten MPD.
+    pub fn manifest(&self) -> &Path {
+        &self.manifest
+    }
+
+    /// Segment files written, the MPD excluded.
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// Mirrors a static MPD to a directory: its remote elements are resolved, the
+/// initialization and media segments of the chosen `Representation`s downloaded, and
+/// the MPD rewritten to reference them by relative paths.
+///
+/// Segments are stored as `<period index>/<representation id>/...`, next to the
+/// rewritten MPD, [`ARCHIVED_MANIFEST`].
+#[derive(Debug, Clone, Default)]
+pub struct Archiver {
+    fetcher: MPDFetcher,
+    downloader: SegmentDownloader,
+    resolver: XLinkResolver,
+    representations: Vec<String>,
+}
+
+impl Archiver {
+    pub fn new() -> Self {
+        Archiver::default()
+    }
+
+    pub fn with_fetcher(mut self, fetcher: MPDFetcher) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    pub fn with_downloader(mut self, downloader: SegmentDownloader) -> Self {
+        self.downloader = downloader;
+        self
+    }
+
+    /// Resolver of the remote elements, those to actuate `onRequest` included.
+    pub fn with_xlink_resolver(mut self, resolver: XLinkResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Archives only the `Representation`s with these ids, every one by default. The
+    /// others are removed from the MPD, and so are the `AdaptationSet`s left empty.
+    pub fn with_representations<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.representations = ids.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub async fn archive(
+        &self,
+        url: &str,
+        directory: &Path,
+    ) -> Result<ArchiveReport, ArchiveError> {
+        let fetched = self
+            .fetcher
+            .fetch(url)
+            .await
+            .map_err(|error| ArchiveError::Fetch(Box::new(error)))?;
+        if !fetched.mpd().is_static() {
+            return Err(ArchiveError::Dynamic(fetched.url().to_string()));
+        }
+        let mpd = self
+            .resolver
+            .clone()
+            .with_on_request(true)
+            .resolve(fetched.mpd(), fetched.url())
+            .await
+            .map_err(|error| ArchiveError::XLink(Box::new(error)))?;
+
+        let timeline = mpd.period_timeline();
+        let mut representations = Vec::new();
+        for (period_index, period) in mpd.periods().iter().enumerate() {
+            for (adaptation_index, adaptation) in period.adaptations().iter().enumerate() {
+                for (index, representation) in adaptation.representations().iter().enumerate() {
+                    if !self.representations.is_empty()
+                        && !self
+                            .representations
+                            .iter()
+                            .any(|id| id == representation.id())
+                    {
+                        continue;
+                    }
+                    let context = Context {
+                        base: mpd
+                            .resolve_base_urls(fetched.url(), period, adaptation, representation)?
+                            .swap_remove(0),
+                        directory: format!(
+                            "{}/{}",
+                            period_index,
+                            file_name(representation.id(), "representation")
+                        ),
+                        id: representation.id().to_string(),
+                        values: TemplateValues::new(
+                            representation.id(),
+                            representation.bandwidth(),
+                        ),
+                        period_duration: mpd.period_duration_in(&timeline, period_index),
+                    };
+                    let mut archived =
+                        context.archive(period.segment_information(adaptation, representation))?;
+                    archived.position = (period_index, adaptation_index, index);
+                    representations.push(archived);
+                }
+            }
+        }
+
+        let mut files = 0;
+        let mut bytes = 0;
+        for archived in &representations {
+            let locations: Vec<_> = archived
+                .files
+                .iter()
+                .map(|(location, _)| location.clone())
+                .collect();
+            // Each file is written as soon as it is downloaded, a `Representation` can
+            // be much larger than the memory.
+            let mut downloads = self.downloader.spawn_all(locations);
+            while let Some((index, download)) = downloads.recv().await {
+                let content = download.map_err(ArchiveError::Download)?;
+                let path = directory.join(&archived.files[index].1);
+                write(&path, &content)
+                    .await
+                    .map_err(|error| ArchiveError::Io(path, error))?;
+                files += 1;
+                bytes += content.len() as u64;
+            }
+        }
+
+        let manifest = directory.join(ARCHIVED_MANIFEST);
+        let rewritten = rewrite(&mpd, &representations)
+            .and_then(|mpd| mpd.to_xml())
+            .map_err(ArchiveError::InvalidMPD)?;
+        write(&manifest, rewritten.as_bytes())
+            .await
+            .map_err(|error| ArchiveError::Io(manifest.clone(), error))?;
+        Ok(ArchiveReport {
+            manifest,
+            files,
+            bytes,
+        })
+    }
+}
+
+/// Files of a `Representation` and the elements giving its segments in the archive.
+struct Archived {
+    /// Indices of the period, adaptation set and representation.
+    position: (usize, usize, usize),
+    /// Remote location and path in the archive of each file.
+    files: Vec<(SegmentLocation, String)>,
+    elements: Vec<Element>,
+}
+
+/// What is known of a `Representation` to list its segments.
+struct Context {
+    base: ResolvedBaseURL,
+    /// Directory of its files, relative to the archive's.
+    directory: String,
+    id: String,
+    values: TemplateValues,
+    period_duration: Option<Duration>,
+}
+
+impl Context {
+    fn archive(&self, information: Option<SegmentInformation>) -> Result<Archived, ArchiveError> {
+        let mut archived = Archived {
+            position: (0, 0, 0),
+            files: Vec::new(),
+            elements: Vec::new(),
+        };
+        match information {
+            // The whole file is kept, the ranges of the `SegmentBase` are still valid.
+            None | Some(SegmentInformation::Base(_)) => {
+                let path = self.path(&file_name(last_segment(self.base.url().path()), "media"));
+                archived
+                    .files
+                    .push((self.base.locate("", None)?, path.clone()));
+                archived.elements.push(text_element("BaseURL", &path));
+                if let Some(SegmentInformation::Base(base)) = information {
+                    let mut element = to_element(&base)?;
+                    if let Some(initialization) = base.initialization() {
+                        if !initialization.source_url().is_empty() {
+                            let path = self.initialization(initialization, &mut archived)?;
+                            rewrite_initialization(&mut element, &path);
+                        }
+                    }
+                    archived.elements.push(element);
+                }
+            }
+            Some(SegmentInformation::List(list)) => {
+                let mut element = to_element(&list)?;
+                if let Some(initialization) = list.initialization() {
+                    let path = self.initialization(initialization, &mut archived)?;
+                    rewrite_initialization(&mut element, &path);
+                }
+                let mut paths = Vec::new();
+                for (index, segment) in list.segment_url().iter().enumerate() {
+                    let location = self
+                        .base
+                        .locate(segment.media(), range(segment.media_range())?)?;
+                    let path = self.path(&format!(
+                        "{}{}",
+                        list.start_number() + index as u64,
+                        extension(reference_path(segment.media(), location.url()))
+                    ));
+                    archived.files.push((location, path.clone()));
+                    paths.push(path);
+                }
+                let mut paths = paths.into_iter();
+                for child in elements_mut(&mut element, "SegmentURL") {
+                    if let Some(path) = paths.next() {
+                        child.set_attribute("media", &path);
+                    }
+                    remove_attributes(child, &["mediaRange", "index", "indexRange"]);
+                }
+                archived.elements.push(element);
+            }
+            Some(SegmentInformation::Template(template)) => {
+                let mut element = to_element(&template)?;
+                if !template.initialization_url().is_empty() {
+                    let local = self.template(template.initialization_url())?;
+                    let location = self
+                        .base
+                        .locate(&template.expand_initialization(&self.values)?, None)?;
+                    archived.files.push((location, local.expand(&self.values)?));
+                    element.set_attribute("initialization", local.as_str());
+                } else if let Some(initialization) = template.initialization() {
+                    let path = self.initialization(initialization, &mut archived)?;
+                    rewrite_initialization(&mut element, &path);
+                }
+                if template.media().is_empty() {
+                    return Err(ArchiveError::UnlistableSegments(self.id.clone()));
+                }
+                let local = self.template(template.media())?;
+                for (number, time) in self.numbers(&template)? {
+                    let values = self.values.clone().with_number(number).with_time(time);
+                    let location = self.base.locate(&template.expand_media(&values)?, None)?;
+                    archived.files.push((location, local.expand(&values)?));
+                }
+                element.set_attribute("media", local.as_str());
+                archived.elements.push(element);
+            }
+        }
+        for element in &mut archived.elements {
+            remove_attributes(
+                element,
+                &["xlink:href", "xlink:actuate", "index", "bitstreamSwitching"],
+            );
+            element.children.retain(|node| {
+                !matches!(node, Node::Element(child)
+                    if child.name == "RepresentationIndex" || child.name == "BitstreamSwitching")
+            });
+        }
+        // Segments sharing a file, e.g. with byte ranges, are downloaded once.
+        let mut paths = HashSet::new();
+        archived
+            .files
+            .retain(|(_, path)| paths.insert(path.clone()));
+        if let Some((_, path)) = archived.files.iter().find(|(_, path)| !contained(path)) {
+            return Err(ArchiveError::UnsafePath(path.clone()));
+        }
+        Ok(archived)
+    }
+
+    /// `$Number$` and `$Time$` of the segments of a `SegmentTemplate`.
+    fn numbers(&self, template: &SegmentTemplate) -> Result<Vec<(u64, u64)>, ArchiveError> {
+        if let Some(segments) = template.timeline_segments(self.period_duration) {
+            return Ok(segments
+                .map(|segment| (segment.number(), segment.time()))
+                .collect());
+        }
+        match self.period_duration {
+            Some(period_duration) if template.duration() > 0 => {
+                let ticks = MediaTime::from_duration(period_duration, template.timescale()).ticks();
+                let count = (ticks.max(0) as u64).div_ceil(template.duration());
+                Ok((0..count)
+                    .map(|index| {
+                        (
+                            template.start_number() + index,
+                            template.presentation_time_offset() + index * template.duration(),
+                        )
+                    })
+                    .collect())
+            }
+            _ => Err(ArchiveError::UnlistableSegments(self.id.clone())),
+        }
+    }
+
+    /// Downloads an `Initialization` to its own file, whose path is returned.
+    fn initialization(
+        &self,
+        initialization: &SegmentUrl,
+        archived: &mut Archived,
+    ) -> Result<String, ArchiveError> {
+        let location = self
+            .base
+            .locate(initialization.source_url(), range(initialization.range())?)?;
+        let path = self.path(&format!(
+            "init{}",
+            extension(reference_path(initialization.source_url(), location.url()))
+        ));
+        archived.files.push((location, path.clone()));
+        Ok(path)
+    }
+
+    /// Local counterpart of a segment template: its identifiers are kept, so the MPD
+    /// can still use it, but it is made a file name in the directory. The
+    /// `$RepresentationID$` is replaced by its file name, the id being any text.
+    fn template(&self, remote: &str) -> Result<Template, TemplateError> {
+        let remote = remote.trim();
+        let path = match remote.find("://") {
+            Some(scheme) => remote[scheme + 3..]
+                .find('/')
+                .map_or("", |host| &remote[scheme + 3 + host..]),
+            None => remote,
+        };
+        let path = path.replace("$RepresentationID$", &file_name(&self.id, "representation"));
+        let name: String = path
+            .trim_start_matches('/')
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' | '$' | '%' => c,
+                _ => '_',
+            })
+            .collect();
+        self.path(name.trim_start_matches('.')).parse()
+    }
+
+    fn path(&self, name: &str) -> String {
+        format!("{}/{}", self.directory, name)
+    }
+}
+
+pub(crate) fn range(range: &str) -> Result<Option<ByteRange>, BaseURLError> {
+    match range.trim() {
+        "" => Ok(None),
+        range => range.parse().map(Some),
+    }
+}
+
+/// Path of a segment URL, or of the URL it resolved to when it's empty.
+pub(crate) fn reference_path<'a>(reference: &'a str, url: &'a Url) -> &'a str {
+    let reference = reference
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if reference.is_empty() {
+        url.path()
+    } else {
+        reference
+    }
+}
+
+pub(crate) fn last_segment(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or_default()
+}
+
+/// Extension of the last segment of `path`, with its dot, if it looks like one.
+pub(crate) fn extension(path: &str) -> &str {
+    let name = last_segment(path);
+    match name.rfind('.') {
+        Some(dot)
+            if dot > 0
+                && name.len() - dot <= 6
+                && name[dot + 1..].chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            &name[dot..]
+        }
+        _ => "",
+    }
+}
+
+/// Whether `path`, relative to a directory, names a file in it.
+fn contained(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// `name` with the characters which aren't safe in a file name replaced, or `default`
+/// when nothing is left.
+pub(crate) fn file_name(name: &str, default: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+    match name.trim_start_matches('.') {
+        "" => default.to_string(),
+        name => name.to_string(),
+    }
+}
+
+fn to_element<T: serde::Serialize>(value: &T) -> Result<Element, ArchiveError> {
+    xml::to_element(value).map_err(ArchiveError::InvalidMPD)
+}
+
+fn text_element(name: &str, text: &str) -> Element {
+    Element {
+        name: name.to_string(),
+        attributes: Vec::new(),
+        children: vec![Node::Text(text.to_string())],
+        namespace: None,
+        position: None,
+    }
+}
+
+fn rewrite_initialization(element: &mut Element, path: &str) {
+    for initialization in elements_mut(element, "Initialization") {
+        initialization.set_attribute("sourceURL", path);
+        remove_attributes(initialization, &["range"]);
+    }
+}
+
+pub(crate) fn remove_attributes(element: &mut Element, names: &[&str]) {
+    element
+        .attributes
+        .retain(|attribute| !names.contains(&attribute.name.as_str()));
+}
+
+pub(crate) fn remove_children(element: &mut Element, names: &[&str]) {
+    element.children.retain(
+        |node| !matches!(node, Node::Element(child) if names.contains(&child.name.as_str())),
+    );
+}
+
+pub(crate) fn elements_mut<'a>(
+    element: &'a mut Element,
+    name: &'a str,
+) -> impl Iterator<Item = &'a mut Element> {
+    element
+        .children
+        .iter_mut()
+        .filter_map(move |node| match node {
+            Node::Element(child) if child.name == name => Some(child),
+            _ => None,
+        })
+}
+
+/// `mpd` with only the archived `Representation`s, which reference their files by
+/// paths relative to the MPD: every other `BaseURL` is removed.
+fn rewrite(mpd: &MPD, representations: &[Archived]) -> Result<MPD, Error> {
+    let mut root = Element::parse(&mpd.to_xml()?)?;
+    remove_children(&mut root, &["BaseURL", "Location", "PatchLocation"]);
+    for (period_index, period) in elements_mut(&mut root, "Period").enumerate() {
+        remove_children(period, &SEGMENT_ELEMENTS);
+        for (adaptation_index, adaptation) in elements_mut(period, "AdaptationSet").enumerate() {
+            remove_children(adaptation, &SEGMENT_ELEMENTS);
+            let archived: Vec<_> = representations
+                .iter()
+                .filter(|archived| {
+                    (archived.position.0, archived.position.1) == (period_index, adaptation_index)
+                })
+                .collect();
+            let mut index = 0;
+            adaptation.children.retain(|node| match node {
+                Node::Element(child) if child.name == "Representation" => {
+                    index += 1;
+                    archived
+                        .iter()
+                        .any(|archived| archived.position.2 == index - 1)
+                }
+                _ => true,
+            });
+            for (representation, archived) in
+                elements_mut(adaptation, "Representation").zip(archived)
+            {
+                remove_children(representation, &SEGMENT_ELEMENTS);
+                representation
+                    .children
+                    .extend(archived.elements.iter().cloned().map(Node::Element));
+            }
+        }
+        period.children.retain(|node| {
+            !matches!(node, Node::Element(child)
+                if child.name == "AdaptationSet" && child.elements().all(|c| c.name != "Representation"))
+        });
+    }
+    let mut source = String::new();
+    root.write(&mut source, 0);
+    source.parse()
+}
+
+/// Writes `content` to `path`, creating its missing parent directories.
+pub(crate) async fn write(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, content).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" xmlns:xlink="http://www.w3.org/1999/xlink" type="static" profiles="urn:mpeg:dash:profile:full:2011" mediaPresentationDuration="PT12S" minBufferTime="PT2S">
+  <BaseURL>media/</BaseURL>
+  <Period id="main" start="PT0S" duration="PT8S">
+    <AdaptationSet mimeType="video/mp4">
+      <SegmentTemplate media="$RepresentationID$/seg-$Number%03d$.m4s" initialization="$RepresentationID$/init.mp4" duration="4" timescale="1" startNumber="1"/>
+      <Representation id="v1" bandwidth="1000000"/>
+      <Representation id="v2" bandwidth="2000000"/>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4">
+      <Representation id="a1" bandwidth="64000">
+        <BaseURL>audio.mp4</BaseURL>
+        <SegmentList duration="4" timescale="1">
+          <Initialization range="0-9"/>
+          <SegmentURL mediaRange="10-19"/>
+          <SegmentURL mediaRange="20-29"/>
+        </SegmentList>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet mimeType="text/vtt">
+      <Representation id="t1" bandwidth="1000">
+        <BaseURL>subs.vtt</BaseURL>
+      </Representation>
+    </AdaptationSet>
+  </Period>
+  <Period xlink:href="/vod/ad.xml"/>
+</MPD>"#;
+
+    const AD: &str = r#"<Period xmlns="urn:mpeg:dash:schema:mpd:2011" id="ad" start="PT8S">
+  <AdaptationSet mimeType="video/mp4">
+    <Representation id="ad" bandwidth="500000">
+      <SegmentTemplate media="ad-$Time$.m4s" timescale="1">
+        <SegmentTimeline><S t="0" d="2" r="-1"/></SegmentTimeline>
+      </SegmentTemplate>
+    </Representation>
+  </AdaptationSet>
+</Period>"#;
+
+    const AUDIO: &str = "0123456789ABCDEFGHIJKLMNOPQRST";
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("dash-archive-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn read(directory: &Path, path: &str) -> String {
+        std::fs::read_to_string(directory.join(path)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_archive_the_chosen_representations() {
+        let (root, requests) = serve(|request| match request.path.as_str() {
+            "/vod/manifest.mpd" => Response::ok(MANIFEST),
+            "/vod/ad.xml" => Response::ok(AD),
+            "/vod/media/audio.mp4" => Response::ok(AUDIO),
+            path if path.starts_with("/vod/media/") => Response::ok(path),
+            _ => Response::not_found(),
+        })
+        .await;
+        let directory = directory("chosen");
+
+        let report = Archiver::new()
+            .with_representations(["v1", "a1", "t1", "ad"])
+            .archive(root.join("vod/manifest.mpd").unwrap().as_str(), &directory)
+            .await
+            .unwrap();
+
+        assert_eq!(report.manifest(), directory.join(ARCHIVED_MANIFEST));
+        assert_eq!(report.files(), 9);
+        assert_eq!(
+            read(&directory, "0/v1/v1_init.mp4"),
+            "/vod/media/v1/init.mp4"
+        );
+        assert_eq!(
+            read(&directory, "0/v1/v1_seg-002.m4s"),
+            "/vod/media/v1/seg-002.m4s"
+        );
+        assert_eq!(read(&directory, "0/a1/init.mp4"), "0123456789");
+        assert_eq!(read(&directory, "0/a1/2.mp4"), "KLMNOPQRST");
+        assert_eq!(read(&directory, "0/t1/subs.vtt"), "/vod/media/subs.vtt");
+        assert_eq!(read(&directory, "1/ad/ad-2.m4s"), "/vod/media/ad-2.m4s");
+        assert!(requests
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|request| !request.path.contains("v2")));
+
+        let mpd: MPD = read(&directory, ARCHIVED_MANIFEST).parse().unwrap();
+        assert!(mpd.base_url().is_empty());
+        let main = &mpd.periods()[0];
+        let video = &main.adaptations()[0];
+        assert_eq!(video.representations().len(), 1);
+        let template = video.representations()[0].segment_template().unwrap();
+        assert_eq!(template.media(), "0/v1/v1_seg-$Number%03d$.m4s");
+        assert_eq!(template.initialization_url(), "0/v1/v1_init.mp4");
+        let audio = &main.adaptations()[1].representations()[0];
+        assert!(audio.base_urls().is_empty());
+        let list = audio.segment_list().unwrap();
+        assert_eq!(list.initialization().unwrap().source_url(), "0/a1/init.mp4");
+        assert_eq!(list.initialization().unwrap().range(), "");
+        assert_eq!(list.segment_url()[1].media(), "0/a1/2.mp4");
+        assert_eq!(list.segment_url()[1].media_range(), "");
+        let text = &main.adaptations()[2].representations()[0];
+        assert_eq!(text.base_urls()[0].url(), "0/t1/subs.vtt");
+        assert_eq!(mpd.periods()[1].id(), "ad");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_drop_empty_adaptation_sets() {
+        let (root, _) = serve(|request| match request.path.as_str() {
+            "/vod/manifest.mpd" => Response::ok(MANIFEST),
+            "/vod/ad.xml" => Response::ok(AD),
+            path => Response::ok(path),
+        })
+        .await;
+        let directory = directory("empty");
+
+        let report = Archiver::new()
+            .with_representations(["t1"])
+            .archive(root.join("vod/manifest.mpd").unwrap().as_str(), &directory)
+            .await
+            .unwrap();
+
+        assert_eq!(report.files(), 1);
+        let mpd: MPD = read(&directory, ARCHIVED_MANIFEST).parse().unwrap();
+        assert_eq!(mpd.periods()[0].adaptations().len(), 1);
+        assert!(mpd.periods()[1].adaptations().is_empty());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_archive_periods_ending_with_the_presentation() {
+        const PERIODS: &str = r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" profiles="urn:mpeg:dash:profile:isoff-live:2011" mediaPresentationDuration="PT12S" minBufferTime="PT2S">
+  <Period id="first" duration="PT8S">
+    <AdaptationSet mimeType="video/mp4">
+      <SegmentTemplate media="$RepresentationID$-$Number$.m4s" duration="4" timescale="1"/>
+      <Representation id="p1" bandwidth="1000"/>
+    </AdaptationSet>
+  </Period>
+  <Period id="last">
+    <AdaptationSet mimeType="video/mp4">
+      <SegmentTemplate media="$RepresentationID$-$Number$.m4s" duration="2" timescale="1"/>
+      <Representation id="p2" bandwidth="1000"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+        let (root, _) = serve(|request| match request.path.as_str() {
+            "/manifest.mpd" => Response::ok(PERIODS),
+            path => Response::ok(path),
+        })
+        .await;
+        let directory = directory("periods");
+
+        let report = Archiver::new()
+            .archive(root.join("manifest.mpd").unwrap().as_str(), &directory)
+            .await
+            .unwrap();
+
+        assert_eq!(report.files(), 4);
+        assert_eq!(read(&directory, "0/p1/p1-2.m4s"), "/p1-2.m4s");
+        assert_eq!(read(&directory, "1/p2/p2-2.m4s"), "/p2-2.m4s");
+        assert!(!directory.join("1/p2/p2-3.m4s").exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_keep_files_in_the_directory_whatever_the_ids() {
+        const ESCAPING: &str = r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" profiles="urn:mpeg:dash:profile:isoff-live:2011" mediaPresentationDuration="PT4S" minBufferTime="PT2S">
+  <Period id="main">
+    <AdaptationSet mimeType="video/mp4">
+      <SegmentTemplate media="$RepresentationID$/$Number$.m4s" initialization="$RepresentationID$/init.mp4" duration="4" timescale="1"/>
+      <Representation id="../../escaped" bandwidth="1000"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+        let (root, _) = serve(|request| match request.path.as_str() {
+            "/manifest.mpd" => Response::ok(ESCAPING),
+            path => Response::ok(path),
+        })
+        .await;
+        let directory = directory("escaping");
+
+        let report = Archiver::new()
+            .archive(root.join("manifest.mpd").unwrap().as_str(), &directory)
+            .await
+            .unwrap();
+
+        assert_eq!(report.files(), 2);
+        assert_eq!(
+            read(&directory, "0/_.._escaped/_.._escaped_1.m4s"),
+            "/escaped/1.m4s"
+        );
+        let mpd: MPD = read(&directory, ARCHIVED_MANIFEST).parse().unwrap();
+        let representation = &mpd.periods()[0].adaptations()[0].representations()[0];
+        assert_eq!(
+            representation.segment_template().unwrap().media(),
+            "0/_.._escaped/_.._escaped_$Number$.m4s"
+        );
+        assert!(contained("0/a/1.m4s"));
+        assert!(!contained("0/a/../../../1.m4s"));
+        assert!(!contained("/tmp/1.m4s"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_refuse_dynamic_mpds() {
+        let (root, _) = serve(|_| {
+            Response::ok(
+                r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z"><Period id="1" start="PT0S"/></MPD>"#,
+            )
+        })
+        .await;
+
+        let result = Archiver::new()
+            .archive(
+                root.join("live.mpd").unwrap().as_str(),
+                &directory("dynamic"),
+            )
+            .await;
+
+        assert!(matches!(result, Err(ArchiveError::Dynamic(_))));
+    }
+}
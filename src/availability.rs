@@ -0,0 +1,393 @@
+//! Segments available at a given instant (ISO/IEC 23009-1 5.3.9.5).
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::segments::{MediaTime, SegmentInformation};
+
+/// Segment of a `Representation` with the span of time it can be requested in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvailableSegment {
+    number: u64,
+    time: u64,
+    start: MediaTime,
+    duration: MediaTime,
+    availability_start: DateTime<Utc>,
+    availability_end: Option<DateTime<Utc>>,
+}
+
+impl AvailableSegment {
+    /// Value of `$Number$`.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Media time, value of `$Time$`.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Start relative to the start of the period.
+    pub fn start(&self) -> MediaTime {
+        self.start
+    }
+
+    pub fn duration(&self) -> MediaTime {
+        self.duration
+    }
+
+    pub fn end(&self) -> MediaTime {
+        MediaTime::new(
+            self.start.ticks() + self.duration.ticks(),
+            self.start.timescale(),
+        )
+    }
+
+    pub fn availability_start(&self) -> DateTime<Utc> {
+        self.availability_start
+    }
+
+    /// `None` when the segment stays available, without `timeShiftBufferDepth`.
+    pub fn availability_end(&self) -> Option<DateTime<Utc>> {
+        self.availability_end
+    }
+}
+
+/// Segments of a `Representation` available at an instant, and where to play from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AvailabilityWindow {
+    period_start: DateTime<Utc>,
+    earliest: AvailableSegment,
+    latest: AvailableSegment,
+    latest_partial: Option<AvailableSegment>,
+    playback_start: Duration,
+}
+
+impl AvailabilityWindow {
+    /// Wall-clock time of the start of the period, to which the segment times are
+    /// relative.
+    pub fn period_start(&self) -> DateTime<Utc> {
+        self.period_start
+    }
+
+    /// First segment still in the time shift buffer.
+    pub fn earliest(&self) -> &AvailableSegment {
+        &self.earliest
+    }
+
+    /// Last segment which can be requested completely.
+    pub fn latest(&self) -> &AvailableSegment {
+        &self.latest
+    }
+
+    /// Last segment whose first chunks can be requested, when
+    /// `availabilityTimeComplete="false"` makes it later than [`latest`](Self::latest).
+    pub fn latest_partial(&self) -> Option<&AvailableSegment> {
+        self.latest_partial.as_ref()
+    }
+
+    /// End of the latest complete segment, relative to the start of the period.
+    pub fn live_edge(&self) -> Duration {
+        self.latest.end().to_duration()
+    }
+
+    /// Where to start playing, relative to the start of the period: the live edge
+    /// minus `suggestedPresentationDelay`, not before the earliest segment.
+    pub fn playback_start(&self) -> Duration {
+        self.playback_start
+    }
+}
+
+/// Timing of the MPD and period needed to compute a window.
+pub(crate) struct Presentation {
+    pub(crate) availability_start: DateTime<Utc>,
+    pub(crate) period_start: Duration,
+    pub(crate) period_duration: Option<Duration>,
+    pub(crate) time_shift_buffer_depth: Option<Duration>,
+    pub(crate) suggested_presentation_delay: Option<Duration>,
+    /// Sum of the `availabilityTimeOffset` of the `BaseURL` of each level.
+    pub(crate) base_url_availability_time_offset: f64,
+}
+
+/// Segment with times in timescale units relative to the start of the period.
+#[derive(Clone, Copy)]
+struct Timed {
+    number: u64,
+    time: u64,
+    start: i64,
+    duration: u64,
+}
+
+impl Timed {
+    fn end(&self) -> i64 {
+        self.start + self.duration as i64
+    }
+}
+
+/// Window of the segments described by `information` at `now`, `None` when the
+/// segments can't be listed or none is available yet.
+pub(crate) fn window(
+    presentation: &Presentation,
+    information: &SegmentInformation,
+    now: DateTime<Utc>,
+) -> Option<AvailabilityWindow> {
+    let timescale = information.timescale();
+    let period_start = presentation.availability_start + presentation.period_start;
+    let elapsed = now - period_start;
+    let offset = availability_time_offset(presentation, information);
+
+    // A segment is available from its end minus the offset, until its end plus the time
+    // shift buffer depth.
+    let complete_offset = if information.availability_time_complete() {
+        offset
+    } else {
+        Duration::zero()
+    };
+    let latest_end = ticks_floor(elapsed + complete_offset, timescale);
+    let partial_end = ticks_floor(elapsed + offset, timescale);
+    let earliest_end = presentation
+        .time_shift_buffer_depth
+        .map(|depth| ticks_ceil(elapsed - depth, timescale));
+    let period_end = presentation
+        .period_duration
+        .map(|duration| ticks_ceil(duration, timescale));
+
+    let (earliest, latest, partial) = match information.segment_timeline() {
+        Some(timeline) => {
+            let segments = timeline.iter(
+                timescale,
+                information.start_number(),
+                information.presentation_time_offset(),
+                period_end.map(|end| end.max(0) as u64),
+            );
+            let mut earliest = None;
+            let mut latest = None;
+            let mut partial = None;
+            for segment in segments {
+                let timed = Timed {
+                    number: segment.number(),
+                    time: segment.time(),
+                    start: segment.start(),
+                    duration: segment.duration(),
+                };
+                if timed.end() > partial_end {
+                    break;
+                }
+                if earliest.is_none() && earliest_end.is_none_or(|end| timed.end() >= end) {
+                    earliest = Some(timed);
+                }
+                if timed.end() <= latest_end {
+                    latest = Some(timed);
+                }
+                partial = Some(timed);
+            }
+            (earliest?, latest?, partial?)
+        }
+        None => {
+            let duration = information.duration();
+            if duration == 0 {
+                return None;
+            }
+            let count = period_end.map(|end| (end.max(0) as u64).div_ceil(duration));
+            let index = |end: i64| {
+                let last = (end.div_euclid(duration as i64) - 1).max(-1);
+                count.map_or(last, |count| last.min(count as i64 - 1))
+            };
+            let timed = |index: i64| Timed {
+                number: information.start_number() + index as u64,
+                time: information.presentation_time_offset() + index as u64 * duration,
+                start: index * duration as i64,
+                duration,
+            };
+            let latest = index(latest_end);
+            let partial = index(partial_end);
+            if latest < 0 {
+                return None;
+            }
+            // First segment ending at or after `end`.
+            let earliest = earliest_end.map_or(0, |end| {
+                ((end + duration as i64 - 1).div_euclid(duration as i64) - 1).clamp(0, latest)
+            });
+            (timed(earliest), timed(latest), timed(partial))
+        }
+    };
+
+    let available = |timed: Timed, offset: Duration| {
+        let end = period_start + MediaTime::new(timed.end(), timescale).to_duration();
+        AvailableSegment {
+            number: timed.number,
+            time: timed.time,
+            start: MediaTime::new(timed.start, timescale),
+            duration: MediaTime::new(timed.duration as i64, timescale),
+            availability_start: end - offset,
+            availability_end: presentation
+                .time_shift_buffer_depth
+                .map(|depth| end + depth),
+        }
+    };
+    let earliest = available(earliest, complete_offset);
+    let latest = available(latest, complete_offset);
+    let latest_partial =
+        Some(available(partial, offset)).filter(|partial| partial.number != latest.number);
+
+    let delay = presentation
+        .suggested_presentation_delay
+        .unwrap_or_else(Duration::zero);
+    let playback_start = (latest.end().to_duration() - delay).max(earliest.start().to_duration());
+    Some(AvailabilityWindow {
+        period_start,
+        earliest,
+        latest,
+        latest_partial,
+        playback_start,
+    })
+}
+
+/// `availabilityTimeOffset` of the segment information and of the `BaseURL`s, summed,
+/// as a duration. `INF` makes the whole period available, when its end is known.
+fn availability_time_offset(
+    presentation: &Presentation,
+    information: &SegmentInformation,
+) -> Duration {
+    let offset =
+        information.availability_time_offset() + presentation.base_url_availability_time_offset;
+    if offset.is_finite() {
+        Duration::nanoseconds((offset.max(0.0) * 1e9) as i64)
+    } else {
+        presentation.period_duration.unwrap_or_else(Duration::zero)
+    }
+}
+
+fn ticks_floor(duration: Duration, timescale: u64) -> i64 {
+    MediaTime::from_duration(duration, timescale).ticks()
+}
+
+fn ticks_ceil(duration: Duration, timescale: u64) -> i64 {
+    let ticks = MediaTime::from_duration(duration, timescale);
+    ticks.ticks() + i64::from(ticks.to_duration() < duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mpd::tests::bundled_manifest;
+    use crate::MPD;
+    use chrono::TimeZone;
+
+    const NUMBERED: &str = r#"<MPD type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z" timeShiftBufferDepth="PT30S" suggestedPresentationDelay="PT6S" minBufferTime="PT2S">
+    <Period id="1" start="PT10S">
+        <AdaptationSet>
+            <SegmentTemplate timescale="1000" duration="2000" startNumber="5" media="$Number$.m4s"/>
+            <Representation id="1" bandwidth="1"/>
+            <Representation id="2" bandwidth="2">
+                <SegmentTemplate availabilityTimeOffset="1.5"/>
+            </Representation>
+            <Representation id="3" bandwidth="3">
+                <SegmentTemplate availabilityTimeOffset="1.5" availabilityTimeComplete="false"/>
+            </Representation>
+            <Representation id="4" bandwidth="4">
+                <BaseURL availabilityTimeOffset="1.5">low-latency/</BaseURL>
+            </Representation>
+            <Representation id="5" bandwidth="5">
+                <BaseURL availabilityTimeOffset="1">low-latency/</BaseURL>
+                <SegmentTemplate availabilityTimeOffset="1.5"/>
+            </Representation>
+        </AdaptationSet>
+    </Period>
+</MPD>"#;
+
+    fn at(seconds: i64, millis: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+            + Duration::seconds(seconds)
+            + Duration::milliseconds(millis)
+    }
+
+    #[test]
+    fn should_compute_window_of_numbered_segments() {
+        let mpd: MPD = NUMBERED.parse().unwrap();
+        // 100.5 s after the start of the period.
+        let window = mpd.availability_window(0, 0, 0, at(110, 500)).unwrap();
+        assert_eq!(window.period_start(), at(10, 0));
+        assert_eq!(window.latest().number(), 5 + 49);
+        assert_eq!(window.latest().time(), 98000);
+        assert_eq!(window.latest().availability_start(), at(110, 0));
+        assert_eq!(window.live_edge(), Duration::seconds(100));
+        // Segments ending at or after 70.5 s are still in the buffer.
+        assert_eq!(window.earliest().number(), 5 + 35);
+        assert_eq!(window.earliest().availability_end(), Some(at(112, 0)));
+        assert_eq!(window.playback_start(), Duration::seconds(94));
+        assert_eq!(window.latest_partial(), None);
+
+        assert!(mpd.availability_window(0, 0, 0, at(11, 999)).is_none());
+        let first = mpd.availability_window(0, 0, 0, at(12, 0)).unwrap();
+        assert_eq!((first.earliest().number(), first.latest().number()), (5, 5));
+        assert_eq!(first.playback_start(), Duration::zero());
+    }
+
+    #[test]
+    fn should_apply_availability_time_offset() {
+        let mpd: MPD = NUMBERED.parse().unwrap();
+        let window = mpd.availability_window(0, 0, 1, at(110, 500)).unwrap();
+        assert_eq!(window.latest().number(), 5 + 50);
+        assert_eq!(window.latest().availability_start(), at(110, 500));
+        assert_eq!(window.latest_partial(), None);
+
+        let window = mpd.availability_window(0, 0, 2, at(110, 500)).unwrap();
+        assert_eq!(window.latest().number(), 5 + 49);
+        let partial = window.latest_partial().unwrap();
+        assert_eq!(partial.number(), 5 + 50);
+        assert_eq!(partial.availability_start(), at(110, 500));
+    }
+
+    #[test]
+    fn should_add_availability_time_offset_of_base_url() {
+        let mpd: MPD = NUMBERED.parse().unwrap();
+        let window = mpd.availability_window(0, 0, 3, at(110, 500)).unwrap();
+        assert_eq!(window.latest().number(), 5 + 50);
+        assert_eq!(window.latest().availability_start(), at(110, 500));
+
+        // 99.6 s after the start of the period, 1 s of the `BaseURL` and 1.5 s of the
+        // `SegmentTemplate` make the segment ending at 102 s available.
+        let window = mpd.availability_window(0, 0, 1, at(109, 600)).unwrap();
+        assert_eq!(window.latest().number(), 5 + 49);
+        let window = mpd.availability_window(0, 0, 4, at(109, 600)).unwrap();
+        assert_eq!(window.latest().number(), 5 + 50);
+    }
+
+    #[test]
+    fn should_compute_window_of_timeline() {
+        let mpd: MPD = r#"<MPD type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z" timeShiftBufferDepth="PT10S">
+    <Period id="1" start="PT0S">
+        <AdaptationSet>
+            <SegmentTemplate timescale="90000" presentationTimeOffset="900000" startNumber="100" media="$Time$.m4s">
+                <SegmentTimeline><S t="900000" d="180000" r="-1"/></SegmentTimeline>
+            </SegmentTemplate>
+            <Representation id="1" bandwidth="1"/>
+        </AdaptationSet>
+    </Period>
+</MPD>"#
+            .parse()
+            .unwrap();
+        let window = mpd.availability_window(0, 0, 0, at(3600, 1)).unwrap();
+        assert_eq!(window.latest().number(), 100 + 1799);
+        assert_eq!(window.latest().time(), 900000 + 1799 * 180000);
+        assert_eq!(window.live_edge(), Duration::seconds(3600));
+        assert_eq!(window.earliest().number(), 100 + 1795);
+        assert_eq!(window.playback_start(), Duration::seconds(3600));
+    }
+
+    #[test]
+    fn should_resolve_bundled_manifest_at_publish_time() {
+        let mpd = bundled_manifest();
+        let now = mpd.publish_time().unwrap().with_timezone(&Utc);
+        let window = mpd.availability_window(0, 0, 0, now).unwrap();
+        // The first segment left the time shift buffer, the last listed one is available.
+        assert_eq!(window.earliest().number(), 3059200);
+        assert_eq!(window.earliest().time(), 1516128710400 + 518400);
+        assert_eq!(window.latest().number(), 3059230);
+        assert_eq!(window.latest().time(), 1516141151997);
+        assert_eq!(
+            window.latest().availability_start(),
+            Utc.with_ymd_and_hms(2021, 11, 28, 15, 42, 36).unwrap()
+                + Duration::nanoseconds(50_777_777)
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! Resolution of the `BaseURL` elements of a MPD into absolute URLs (ISO/IEC 23009-1 5.6).
+use std::fmt;
+use std::str::FromStr;
+
+use url::Url;
+
+use crate::models::segments::BaseURL;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BaseURLError {
+    /// A URL can't be resolved against its base.
+    InvalidUrl(String, url::ParseError),
+    /// A byte range isn't of the form `<first>-<last>` or `<first>-`.
+    InvalidByteRange(String),
+    /// The `byteRange` template uses an identifier other than `$base$`, `$query$`,
+    /// `$first$` and `$last$`.
+    InvalidByteRangeTemplate(String),
+    /// No `Representation` at these indices of period, adaptation set and
+    /// representation.
+    UnknownRepresentation(usize, usize, usize),
+}
+
+impl fmt::Display for BaseURLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseURLError::InvalidUrl(url, error) => {
+                write!(f, "Invalid URL: {} > {}", error, url)
+            }
+            BaseURLError::InvalidByteRange(range) => {
+                write!(f, "Invalid byte range: {}", range)
+            }
+            BaseURLError::InvalidByteRangeTemplate(template) => {
+                write!(f, "Invalid byteRange template: {}", template)
+            }
+            BaseURLError::UnknownRepresentation(period, adaptation, representation) => write!(
+                f,
+                "No Representation {} in AdaptationSet {} of Period {}",
+                representation, adaptation, period
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BaseURLError {}
+
+/// Range of bytes, as in `mediaRange="0-863"`, the last byte included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    first: u64,
+    last: Option<u64>,
+}
+
+impl ByteRange {
+    pub fn new(first: u64, last: Option<u64>) -> Self {
+        ByteRange { first, last }
+    }
+
+    pub fn first(&self) -> u64 {
+        self.first
+    }
+
+    /// `None` up to the end of the resource.
+    pub fn last(&self) -> Option<u64> {
+        self.last
+    }
+
+    /// Value of a HTTP `Range` header requesting these bytes.
+    pub fn to_header(&self) -> String {
+        format!("bytes={}", self)
+    }
+}
+
+impl FromStr for ByteRange {
+    type Err = BaseURLError;
+
+    fn from_str(source: &str) -> Result<Self, BaseURLError> {
+        let invalid = || BaseURLError::InvalidByteRange(source.to_string());
+        let (first, last) = source.trim().split_once('-').ok_or_else(invalid)?;
+        let first = first.parse().map_err(|_| invalid())?;
+        let last = match last {
+            "" => None,
+            last => Some(last.parse().map_err(|_| invalid())?),
+        };
+        if last.is_some_and(|last| last < first) {
+            return Err(invalid());
+        }
+        Ok(ByteRange { first, last })
+    }
+}
+
+impl fmt::Display for ByteRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.last {
+            Some(last) => write!(f, "{}-{}", self.first, last),
+            None => write!(f, "{}-", self.first),
+        }
+    }
+}
+
+/// Absolute URL of a resource, with the bytes to request in a `Range` header when the
+/// `BaseURL` doesn't carry them in the URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentLocation {
+    url: Url,
+    range: Option<ByteRange>,
+}
+
+impl SegmentLocation {
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn range(&self) -> Option<ByteRange> {
+        self.range
+    }
+}
+
+/// Absolute base URL obtained by resolving one `BaseURL` of each level against the
+/// level above, down from the URL of the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedBaseURL {
+    url: Url,
+    service_location: String,
+    byte_range: String,
+    /// `BaseURL` elements used, from the `MPD` level down.
+    chain: Vec<BaseURL>,
+}
+
+impl ResolvedBaseURL {
+    pub fn new(url: Url) -> Self {
+        ResolvedBaseURL {
+            url,
+            service_location: String::new(),
+            byte_range: String::new(),
+            chain: Vec::new(),
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// `serviceLocation` of the lowest `BaseURL` giving one.
+    pub fn service_location(&self) -> &str {
+        &self.service_location
+    }
+
+    /// `byteRange` template of the lowest `BaseURL` giving one.
+    pub fn byte_range(&self) -> &str {
+        &self.byte_range
+    }
+
+    pub fn chain(&self) -> &[BaseURL] {
+        &self.chain
+    }
+
+    /// `dvb:priority` of the lowest `BaseURL` giving one, 1 by default.
+    pub fn priority(&self) -> u32 {
+        self.chain
+            .iter()
+            .rev()
+            .find_map(BaseURL::priority)
+            .unwrap_or(1)
+    }
+
+    /// `dvb:weight` of the lowest `BaseURL` giving one, 1 by default.
+    pub fn weight(&self) -> u32 {
+        self.chain
+            .iter()
+            .rev()
+            .find_map(BaseURL::weight)
+            .unwrap_or(1)
+    }
+
+    /// Resolves `base_url` against `self`, as RFC 3986 does.
+    pub fn join(&self, base_url: &BaseURL) -> Result<ResolvedBaseURL, BaseURLError> {
+        let mut chain = self.chain.clone();
+        chain.push(base_url.clone());
+        Ok(ResolvedBaseURL {
+            url: join(&self.url, base_url.url())?,
+            service_location: inherit(base_url.service_location(), &self.service_location),
+            byte_range: inherit(base_url.byte_range(), &self.byte_range),
+            chain,
+        })
+    }
+
+    /// Location of `reference`, a segment URL from the segment information, or of the
+    /// base URL itself when it is empty. With a `byteRange` template, `range` is
+    /// written in the URL.
+    pub fn locate(
+        &self,
+        reference: &str,
+        range: Option<ByteRange>,
+    ) -> Result<SegmentLocation, BaseURLError> {
+        let url = join(&self.url, reference)?;
+        match range {
+            Some(range) if !self.byte_range.is_empty() => Ok(SegmentLocation {
+                url: expand_byte_range(&self.byte_range, &url, range)?,
+                range: None,
+            }),
+            range => Ok(SegmentLocation { url, range }),
+        }
+    }
+}
+
+fn inherit(value: &str, parent: &str) -> String {
+    if value.is_empty() { parent } else { value }.to_string()
+}
+
+fn join(base: &Url, reference: &str) -> Result<Url, BaseURLError> {
+    let reference = reference.trim();
+    base.join(reference)
+        .map_err(|error| BaseURLError::InvalidUrl(reference.to_string(), error))
+}
+
+/// Every combination of the `BaseURL` elements of `levels`, resolved from `manifest`
+/// down; a level without any is skipped. Combinations giving the same URL are kept
+/// once, in document order.
+pub(crate) fn resolve(
+    manifest: &Url,
+    levels: &[&[BaseURL]],
+) -> Result<Vec<ResolvedBaseURL>, BaseURLError> {
+    let mut resolved = vec![ResolvedBaseURL::new(manifest.clone())];
+    for base_urls in levels.iter().filter(|base_urls| !base_urls.is_empty()) {
+        let mut next: Vec<ResolvedBaseURL> = Vec::new();
+        for parent in &resolved {
+            for base_url in base_urls.iter() {
+                let candidate = parent.join(base_url)?;
+                if next.iter().all(|known| known.url != candidate.url) {
+                    next.push(candidate);
+                }
+            }
+        }
+        resolved = next;
+    }
+    Ok(resolved)
+}
+
+/// Substitutes `$base$`, `$query$`, `$first$` and `$last$` in a `byteRange` template.
+/// Without query, `$query$` is removed with the `?` or `&` joining it to the URL.
+fn expand_byte_range(template: &str, url: &Url, range: ByteRange) -> Result<Url, BaseURLError> {
+    let invalid = || BaseURLError::InvalidByteRangeTemplate(template.to_string());
+    let mut base = url.clone();
+    base.set_query(None);
+    let query = url.query().unwrap_or_default();
+    let mut source = template.to_string();
+    if query.is_empty() {
+        for joined in ["$query$&", "?$query$", "&$query$"] {
+            source = source.replace(joined, "");
+        }
+    }
+
+    let mut expanded = String::with_capacity(source.len() + base.as_str().len());
+    let mut parts = source.split('$');
+    expanded.push_str(parts.next().unwrap_or_default());
+    while let Some(identifier) = parts.next() {
+        match identifier {
+            "" => expanded.push('$'),
+            "base" => expanded.push_str(base.as_str()),
+            "query" => expanded.push_str(query),
+            "first" => expanded.push_str(&range.first.to_string()),
+            "last" => {
+                if let Some(last) = range.last {
+                    expanded.push_str(&last.to_string())
+                }
+            }
+            _ => return Err(invalid()),
+        }
+        expanded.push_str(parts.next().ok_or_else(invalid)?);
+    }
+    Url::parse(&expanded).map_err(|error| BaseURLError::InvalidUrl(expanded, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MPD;
+
+    const SOURCE: &str = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M">
+    <BaseURL serviceLocation="a">https://cdn-a.example.com/content/</BaseURL>
+    <BaseURL serviceLocation="b">https://cdn-b.example.com/vod/content/</BaseURL>
+    <Period id="1">
+        <BaseURL>period-1/</BaseURL>
+        <AdaptationSet>
+            <BaseURL byteRange="$base$?$query$&amp;range=$first$-$last$">../video/</BaseURL>
+            <Representation id="1" bandwidth="1">
+                <BaseURL>720p.mp4?token=abc</BaseURL>
+            </Representation>
+            <Representation id="2" bandwidth="2">
+                <BaseURL>https://origin.example.com/360p.mp4</BaseURL>
+            </Representation>
+            <Representation id="3" bandwidth="3"/>
+        </AdaptationSet>
+    </Period>
+</MPD>"#;
+
+    fn resolved(representation: usize) -> Vec<ResolvedBaseURL> {
+        let mpd: MPD = SOURCE.parse().unwrap();
+        let period = &mpd.periods()[0];
+        let adaptation = &period.adaptations()[0];
+        mpd.resolve_base_urls(
+            &Url::parse("https://manifests.example.com/live/manifest.mpd").unwrap(),
+            period,
+            adaptation,
+            &adaptation.representations()[representation],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_compose_base_urls_of_every_level() {
+        let resolved = resolved(0);
+        let urls: Vec<&str> = resolved.iter().map(|base| base.url().as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://cdn-a.example.com/content/video/720p.mp4?token=abc",
+                "https://cdn-b.example.com/vod/content/video/720p.mp4?token=abc",
+            ]
+        );
+        assert_eq!(resolved[1].service_location(), "b");
+        assert_eq!(resolved[1].chain().len(), 4);
+
+        let absolute = self::resolved(1);
+        assert_eq!(absolute.len(), 1);
+        assert_eq!(
+            absolute[0].url().as_str(),
+            "https://origin.example.com/360p.mp4"
+        );
+        assert_eq!(
+            self::resolved(2)[0].url().as_str(),
+            "https://cdn-a.example.com/content/video/"
+        );
+    }
+
+    #[test]
+    fn should_resolve_against_manifest_url_without_base_url() {
+        let manifest = Url::parse("https://example.com/a/b/manifest.mpd?session=1").unwrap();
+        let resolved = resolve(&manifest, &[&[], &[]]).unwrap();
+        let location = resolved[0].locate("../seg/1.m4s", None).unwrap();
+        assert_eq!(location.url().as_str(), "https://example.com/a/seg/1.m4s");
+        assert_eq!(
+            resolved[0].locate("", None).unwrap().url().as_str(),
+            "https://example.com/a/b/manifest.mpd?session=1"
+        );
+    }
+
+    #[test]
+    fn should_write_byte_range_in_url_with_template() {
+        let resolved = resolved(0);
+        let range: ByteRange = "800-1199".parse().unwrap();
+        let location = resolved[0].locate("", Some(range)).unwrap();
+        assert_eq!(
+            location.url().as_str(),
+            "https://cdn-a.example.com/content/video/720p.mp4?token=abc&range=800-1199"
+        );
+        assert_eq!(location.range(), None);
+
+        let location = self::resolved(2)[0]
+            .locate("init.mp4", Some(ByteRange::new(0, None)))
+            .unwrap();
+        assert_eq!(
+            location.url().as_str(),
+            "https://cdn-a.example.com/content/video/init.mp4?range=0-"
+        );
+    }
+
+    #[test]
+    fn should_keep_byte_range_for_header_without_template() {
+        let manifest = Url::parse("https://example.com/manifest.mpd").unwrap();
+        let resolved = resolve(&manifest, &[]).unwrap();
+        let location = resolved[0]
+            .locate("video.mp4", Some("0-863".parse().unwrap()))
+            .unwrap();
+        assert_eq!(location.url().as_str(), "https://example.com/video.mp4");
+        assert_eq!(location.range().unwrap().to_header(), "bytes=0-863");
+    }
+
+    #[test]
+    fn should_fail_on_invalid_ranges_and_templates() {
+        assert_eq!(
+            "863-0".parse::<ByteRange>(),
+            Err(BaseURLError::InvalidByteRange("863-0".to_string()))
+        );
+        assert!("abc".parse::<ByteRange>().is_err());
+        let url = Url::parse("https://example.com/video.mp4").unwrap();
+        assert_eq!(
+            expand_byte_range("$base$?r=$start$", &url, ByteRange::new(0, Some(1))),
+            Err(BaseURLError::InvalidByteRangeTemplate(
+                "$base$?r=$start$".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Synchronisation of the local clock with the `UTCTiming` sources of a MPD
+//! (ISO/IEC 23009-1 5.8.5.7).
+use std::fmt;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use reqwest::Client;
+use url::Url;
+
+use crate::models::utils::Descriptor;
+
+const HTTP_XSDATE: &str = "urn:mpeg:dash:utc:http-xsdate:2014";
+const HTTP_ISO: &str = "urn:mpeg:dash:utc:http-iso:2014";
+const HTTP_NTP: &str = "urn:mpeg:dash:utc:http-ntp:2014";
+const HTTP_HEAD: &str = "urn:mpeg:dash:utc:http-head:2014";
+const DIRECT: &str = "urn:mpeg:dash:utc:direct:2014";
+
+/// Seconds from the NTP epoch, 1900-01-01, to the Unix one.
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClockSyncError {
+    /// The MPD has no `UTCTiming` with a supported scheme.
+    NoTiming,
+    UnsupportedScheme(String),
+    InvalidUrl(String),
+    /// The request to a time server failed.
+    Request(String, String),
+    /// The time given by a source can't be read.
+    InvalidTime(String, String),
+}
+
+impl fmt::Display for ClockSyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClockSyncError::NoTiming => write!(f, "No supported UTCTiming to synchronise with"),
+            ClockSyncError::UnsupportedScheme(scheme) => {
+                write!(f, "Unsupported UTCTiming scheme: {}", scheme)
+            }
+            ClockSyncError::InvalidUrl(url) => write!(f, "Invalid time server URL: {}", url),
+            ClockSyncError::Request(url, error) => {
+                write!(f, "Can't get time from {}: {}", url, error)
+            }
+            ClockSyncError::InvalidTime(source, time) => {
+                write!(f, "Invalid time from {}: {}", source, time)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClockSyncError {}
+
+/// Measures the offset between the local clock and the time sources of a MPD.
+///
+/// The offset is what to add to the local time to get the server time: pass
+/// `Utc::now() + offset` to [`MPD::availability_window`](crate::MPD::availability_window).
+#[derive(Debug, Clone, Default)]
+pub struct ClockSync {
+    client: Client,
+    manifest_url: Option<Url>,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        ClockSync::default()
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// URL the relative time server URLs are resolved against.
+    pub fn with_manifest_url(mut self, manifest_url: Url) -> Self {
+        self.manifest_url = Some(manifest_url);
+        self
+    }
+
+    /// Offset given by the first of `timings` which answers, in the order of the MPD.
+    pub async fn synchronise(&self, timings: &[Descriptor]) -> Result<Duration, ClockSyncError> {
+        let mut last_error = ClockSyncError::NoTiming;
+        for timing in timings {
+            match self.offset(timing).await {
+                Ok(offset) => return Ok(offset),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Offset given by `timing`, trying each of the whitespace separated URLs of its
+    /// value. A `direct` timing is compared with the current time, so it should be
+    /// used right after the MPD is fetched.
+    pub async fn offset(&self, timing: &Descriptor) -> Result<Duration, ClockSyncError> {
+        let scheme = timing.scheme_id_uri();
+        if scheme == DIRECT {
+            return Ok(parse_date_time(DIRECT, timing.value())? - Utc::now());
+        }
+        if ![HTTP_XSDATE, HTTP_ISO, HTTP_NTP, HTTP_HEAD].contains(&scheme) {
+            return Err(ClockSyncError::UnsupportedScheme(scheme.to_string()));
+        }
+
+        let mut last_error = ClockSyncError::NoTiming;
+        for url in timing.value().split_whitespace() {
+            match self.request(scheme, url).await {
+                Ok(offset) => return Ok(offset),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn request(&self, scheme: &str, url: &str) -> Result<Duration, ClockSyncError> {
+        let url = match &self.manifest_url {
+            Some(manifest_url) => manifest_url.join(url),
+            None => Url::parse(url),
+        }
+        .map_err(|_| ClockSyncError::InvalidUrl(url.to_string()))?;
+        let failed =
+            |error: reqwest::Error| ClockSyncError::Request(url.to_string(), error.to_string());
+
+        let sent = Utc::now();
+        let request = if scheme == HTTP_HEAD {
+            self.client.head(url.clone())
+        } else {
+            self.client.get(url.clone())
+        };
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(failed)?;
+        let server_time = match scheme {
+            HTTP_HEAD => {
+                let date = response
+                    .headers()
+                    .get(reqwest::header::DATE)
+                    .and_then(|date| date.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                DateTime::parse_from_rfc2822(&date)
+                    .map(|date| date.with_timezone(&Utc))
+                    .map_err(|_| ClockSyncError::InvalidTime(url.to_string(), date))?
+            }
+            HTTP_NTP => parse_ntp(url.as_str(), &response.bytes().await.map_err(failed)?)?,
+            _ => parse_date_time(url.as_str(), &response.text().await.map_err(failed)?)?,
+        };
+        let received = Utc::now();
+        // The server time is taken halfway through the exchange.
+        Ok(server_time - (sent + (received - sent) / 2))
+    }
+}
+
+/// Reads a `xs:dateTime` or ISO 8601 time, in UTC when it has no time zone.
+fn parse_date_time(source: &str, time: &str) -> Result<DateTime<Utc>, ClockSyncError> {
+    let time = time.trim();
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|time| DateTime::from_naive_utc_and_offset(time, Utc))
+        })
+        .map_err(|_| ClockSyncError::InvalidTime(source.to_string(), time.to_string()))
+}
+
+/// Reads a 64 bits NTP timestamp: seconds since 1900 then the fraction of a second.
+fn parse_ntp(source: &str, bytes: &[u8]) -> Result<DateTime<Utc>, ClockSyncError> {
+    let invalid = || ClockSyncError::InvalidTime(source.to_string(), format!("{:02x?}", bytes));
+    if bytes.len() != 8 {
+        return Err(invalid());
+    }
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as i64;
+    let nanos = (fraction * 1_000_000_000) >> 32;
+    DateTime::from_timestamp(seconds - NTP_UNIX_OFFSET, nanos as u32).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+    use crate::MPD;
+    use chrono::TimeZone;
+
+    /// Time given by the stand-in server, an hour ahead of the local clock.
+    fn server_time() -> DateTime<Utc> {
+        Utc::now() + Duration::hours(1)
+    }
+
+    /// Local HTTP server answering `/xsdate`, `/iso`, `/ntp` and `/head`, any other path
+    /// with a 404.
+    async fn time_server() -> Url {
+        let (url, _) = serve(|request| {
+            let now = server_time();
+            match request.path.as_str() {
+                "/xsdate" => Response::ok(now.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()),
+                "/iso" => Response::ok(now.format("%Y-%m-%dT%H:%M:%S%.3f").to_string()),
+                "/ntp" => {
+                    let seconds = (now.timestamp() + NTP_UNIX_OFFSET) as u32;
+                    let fraction = ((now.timestamp_subsec_nanos() as u64) << 32) / 1_000_000_000;
+                    let mut body = seconds.to_be_bytes().to_vec();
+                    body.extend_from_slice(&(fraction as u32).to_be_bytes());
+                    Response::ok(body)
+                }
+                "/head" => Response::ok("")
+                    .with_header("Date", &now.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+                _ => Response::not_found(),
+            }
+        })
+        .await;
+        url
+    }
+
+    fn timings(base: &Url, elements: &str) -> Vec<Descriptor> {
+        let mpd: MPD = format!(
+            r#"<MPD type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z"><Period id="1" start="PT0S"/>{}</MPD>"#,
+            elements.replace("{base}", base.as_str())
+        )
+        .parse()
+        .unwrap();
+        mpd.utc_timing().to_vec()
+    }
+
+    fn assert_about_an_hour(offset: Duration) {
+        let error = (offset - Duration::hours(1)).num_milliseconds().abs();
+        assert!(error < 1500, "offset {} is not about an hour", offset);
+    }
+
+    #[tokio::test]
+    async fn should_measure_offset_with_every_http_scheme() {
+        let base = time_server().await;
+        let timings = timings(
+            &base,
+            r#"<UTCTiming schemeIdUri="urn:mpeg:dash:utc:http-xsdate:2014" value="{base}xsdate"/>
+<UTCTiming schemeIdUri="urn:mpeg:dash:utc:http-iso:2014" value="{base}iso"/>
+<UTCTiming schemeIdUri="urn:mpeg:dash:utc:http-ntp:2014" value="{base}ntp"/>
+<UTCTiming schemeIdUri="urn:mpeg:dash:utc:http-head:2014" value="{base}head"/>"#,
+        );
+        let clock = ClockSync::new();
+        for timing in &timings {
+            assert_about_an_hour(clock.offset(timing).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_next_url_and_timing() {
+        let base = time_server().await;
+        let timings = timings(
+            &base,
+            r#"<UTCTiming schemeIdUri="urn:mpeg:dash:utc:ntp:2014" value="pool.ntp.org"/>
+<UTCTiming schemeIdUri="urn:mpeg:dash:utc:http-xsdate:2014" value="missing xsdate"/>"#,
+        );
+        let clock = ClockSync::new().with_manifest_url(base.join("live/manifest.mpd").unwrap());
+        assert_eq!(
+            clock.offset(&timings[0]).await,
+            Err(ClockSyncError::UnsupportedScheme(
+                "urn:mpeg:dash:utc:ntp:2014".to_string()
+            ))
+        );
+        // `/live/missing` answers 404, `/live/xsdate` as well: the relative URLs resolve
+        // against the manifest.
+        assert!(matches!(
+            clock.offset(&timings[1]).await,
+            Err(ClockSyncError::Request(_, _))
+        ));
+        let clock = ClockSync::new().with_manifest_url(base.clone());
+        assert_about_an_hour(clock.synchronise(&timings).await.unwrap());
+        assert_eq!(
+            ClockSync::new().synchronise(&[]).await,
+            Err(ClockSyncError::NoTiming)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_read_direct_time() {
+        let time = server_time().format("%Y-%m-%dT%H:%M:%S%.3fZ");
+        let timings = timings(
+            &Url::parse("http://unused/").unwrap(),
+            &format!(
+                r#"<UTCTiming schemeIdUri="urn:mpeg:dash:utc:direct:2014" value="{}"/>"#,
+                time
+            ),
+        );
+        assert_about_an_hour(ClockSync::new().offset(&timings[0]).await.unwrap());
+    }
+
+    #[test]
+    fn should_parse_time_formats() {
+        let expected =
+            Utc.with_ymd_and_hms(2021, 11, 28, 15, 42, 44).unwrap() + Duration::milliseconds(500);
+        assert_eq!(
+            parse_date_time("test", "2021-11-28T15:42:44.5Z\n").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_date_time("test", "2021-11-28T16:42:44.500+01:00").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_date_time("test", "2021-11-28T15:42:44.5").unwrap(),
+            expected
+        );
+        assert!(parse_date_time("test", "yesterday").is_err());
+
+        let seconds = (expected.timestamp() + NTP_UNIX_OFFSET) as u32;
+        let mut ntp = seconds.to_be_bytes().to_vec();
+        ntp.extend_from_slice(&0x8000_0000u32.to_be_bytes());
+        assert_eq!(parse_ntp("test", &ntp).unwrap(), expected);
+        assert!(parse_ntp("test", &ntp[..4]).is_err());
+    }
+}
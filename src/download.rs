@@ -0,0 +1,455 @@
+//! Download of media segments, with bounded concurrency, retries and byte ranges.
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::{header, Client, StatusCode};
+use tokio::sync::{mpsc, Semaphore};
+use url::Url;
+
+use crate::base_url::{ByteRange, SegmentLocation};
+use crate::fetch::MPDFetcher;
+
+#[derive(Debug)]
+pub enum DownloadError {
+    Request(String, reqwest::Error),
+    /// The server answered with an error status, after the retries for a 404 or a 5xx.
+    Status(String, u16),
+    /// The server ignored the `Range` header and sent fewer bytes than requested.
+    InvalidRange(String, ByteRange),
+}
+
+impl DownloadError {
+    /// Whether trying again may succeed: a segment not yet published near the live
+    /// edge, a server error, or a network failure.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            DownloadError::Request(_, error) => {
+                error.is_timeout() || error.is_connect() || error.is_body()
+            }
+            DownloadError::Status(_, status) => *status == 404 || *status >= 500,
+            DownloadError::InvalidRange(_, _) => false,
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::Request(url, error) => write!(f, "Can't download {}: {}", url, error),
+            DownloadError::Status(url, status) => {
+                write!(f, "Can't download {}: HTTP status {}", url, status)
+            }
+            DownloadError::InvalidRange(url, range) => {
+                write!(f, "Can't download bytes {} of {}", range, url)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DownloadError::Request(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Step of the download of a segment, reported to [`SegmentDownloader::with_progress`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DownloadProgress {
+    Started {
+        url: Url,
+    },
+    /// Bytes received so far, out of the length announced by the server.
+    Received {
+        url: Url,
+        received: u64,
+        total: Option<u64>,
+    },
+    /// The attempt failed with a transient error, the next one starts after `delay`.
+    Retrying {
+        url: Url,
+        attempt: u32,
+        delay: Duration,
+        reason: String,
+    },
+    Finished {
+        url: Url,
+        size: u64,
+    },
+    Failed {
+        url: Url,
+        reason: String,
+    },
+}
+
+type ProgressCallback = Arc<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+/// Downloads segments from their [`SegmentLocation`], requesting their byte range when
+/// they have one.
+///
+/// Transient failures are retried with an exponential backoff: near the live edge a
+/// segment may be requested a bit before the server publishes it.
+#[derive(Clone)]
+pub struct SegmentDownloader {
+    client: Client,
+    concurrency: usize,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    progress: Option<ProgressCallback>,
+}
+
+impl fmt::Debug for SegmentDownloader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SegmentDownloader")
+            .field("client", &self.client)
+            .field("concurrency", &self.concurrency)
+            .field("max_retries", &self.max_retries)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl Default for SegmentDownloader {
+    fn default() -> Self {
+        SegmentDownloader {
+            client: MPDFetcher::new().client().clone(),
+            concurrency: 4,
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            progress: None,
+        }
+    }
+}
+
+impl SegmentDownloader {
+    pub fn new() -> Self {
+        SegmentDownloader::default()
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Segments downloaded at the same time by [`download_all`](Self::download_all)
+    /// and [`spawn_all`](Self::spawn_all), 4 by default.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Attempts after the first one for a transient failure, 3 by default.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Delay before the first retry, doubled for each next one up to `max`; 500ms and
+    /// 8s by default.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(&DownloadProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Delay before the retry following the attempt `attempt`, from 0.
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+
+    fn report(&self, progress: DownloadProgress) {
+        if let Some(callback) = &self.progress {
+            callback(&progress);
+        }
+    }
+
+    pub async fn download(&self, location: &SegmentLocation) -> Result<Vec<u8>, DownloadError> {
+        let url = location.url().clone();
+        self.report(DownloadProgress::Started { url: url.clone() });
+        let mut attempt = 0;
+        loop {
+            match self.attempt(location).await {
+                Ok(bytes) => {
+                    self.report(DownloadProgress::Finished {
+                        url,
+                        size: bytes.len() as u64,
+                    });
+                    return Ok(bytes);
+                }
+                Err(error) if error.is_transient() && attempt < self.max_retries => {
+                    let delay = self.backoff(attempt);
+                    attempt += 1;
+                    self.report(DownloadProgress::Retrying {
+                        url: url.clone(),
+                        attempt,
+                        delay,
+                        reason: error.to_string(),
+                    });
+                    tokio::time::sleep(delay).await;
+                }
+                Err(error) => {
+                    self.report(DownloadProgress::Failed {
+                        url,
+                        reason: error.to_string(),
+                    });
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    async fn attempt(&self, location: &SegmentLocation) -> Result<Vec<u8>, DownloadError> {
+        let url = location.url();
+        let failed = |error| DownloadError::Request(url.to_string(), error);
+        let mut request = self.client.get(url.clone());
+        if let Some(range) = location.range() {
+            request = request.header(header::RANGE, range.to_header());
+        }
+        let mut response = request.send().await.map_err(failed)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DownloadError::Status(url.to_string(), status.as_u16()));
+        }
+
+        let total = response.content_length();
+        // The announced length is only a hint, a bogus one mustn't reserve gigabytes.
+        let mut bytes = Vec::with_capacity(total.map_or(0, |total| total.min(8 << 20)) as usize);
+        while let Some(chunk) = response.chunk().await.map_err(failed)? {
+            bytes.extend_from_slice(&chunk);
+            self.report(DownloadProgress::Received {
+                url: url.clone(),
+                received: bytes.len() as u64,
+                total,
+            });
+        }
+
+        match location.range() {
+            // The server sent the whole resource: keep the requested bytes.
+            Some(range) if status != StatusCode::PARTIAL_CONTENT => {
+                let first = range.first() as usize;
+                let end = range.last().map_or(bytes.len(), |last| last as usize + 1);
+                if end > bytes.len() || first >= end {
+                    return Err(DownloadError::InvalidRange(url.to_string(), range));
+                }
+                bytes.truncate(end);
+                bytes.drain(..first);
+                Ok(bytes)
+            }
+            _ => Ok(bytes),
+        }
+    }
+
+    /// Downloads `locations`, e.g. the segments of a `Representation`, a bounded number
+    /// at a time. The results are in the order of `locations`.
+    pub async fn download_all(
+        &self,
+        locations: &[SegmentLocation],
+    ) -> Vec<Result<Vec<u8>, DownloadError>> {
+        let mut results: Vec<_> = locations.iter().map(|_| None).collect();
+        let mut completed = self.spawn_all(locations.to_vec());
+        while let Some((index, result)) = completed.recv().await {
+            results[index] = Some(result);
+        }
+        results
+            .into_iter()
+            .map(|result| result.expect("every download reports its result"))
+            .collect()
+    }
+
+    /// Downloads `locations` a bounded number at a time in new tasks, and sends each
+    /// result with the index of its location as soon as it completes.
+    pub fn spawn_all(
+        &self,
+        locations: Vec<SegmentLocation>,
+    ) -> mpsc::Receiver<(usize, Result<Vec<u8>, DownloadError>)> {
+        let (sender, receiver) = mpsc::channel(self.concurrency);
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+        for (index, location) in locations.into_iter().enumerate() {
+            let downloader = self.clone();
+            let sender = sender.clone();
+            let permits = permits.clone();
+            tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let result = downloader.download(&location).await;
+                let _ = sender.send((index, result)).await;
+            });
+        }
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base_url::ResolvedBaseURL;
+    use crate::test_server::{serve, Response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    /// Answers a `Range` header with a 206 on `/ranged`, and ignores it elsewhere.
+    fn ranged(request: &crate::test_server::Request) -> Response {
+        let range = request
+            .header("Range")
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.parse::<ByteRange>().ok());
+        match (request.path.as_str(), range) {
+            ("/ranged", Some(range)) => {
+                let last = range.last().unwrap_or(CONTENT.len() as u64 - 1) as usize;
+                Response::new(206, &CONTENT[range.first() as usize..=last])
+            }
+            ("/ranged" | "/full", _) => Response::ok(CONTENT),
+            (path, _) if path.starts_with("/full?bytes=") => {
+                let range: ByteRange = path["/full?bytes=".len()..].parse().unwrap();
+                Response::ok(&CONTENT[range.first() as usize..=range.last().unwrap() as usize])
+            }
+            _ => Response::not_found(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_request_byte_ranges() {
+        let (root, requests) = serve(ranged).await;
+        let base = ResolvedBaseURL::new(root.clone());
+        let locations = vec![
+            base.locate("ranged", Some("2-5".parse().unwrap())).unwrap(),
+            base.locate("full", Some("6-".parse().unwrap())).unwrap(),
+            base.locate("full", None).unwrap(),
+        ];
+        let results = SegmentDownloader::new().download_all(&locations).await;
+        let bodies: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            bodies,
+            vec![b"2345".to_vec(), b"6789".to_vec(), CONTENT.to_vec()]
+        );
+        let ranges: Vec<_> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|request| request.header("Range").map(str::to_string))
+            .collect();
+        assert_eq!(ranges.len(), 2);
+
+        let templated: ResolvedBaseURL = {
+            let mpd: crate::MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1S">
+                <BaseURL byteRange="$base$?bytes=$first$-$last$">full</BaseURL><Period/></MPD>"#
+                .parse()
+                .unwrap();
+            base.join(&mpd.base_url()[0]).unwrap()
+        };
+        let location = templated.locate("", Some("1-3".parse().unwrap())).unwrap();
+        assert_eq!(location.range(), None);
+        assert_eq!(
+            SegmentDownloader::new().download(&location).await.unwrap(),
+            b"123".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_retry_transient_failures_with_backoff() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counted = attempts.clone();
+        let (root, _) = serve(move |request| match request.path.as_str() {
+            "/live/42.m4s" if counted.fetch_add(1, Ordering::SeqCst) < 2 => Response::not_found(),
+            "/live/42.m4s" => Response::ok(CONTENT),
+            "/forbidden.m4s" => Response::new(403, ""),
+            _ => Response::new(503, ""),
+        })
+        .await;
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let reported = progress.clone();
+        let downloader = SegmentDownloader::new()
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(2))
+            .with_progress(move |progress| reported.lock().unwrap().push(progress.clone()));
+        let base = ResolvedBaseURL::new(root);
+
+        let bytes = downloader
+            .download(&base.locate("live/42.m4s", None).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(bytes, CONTENT);
+        let delays: Vec<_> = progress
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|progress| match progress {
+                DownloadProgress::Retrying { attempt, delay, .. } => Some((*attempt, *delay)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            delays,
+            vec![(1, Duration::from_millis(1)), (2, Duration::from_millis(2))]
+        );
+        assert!(matches!(
+            progress.lock().unwrap().last(),
+            Some(DownloadProgress::Finished { size: 10, .. })
+        ));
+
+        progress.lock().unwrap().clear();
+        let error = downloader
+            .download(&base.locate("forbidden.m4s", None).unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DownloadError::Status(_, 403)));
+        assert_eq!(progress.lock().unwrap().len(), 2);
+
+        let error = downloader
+            .with_max_retries(1)
+            .download(&base.locate("down.m4s", None).unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DownloadError::Status(_, 503)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn should_bound_parallel_downloads() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let highest = Arc::new(AtomicUsize::new(0));
+        let (counted, observed) = (running.clone(), highest.clone());
+        let (root, _) = serve(move |request| {
+            let now = counted.fetch_add(1, Ordering::SeqCst) + 1;
+            observed.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(30));
+            counted.fetch_sub(1, Ordering::SeqCst);
+            Response::ok(request.path.clone())
+        })
+        .await;
+        let base = ResolvedBaseURL::new(root);
+        let locations: Vec<_> = (1..=6)
+            .map(|number| base.locate(&format!("{}.m4s", number), None).unwrap())
+            .collect();
+        let results = SegmentDownloader::new()
+            .with_concurrency(2)
+            .download_all(&locations)
+            .await;
+        let bodies: Vec<_> = results
+            .into_iter()
+            .map(|result| String::from_utf8(result.unwrap()).unwrap())
+            .collect();
+        assert_eq!(
+            bodies,
+            vec!["/1.m4s", "/2.m4s", "/3.m4s", "/4.m4s", "/5.m4s", "/6.m4s"]
+        );
+        assert_eq!(highest.load(Ordering::SeqCst), 2);
+    }
+}
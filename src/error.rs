@@ -0,0 +1,271 @@
+use serde::de;
+use std::fmt;
+
+use crate::models::mpd::MPDError;
+use crate::serde_custom;
+
+/// Location of a node in the parsed document, both 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    pub(crate) fn new(line: usize, column: usize) -> Self {
+        Position { line, column }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Error raised while parsing a MPD.
+///
+/// It keeps the path of the node which could not be read, e.g.
+/// `MPD/Period[id=3]/AdaptationSet[1]/SegmentTemplate@timescale`, its position in the
+/// source and, when the value was rejected by one of the custom deserializers
+/// (`duration_iso_8601`, `conditional_uint`, ...), the name of this deserializer.
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+    path: Vec<String>,
+    attribute: Option<String>,
+    position: Option<Position>,
+    deserializer: Option<&'static str>,
+    kind: ErrorKind,
+}
+
+/// What an [`Error`] stands for, some only steer the deserializer and never reach the
+/// caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorKind {
+    Invalid,
+    /// A `$value` sequence has no child of a known name left, and ends.
+    NoKnownChild,
+}
+
+impl Error {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        Error {
+            message: message.into(),
+            path: Vec::new(),
+            attribute: None,
+            position: None,
+            deserializer: None,
+            kind: ErrorKind::Invalid,
+        }
+    }
+
+    pub(crate) fn no_known_child() -> Self {
+        Error {
+            kind: ErrorKind::NoKnownChild,
+            ..Error::new("no known child left")
+        }
+    }
+
+    pub(crate) fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub(crate) fn with_path(mut self, path: Vec<String>) -> Self {
+        self.path = path;
+        self
+    }
+
+    pub(crate) fn with_position(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Locates an error raised while reading the value of an attribute.
+    /// Has no effect when the error was already located deeper in the document.
+    pub(crate) fn at_attribute(mut self, name: &str, position: Option<Position>) -> Self {
+        if self.path.is_empty() && self.attribute.is_none() {
+            self.attribute = Some(name.to_string());
+            self.position = self.position.or(position);
+        }
+        self
+    }
+
+    /// Prefixes the path of the error with the element it has been raised in.
+    pub(crate) fn within(mut self, element: String, position: Option<Position>) -> Self {
+        self.path.insert(0, element);
+        self.position = self.position.or(position);
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// XML path of the node which could not be read.
+    pub fn path(&self) -> String {
+        let mut path = self.path.join("/");
+        if let Some(attribute) = &self.attribute {
+            path.push('@');
+            path.push_str(attribute);
+        }
+        path
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.position.map(|position| position.line)
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        self.position.map(|position| position.column)
+    }
+
+    /// Name of the custom deserializer which rejected the value, if any.
+    pub fn deserializer(&self) -> Option<&'static str> {
+        self.deserializer
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(deserializer) = self.deserializer {
+            write!(f, " (rejected by {})", deserializer)?;
+        }
+        if !self.path.is_empty() || self.attribute.is_some() {
+            write!(f, " at {}", self.path())?;
+        }
+        if let Some(position) = self.position {
+            write!(f, " ({})", position)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        let message = msg.to_string();
+        Error {
+            deserializer: serde_custom::rejected_by(&message),
+            ..Error::new(message)
+        }
+    }
+}
+
+impl From<MPDError> for Error {
+    fn from(error: MPDError) -> Self {
+        Error::new(error.to_string()).with_path(vec!["MPD".to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MPD;
+
+    const MPD_HEADER: &str =
+        r#"<MPD type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011">"#;
+
+    #[test]
+    fn should_locate_attribute_rejected_by_custom_deserializer() {
+        let source = format!(
+            "{}\n  <Period id=\"3\" start=\"PT1631626458X\">\n  </Period>\n</MPD>",
+            MPD_HEADER
+        );
+        let error = source.parse::<MPD>().unwrap_err();
+        assert_eq!(error.path(), "MPD/Period[id=3]@start");
+        assert_eq!(error.line(), Some(2));
+        assert_eq!(error.column(), Some(18));
+        assert_eq!(error.deserializer(), Some("duration_iso_8601"));
+        assert_eq!(
+            error.to_string(),
+            "Invalid ISO_8601 Duration: X is not a token available > PT1631626458X (rejected by duration_iso_8601) at MPD/Period[id=3]@start (line 2, column 18)"
+        );
+    }
+
+    #[test]
+    fn should_locate_invalid_number_in_nested_element() {
+        let source = format!(
+            r#"{}
+  <Period id="3">
+    <AdaptationSet contentType="audio"></AdaptationSet>
+    <AdaptationSet contentType="video">
+      <SegmentTemplate timescale="90k"/>
+    </AdaptationSet>
+  </Period>
+</MPD>"#,
+            MPD_HEADER
+        );
+        let error = source.parse::<MPD>().unwrap_err();
+        assert_eq!(
+            error.path(),
+            "MPD/Period[id=3]/AdaptationSet[2]/SegmentTemplate@timescale"
+        );
+        assert_eq!(error.line(), Some(5));
+        assert_eq!(error.column(), Some(24));
+        assert_eq!(error.deserializer(), None);
+        assert_eq!(
+            error.message(),
+            "invalid u64 `90k`: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn should_not_credit_a_rejection_raised_for_another_format() {
+        let _: serde::de::value::Error = crate::serde_custom::rejected("conditional_uint", "no");
+        let source = r#"<MPD type="sometimes" profiles="urn:mpeg:dash:profile:isoff-live:2011"/>"#;
+        let error = source.parse::<MPD>().unwrap_err();
+        assert!(error.message().starts_with("unknown variant `sometimes`"));
+        assert_eq!(error.deserializer(), None);
+    }
+
+    #[test]
+    fn should_report_conditional_uint_rejection() {
+        let source = format!(
+            r#"{}
+  <Period><AdaptationSet segmentAlignment="maybe"/></Period>
+</MPD>"#,
+            MPD_HEADER
+        );
+        let error = source.parse::<MPD>().unwrap_err();
+        assert_eq!(error.path(), "MPD/Period/AdaptationSet@segmentAlignment");
+        assert_eq!(error.deserializer(), Some("conditional_uint"));
+        assert_eq!(error.line(), Some(2));
+    }
+
+    #[test]
+    fn should_locate_missing_attribute_on_its_element() {
+        let source = format!(
+            r#"{}
+  <Period><AdaptationSet><Representation bandwidth="1"/></AdaptationSet></Period>
+</MPD>"#,
+            MPD_HEADER
+        );
+        let error = source.parse::<MPD>().unwrap_err();
+        assert_eq!(error.path(), "MPD/Period/AdaptationSet/Representation");
+        assert_eq!(error.message(), "missing field `id`");
+        assert_eq!(error.line(), Some(2));
+        assert_eq!(error.column(), Some(26));
+    }
+
+    #[test]
+    fn should_locate_malformed_xml() {
+        let source = format!("{}\n  <Period></Perio>\n</MPD>", MPD_HEADER);
+        let error = source.parse::<MPD>().unwrap_err();
+        assert_eq!(error.path(), "MPD/Period");
+        assert_eq!(error.line(), Some(2));
+    }
+}
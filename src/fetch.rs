@@ -0,0 +1,428 @@
+//! Download of MPDs over HTTP.
+use std::fmt;
+
+use chrono::{Duration, Local};
+use reqwest::{redirect, Client, ClientBuilder};
+use url::Url;
+
+use crate::base_url::{BaseURLError, ResolvedBaseURL};
+use crate::error::Error;
+use crate::models::mpd::MPD;
+
+/// Redirections followed before giving up.
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug)]
+pub enum FetchError {
+    InvalidUrl(String),
+    /// The request failed or the server answered with an error status.
+    Request(String, reqwest::Error),
+    /// The response isn't a valid MPD.
+    Parse(String, Error),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::InvalidUrl(url) => write!(f, "Invalid MPD URL: {}", url),
+            FetchError::Request(url, error) => write!(f, "Can't fetch {}: {}", url, error),
+            FetchError::Parse(url, error) => write!(f, "Invalid MPD at {}: {}", url, error),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FetchError::InvalidUrl(_) => None,
+            FetchError::Request(_, error) => Some(error),
+            FetchError::Parse(_, error) => Some(error),
+        }
+    }
+}
+
+/// MPD downloaded from a server, with the URL it was served from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchedMPD {
+    mpd: MPD,
+    url: Url,
+    patched: bool,
+}
+
+impl FetchedMPD {
+    pub fn mpd(&self) -> &MPD {
+        &self.mpd
+    }
+
+    pub fn into_mpd(self) -> MPD {
+        self.mpd
+    }
+
+    /// URL after the redirections, against which the relative URLs of the MPD resolve.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Whether the MPD was updated from a patch rather than downloaded in full, see
+    /// [`MPDFetcher::refresh`].
+    pub fn is_patched(&self) -> bool {
+        self.patched
+    }
+
+    /// [`MPD::resolve_base_urls`] against [`url`](Self::url), for the `Representation`
+    /// given by its indices, which fails when there is none.
+    pub fn resolve_base_urls(
+        &self,
+        period_index: usize,
+        adaptation_index: usize,
+        representation_index: usize,
+    ) -> Result<Vec<ResolvedBaseURL>, BaseURLError> {
+        let unknown = || {
+            BaseURLError::UnknownRepresentation(
+                period_index,
+                adaptation_index,
+                representation_index,
+            )
+        };
+        let period = self.mpd.periods().get(period_index).ok_or_else(unknown)?;
+        let adaptation = period
+            .adaptations()
+            .get(adaptation_index)
+            .ok_or_else(unknown)?;
+        let representation = adaptation
+            .representations()
+            .get(representation_index)
+            .ok_or_else(unknown)?;
+        self.mpd
+            .resolve_base_urls(&self.url, period, adaptation, representation)
+    }
+}
+
+/// Downloads MPDs with a shared HTTP client.
+#[derive(Debug, Clone)]
+pub struct MPDFetcher {
+    client: Client,
+}
+
+impl Default for MPDFetcher {
+    fn default() -> Self {
+        MPDFetcher {
+            client: MPDFetcher::client_builder()
+                .build()
+                .expect("default HTTP client can't be built"),
+        }
+    }
+}
+
+impl MPDFetcher {
+    pub fn new() -> Self {
+        MPDFetcher::default()
+    }
+
+    /// Client with gzip and brotli decompression, a cookie store and up to 10
+    /// redirections, to configure further with headers, timeouts or a proxy and pass to
+    /// [`with_client`](Self::with_client).
+    pub fn client_builder() -> ClientBuilder {
+        Client::builder()
+            .gzip(true)
+            .brotli(true)
+            .cookie_store(true)
+            .redirect(redirect::Policy::limited(MAX_REDIRECTS))
+    }
+
+    pub fn with_client(client: Client) -> Self {
+        MPDFetcher { client }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub async fn fetch(&self, url: &str) -> Result<FetchedMPD, FetchError> {
+        let url = Url::parse(url).map_err(|_| FetchError::InvalidUrl(url.to_string()))?;
+        let failed = |error| FetchError::Request(url.to_string(), error);
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(failed)?;
+        let final_url = response.url().clone();
+        let bytes = response.bytes().await.map_err(failed)?;
+        let mpd = MPD::from_bytes(&bytes)
+            .map_err(|error| FetchError::Parse(final_url.to_string(), error))?;
+        Ok(FetchedMPD {
+            mpd,
+            url: final_url,
+            patched: false,
+        })
+    }
+
+    /// New version of `previous`, patched from its first `PatchLocation` which is still
+    /// valid and serves a patch applying to it. Falls back to fetching `url` in full.
+    pub async fn refresh(
+        &self,
+        url: &str,
+        previous: &FetchedMPD,
+    ) -> Result<FetchedMPD, FetchError> {
+        match self.patch(previous).await {
+            Some(mpd) => Ok(FetchedMPD {
+                mpd,
+                url: previous.url.clone(),
+                patched: true,
+            }),
+            None => self.fetch(url).await,
+        }
+    }
+
+    async fn patch(&self, previous: &FetchedMPD) -> Option<MPD> {
+        let mpd = previous.mpd();
+        let now = Local::now();
+        for location in mpd.patch_locations() {
+            let expired = location
+                .ttl()
+                .zip(mpd.publish_time())
+                .is_some_and(|(ttl, published)| {
+                    published + Duration::milliseconds((ttl * 1000.0) as i64) < now
+                });
+            let url = match previous.url.join(location.url().trim()) {
+                Ok(url) if !expired => url,
+                _ => continue,
+            };
+            let response = self
+                .client
+                .get(url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            let patch = match response {
+                Ok(response) => response.text().await,
+                Err(error) => Err(error),
+            };
+            if let Some(patched) = patch.ok().and_then(|patch| mpd.apply_patch(&patch).ok()) {
+                return Some(patched);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+    use std::io::Write;
+
+    const MANIFEST: &str = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT10S">
+    <Period id="1">
+        <AdaptationSet>
+            <Representation id="1" bandwidth="1">
+                <BaseURL>video/</BaseURL>
+            </Representation>
+        </AdaptationSet>
+    </Period>
+</MPD>"#;
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(bytes: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        {
+            let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 5, 22);
+            encoder.write_all(bytes).unwrap();
+        }
+        compressed
+    }
+
+    #[tokio::test]
+    async fn should_follow_redirects_and_resolve_against_final_url() {
+        let (root, requests) = serve(|request| match request.path.as_str() {
+            "/manifest.mpd" => {
+                Response::new(302, "").with_header("Location", "/live/v2/manifest.mpd")
+            }
+            "/live/v2/manifest.mpd" => Response::ok(MANIFEST),
+            _ => Response::not_found(),
+        })
+        .await;
+        let fetched = MPD::fetch(root.join("manifest.mpd").unwrap().as_str())
+            .await
+            .unwrap();
+        assert_eq!(fetched.url().path(), "/live/v2/manifest.mpd");
+        assert_eq!(
+            fetched.resolve_base_urls(0, 0, 0).unwrap()[0]
+                .url()
+                .as_str(),
+            root.join("live/v2/video/").unwrap().as_str()
+        );
+        assert!(matches!(
+            fetched.resolve_base_urls(0, 0, 5),
+            Err(BaseURLError::UnknownRepresentation(0, 0, 5))
+        ));
+        assert!(fetched.resolve_base_urls(3, 0, 0).is_err());
+        assert_eq!(fetched.mpd(), &MANIFEST.parse::<MPD>().unwrap());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_decompress_gzip_and_brotli() {
+        let (root, requests) = serve(|request| {
+            let accepted = request.header("Accept-Encoding").unwrap_or_default();
+            match request.path.as_str() {
+                "/gzip.mpd" if accepted.contains("gzip") => {
+                    Response::ok(gzip(MANIFEST.as_bytes())).with_header("Content-Encoding", "gzip")
+                }
+                "/brotli.mpd" if accepted.contains("br") => {
+                    Response::ok(brotli(MANIFEST.as_bytes())).with_header("Content-Encoding", "br")
+                }
+                _ => Response::not_found(),
+            }
+        })
+        .await;
+        let fetcher = MPDFetcher::new();
+        for path in ["gzip.mpd", "brotli.mpd"] {
+            let fetched = fetcher
+                .fetch(root.join(path).unwrap().as_str())
+                .await
+                .unwrap();
+            assert_eq!(fetched.mpd().periods()[0].id(), "1");
+        }
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_send_configured_headers_and_cookies() {
+        let (root, _) = serve(|request| match request.path.as_str() {
+            "/login" => Response::new(302, "")
+                .with_header("Set-Cookie", "session=42; Path=/")
+                .with_header("Location", "/manifest.mpd"),
+            "/manifest.mpd"
+                if request.header("Cookie") == Some("session=42")
+                    && request.header("X-Api-Key") == Some("secret") =>
+            {
+                Response::ok(MANIFEST)
+            }
+            _ => Response::new(403, ""),
+        })
+        .await;
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("X-Api-Key", "secret".parse().unwrap());
+        let client = MPDFetcher::client_builder()
+            .default_headers(headers)
+            .timeout(std::time::Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let fetcher = MPDFetcher::with_client(client);
+        assert!(fetcher
+            .fetch(root.join("login").unwrap().as_str())
+            .await
+            .is_ok());
+        assert!(fetcher
+            .fetch(root.join("manifest.mpd").unwrap().as_str())
+            .await
+            .is_ok());
+
+        let error = MPDFetcher::new()
+            .fetch(root.join("manifest.mpd").unwrap().as_str())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, FetchError::Request(_, error) if error.status().map(|status| status.as_u16()) == Some(403))
+        );
+    }
+
+    fn live_manifest(publish_time: &str, ttl: u32) -> String {
+        format!(
+            r#"<MPD id="live" type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z" publishTime="{}" minimumUpdatePeriod="PT2S">
+    <PatchLocation ttl="{}">patches/next.mpp</PatchLocation>
+    <Period id="1" start="PT0S"/>
+</MPD>"#,
+            publish_time, ttl
+        )
+    }
+
+    const PATCH: &str = r#"<Patch mpdId="live" originalPublishTime="2100-01-01T00:00:00Z" publishTime="2100-01-01T00:00:02Z">
+    <add sel="/MPD/Period" pos="after"><Period id="2" start="PT10S"/></add>
+</Patch>"#;
+
+    #[tokio::test]
+    async fn should_refresh_from_patch_location_or_fall_back_to_full_fetch() {
+        let (root, requests) = serve(|request| match request.path.as_str() {
+            "/live.mpd" => Response::ok(live_manifest("2100-01-01T00:00:02Z", 60)),
+            "/patches/next.mpp" => Response::ok(PATCH),
+            _ => Response::not_found(),
+        })
+        .await;
+        let fetcher = MPDFetcher::new();
+        let url = root.join("live.mpd").unwrap();
+        let previous = FetchedMPD {
+            mpd: live_manifest("2100-01-01T00:00:00Z", 60).parse().unwrap(),
+            url: url.clone(),
+            patched: false,
+        };
+
+        let patched = fetcher.refresh(url.as_str(), &previous).await.unwrap();
+        assert!(patched.is_patched());
+        assert_eq!(patched.url(), &url);
+        assert_eq!(patched.mpd().periods().len(), 2);
+        assert_eq!(
+            patched.mpd().publish_time(),
+            "2100-01-01T00:00:02Z".parse().ok()
+        );
+
+        // The patch is for the previous version, the refreshed one needs a full fetch.
+        let refreshed = fetcher.refresh(url.as_str(), &patched).await.unwrap();
+        assert!(!refreshed.is_patched());
+        assert_eq!(refreshed.mpd().periods().len(), 1);
+
+        let paths: Vec<_> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.path.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["/patches/next.mpp", "/patches/next.mpp", "/live.mpd"]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_skip_expired_patch_location() {
+        let (root, requests) = serve(|request| match request.path.as_str() {
+            "/live.mpd" => Response::ok(live_manifest("2024-01-01T00:00:02Z", 60)),
+            _ => Response::ok(PATCH),
+        })
+        .await;
+        let url = root.join("live.mpd").unwrap();
+        let previous = FetchedMPD {
+            mpd: live_manifest("2024-01-01T00:00:00Z", 60).parse().unwrap(),
+            url: url.clone(),
+            patched: false,
+        };
+        let refreshed = MPDFetcher::new()
+            .refresh(url.as_str(), &previous)
+            .await
+            .unwrap();
+        assert!(!refreshed.is_patched());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_report_invalid_documents() {
+        let (root, _) = serve(|_| Response::ok("<html></html>")).await;
+        let error = MPDFetcher::new()
+            .fetch(root.join("page").unwrap().as_str())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, FetchError::Parse(_, _)));
+        assert!(matches!(
+            MPDFetcher::new().fetch("not a url").await,
+            Err(FetchError::InvalidUrl(_))
+        ));
+    }
+}
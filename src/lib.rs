@@ -0,0 +1,59 @@
+//! Parsing and manipulation of MPEG-DASH Media Presentation Descriptions (MPD).
+
+mod archive;
+mod availability;
+mod base_url;
+mod clock;
+mod download;
+mod error;
+mod fetch;
+pub mod models;
+pub mod mp4;
+mod patch;
+mod period_timeline;
+mod recorder;
+mod segment_index;
+mod selection;
+mod serde_custom;
+mod template;
+#[cfg(test)]
+mod test_server;
+mod validation;
+mod watcher;
+mod xlink;
+mod xml;
+
+pub use archive::{ArchiveError, ArchiveReport, Archiver, ARCHIVED_MANIFEST};
+pub use availability::{AvailabilityWindow, AvailableSegment};
+pub use base_url::{BaseURLError, ByteRange, ResolvedBaseURL, SegmentLocation};
+pub use clock::{ClockSync, ClockSyncError};
+pub use download::{DownloadError, DownloadProgress, SegmentDownloader};
+pub use error::{Error, Position};
+pub use fetch::{FetchError, FetchedMPD, MPDFetcher};
+
+pub use models::extensions::{ExtensionAttribute, ExtensionElement, Extensions};
+pub use models::mpd::{MPDError, MPDType, MPD};
+pub use models::period::Period;
+pub use models::representations::{
+    AdaptationSet, ContentProtection, ProtectionAttribut, Representation, SubRepresentation,
+};
+pub use models::segments::{
+    BaseURL, MediaTime, MultipleSegmentBaseType, Segment, SegmentBase, SegmentInformation,
+    SegmentList, SegmentListUrl, SegmentTemplate, SegmentTimeline, SegmentUrl, TimelineSegment,
+    TimelineSegments, DVB_NAMESPACE,
+};
+pub use models::utils::{
+    ContentComponent, Descriptor, Event, EventStream, HrefActuate, Metrics, PatchLocation,
+    ProgramInformation, Range, Subset, Switching, SwitchingType, Url, VideoScan,
+};
+pub use patch::PatchError;
+pub use period_timeline::{PeriodDiscontinuity, PeriodTimeline, PeriodTiming};
+pub use recorder::{RecordError, Recorder, RecordingReport};
+pub use reqwest;
+pub use segment_index::{SegmentIndexError, SegmentIndexResolver, Subsegment};
+pub use selection::BaseURLSelector;
+pub use template::{Identifier, Template, TemplateError, TemplateValues};
+pub use url;
+pub use validation::{MediaValidator, Mismatch, RepresentationCheck, ValidationError};
+pub use watcher::{MPDChange, MPDWatcher};
+pub use xlink::{XLinkError, XLinkResolver, RESOLVE_TO_ZERO};
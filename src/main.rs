@@ -1,22 +1,150 @@
-use quick_xml::{de::from_str, se::to_string};
-
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+use dash::{Archiver, MediaValidator, Recorder, MPD};
 
-use crate::models::mpd::MPD;
-mod models;
-mod serde_custom;
+const USAGE: &str = "Usage: dash archive <url> <dir> [--representation <id>]...
+       dash record <url> <dir> [--representation <id>]... [--duration <seconds>]
+       dash validate <url>";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = File::open("resources\\hdeindex-1.mpd")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    contents = contents.replace(&['\n'][..], "");
-
-    let mpd: MPD = from_str(&contents).unwrap();
-    println!("{}", &mpd);
-    let _contents_serializer = to_string(&mpd)?;
-    // assert_eq!(contents, contents_serializer);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("archive") => archive(&args[1..]).await,
+        Some("record") => record(&args[1..]).await,
+        Some("validate") => validate(&args[1..]).await,
+        Some(_) => Err(USAGE.into()),
+        None => {
+            let file = File::open(Path::new("resources").join("hdeindex-1.mpd"))?;
+            let mpd = MPD::from_reader(BufReader::new(file))?;
+            println!("{}", &mpd);
+            let contents = mpd.to_xml()?;
+            assert_eq!(mpd, contents.parse::<MPD>()?);
+            Ok(())
+        }
+    }
+}
+
+/// Positional arguments, and the `--<option> <value>` pairs.
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    options: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Args<'a> {
+    fn parse(args: &'a [String]) -> Result<Self, &'static str> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(option) => options.push((option, args.next().ok_or(USAGE)?.as_str())),
+                None => positional.push(arg.as_str()),
+            }
+        }
+        Ok(Args {
+            positional,
+            options,
+        })
+    }
+
+    /// `<url> <dir>`, the only positional arguments of the commands.
+    fn url_and_directory(&self) -> Result<(&'a str, &'a Path), &'static str> {
+        match self.positional[..] {
+            [url, directory] => Ok((url, Path::new(directory))),
+            _ => Err(USAGE),
+        }
+    }
+
+    fn values(&self, name: &'a str) -> impl Iterator<Item = &'a str> + '_ {
+        self.options
+            .iter()
+            .filter(move |(option, _)| *option == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+/// `dash archive <url> <dir>`: mirrors a static MPD and its segments to `dir`, all the
+/// `Representation`s or those given with `--representation`.
+async fn archive(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse(args)?;
+    let (url, directory) = args.url_and_directory()?;
+    let report = Archiver::new()
+        .with_representations(args.values("representation"))
+        .archive(url, directory)
+        .await?;
+    println!(
+        "Archived {} files ({} bytes), play {}",
+        report.files(),
+        report.bytes(),
+        report.manifest().display()
+    );
     Ok(())
 }
+
+/// `dash record <url> <dir>`: records a live MPD to `dir` until it ends, `--duration`
+/// seconds are recorded, or Ctrl-C.
+async fn record(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse(args)?;
+    let (url, directory) = args.url_and_directory()?;
+    let mut recorder = Recorder::new().with_representations(args.values("representation"));
+    if let Some(seconds) = args.values("duration").last() {
+        recorder = recorder.with_max_duration(Duration::try_from_secs_f64(seconds.parse()?)?);
+    }
+    let report = recorder
+        .record_until(url, directory, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    println!(
+        "Recorded {} segments ({} bytes, {} failed) covering {}s, play {}",
+        report.segments(),
+        report.bytes(),
+        report.failed(),
+        report.duration().num_milliseconds() as f64 / 1000.0,
+        report.manifest().display()
+    );
+    Ok(())
+}
+
+/// `dash validate <url>`: compares the `Representation`s of a MPD with their
+/// initialization segments, failing when one doesn't match.
+async fn validate(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let url = match args {
+        [url] => url,
+        _ => return Err(USAGE.into()),
+    };
+    let checks = MediaValidator::new().validate(url).await?;
+    let mut invalid = 0;
+    for check in &checks {
+        let name = format!(
+            "Period {} Representation {}",
+            check.period(),
+            check.representation_id()
+        );
+        if let Some(error) = check.error() {
+            println!("{}: {}", name, error);
+        }
+        for mismatch in check.mismatches() {
+            println!("{}: {}", name, mismatch);
+        }
+        if !check.is_valid() {
+            invalid += 1;
+        }
+    }
+    match invalid {
+        0 => {
+            println!("{} Representations match their media", checks.len());
+            Ok(())
+        }
+        _ => Err(format!(
+            "{} of {} Representations don't match their media",
+            invalid,
+            checks.len()
+        )
+        .into()),
+    }
+}
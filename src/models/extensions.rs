@@ -0,0 +1,322 @@
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::Error;
+use crate::xml;
+
+/// Attribute the model doesn't know, e.g. `dvb:priority`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionAttribute {
+    name: String,
+    value: String,
+    namespace: Option<String>,
+}
+
+impl ExtensionAttribute {
+    /// `namespace` is the URI bound to the prefix of `name`, if any.
+    pub fn new(name: &str, value: &str, namespace: Option<&str>) -> Self {
+        ExtensionAttribute {
+            name: name.to_string(),
+            value: value.to_string(),
+            namespace: namespace.map(str::to_string),
+        }
+    }
+
+    /// Qualified name, with its prefix.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn local_name(&self) -> &str {
+        xml::split_name(&self.name).1
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+}
+
+/// Child element the model doesn't know, e.g. a `scte35:SpliceInfoSection`, kept as raw
+/// XML. The namespaces it uses are declared on it when they were declared by an ancestor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionElement {
+    name: String,
+    namespace: Option<String>,
+    xml: String,
+}
+
+impl ExtensionElement {
+    /// Reads the element from its XML, which should declare the namespaces it uses.
+    pub fn from_xml(source: &str) -> Result<Self, Error> {
+        let element = xml::Element::parse(source)?;
+        Ok(ExtensionElement {
+            name: element.name.clone(),
+            namespace: element.namespace.clone(),
+            xml: element.to_detached_xml(),
+        })
+    }
+
+    /// Qualified name, with its prefix.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn local_name(&self) -> &str {
+        xml::split_name(&self.name).1
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub fn xml(&self) -> &str {
+        &self.xml
+    }
+}
+
+/// Attributes and children of a node which aren't part of the model, such as vendor
+/// extensions. They are written back after the known ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Extensions {
+    attributes: Vec<ExtensionAttribute>,
+    elements: Vec<ExtensionElement>,
+}
+
+impl Extensions {
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.elements.is_empty()
+    }
+
+    pub fn attributes(&self) -> &[ExtensionAttribute] {
+        &self.attributes
+    }
+
+    pub fn attributes_mut(&mut self) -> &mut Vec<ExtensionAttribute> {
+        &mut self.attributes
+    }
+
+    /// Value of the attribute with the qualified name `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(ExtensionAttribute::value)
+    }
+
+    pub fn elements(&self) -> &[ExtensionElement] {
+        &self.elements
+    }
+
+    pub fn elements_mut(&mut self) -> &mut Vec<ExtensionElement> {
+        &mut self.elements
+    }
+}
+
+/// Rows exchanged with the XML backend under the `$extensions` key: attributes as
+/// `(name, value, namespace)`, elements as `(name, namespace, xml)`, with an empty
+/// namespace when there is none.
+#[derive(Deserialize)]
+struct Rows {
+    attributes: Vec<(String, String, String)>,
+    elements: Vec<(String, String, String)>,
+}
+
+fn non_empty(namespace: String) -> Option<String> {
+    Some(namespace).filter(|namespace| !namespace.is_empty())
+}
+
+impl Serialize for Extensions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let attributes: Vec<(&str, &str, &str)> = self
+            .attributes
+            .iter()
+            .map(|attribute| {
+                (
+                    attribute.name.as_str(),
+                    attribute.value.as_str(),
+                    attribute.namespace().unwrap_or_default(),
+                )
+            })
+            .collect();
+        let elements: Vec<(&str, &str, &str)> = self
+            .elements
+            .iter()
+            .map(|element| {
+                (
+                    element.name.as_str(),
+                    element.namespace().unwrap_or_default(),
+                    element.xml.as_str(),
+                )
+            })
+            .collect();
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("attributes", &attributes)?;
+        map.serialize_entry("elements", &elements)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Extensions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows = Rows::deserialize(deserializer)?;
+        Ok(Extensions {
+            attributes: rows
+                .attributes
+                .into_iter()
+                .map(|(name, value, namespace)| ExtensionAttribute {
+                    name,
+                    value,
+                    namespace: non_empty(namespace),
+                })
+                .collect(),
+            elements: rows
+                .elements
+                .into_iter()
+                .map(|(name, namespace, xml)| ExtensionElement {
+                    name,
+                    namespace: non_empty(namespace),
+                    xml,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProtectionAttribut, MPD};
+
+    const SOURCE: &str = r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" xmlns:dvb="urn:dvb:dash:dash-extensions:2014-1" xmlns:scte35="http://www.scte.org/schemas/35/2016" xmlns:cenc="urn:mpeg:cenc:2013" profiles="urn:dvb:dash:profile:dvb-dash:2014" mediaPresentationDuration="PT1M" dvb:version="2">
+    <Period id="1" dvb:label="main">
+        <scte35:SpliceInfoSection ptsAdjustment="0">
+            <scte35:SpliceInsert spliceEventId="7" outOfNetworkIndicator="true"/>
+        </scte35:SpliceInfoSection>
+        <AdaptationSet contentType="video" xmlns:vendor="urn:example:vendor" vendor:hint="fast">
+            <ContentProtection schemeIdUri="urn:uuid:e2719d58-a985-b3c9-781a-b030af78d30e" xmlns:clearkey="http://dashif.org/guidelines/clearKey">
+                <clearkey:Laurl Lic_type="EME-1.0">https://drm.example.com/AcquireLicense</clearkey:Laurl>
+                <cenc:pssh>AAAAbXBzc2g=</cenc:pssh>
+            </ContentProtection>
+            <SegmentTemplate timescale="1000" vendor:cache="none"/>
+            <Representation id="1" bandwidth="100000">
+                <Label lang="en">Main <vendor:b>camera</vendor:b></Label>
+            </Representation>
+        </AdaptationSet>
+    </Period>
+</MPD>"#;
+
+    #[test]
+    fn should_keep_unknown_attributes_with_their_namespace() {
+        let mpd: MPD = SOURCE.parse().unwrap();
+        assert_eq!(mpd.extensions().attribute("dvb:version"), Some("2"));
+        let attribute = &mpd.extensions().attributes()[0];
+        assert_eq!(attribute.local_name(), "version");
+        assert_eq!(
+            attribute.namespace(),
+            Some("urn:dvb:dash:dash-extensions:2014-1")
+        );
+        assert_eq!(
+            mpd.periods()[0].extensions().attribute("dvb:label"),
+            Some("main")
+        );
+
+        let adaptation = &mpd.periods()[0].adaptations()[0];
+        assert_eq!(
+            adaptation.extensions().attribute("vendor:hint"),
+            Some("fast")
+        );
+        assert_eq!(
+            adaptation.extensions().attribute("xmlns:vendor"),
+            Some("urn:example:vendor")
+        );
+        let template = adaptation.segment_template().unwrap();
+        assert_eq!(
+            template.extensions().attribute("vendor:cache"),
+            Some("none")
+        );
+        assert_eq!(
+            template.extensions().attributes()[0].namespace(),
+            Some("urn:example:vendor")
+        );
+    }
+
+    #[test]
+    fn should_keep_unknown_children_as_raw_xml() {
+        let mpd: MPD = SOURCE.parse().unwrap();
+        let splice = &mpd.periods()[0].extensions().elements()[0];
+        assert_eq!(splice.name(), "scte35:SpliceInfoSection");
+        assert_eq!(splice.local_name(), "SpliceInfoSection");
+        assert_eq!(
+            splice.namespace(),
+            Some("http://www.scte.org/schemas/35/2016")
+        );
+        assert_eq!(
+            splice.xml(),
+            "<scte35:SpliceInfoSection ptsAdjustment=\"0\" xmlns:scte35=\"http://www.scte.org/schemas/35/2016\">\n  <scte35:SpliceInsert spliceEventId=\"7\" outOfNetworkIndicator=\"true\"/>\n</scte35:SpliceInfoSection>"
+        );
+
+        let adaptation = &mpd.periods()[0].adaptations()[0];
+        let protection = &adaptation.content_protection()[0];
+        assert_eq!(
+            protection.protection_key(),
+            &[ProtectionAttribut::CencPssh("AAAAbXBzc2g=".to_string())]
+        );
+        let laurl = &protection.extensions().elements()[0];
+        assert_eq!(
+            laurl.namespace(),
+            Some("http://dashif.org/guidelines/clearKey")
+        );
+        assert!(laurl
+            .xml()
+            .starts_with("<clearkey:Laurl Lic_type=\"EME-1.0\" xmlns:clearkey="));
+
+        let label = &adaptation.representations()[0].extensions().elements()[0];
+        assert_eq!(label.name(), "Label");
+        assert_eq!(label.namespace(), Some("urn:mpeg:dash:schema:mpd:2011"));
+        assert_eq!(
+            label.xml(),
+            "<Label lang=\"en\" xmlns:vendor=\"urn:example:vendor\">Main <vendor:b>camera</vendor:b></Label>"
+        );
+    }
+
+    #[test]
+    fn should_write_extensions_back() {
+        let mpd: MPD = SOURCE.parse().unwrap();
+        let written = mpd.to_xml().unwrap();
+        assert!(written.contains(r#" dvb:version="2""#));
+        assert!(written.contains(r#"<Period id="1" dvb:label="main">"#));
+        assert!(written.contains("<scte35:SpliceInsert spliceEventId=\"7\""));
+        assert!(written.contains(r#"<SegmentTemplate timescale="1000" vendor:cache="none"/>"#));
+        assert_eq!(mpd, written.parse::<MPD>().unwrap());
+    }
+
+    #[test]
+    fn should_write_extensions_added_in_memory() {
+        let mut mpd: MPD = SOURCE.parse().unwrap();
+        let period = &mut mpd.periods_mut()[0];
+        period
+            .extensions_mut()
+            .attributes_mut()
+            .push(ExtensionAttribute::new(
+                "dvb:id",
+                "live",
+                Some("urn:dvb:dash:dash-extensions:2014-1"),
+            ));
+        period.extensions_mut().elements_mut().push(
+            ExtensionElement::from_xml(r#"<ex:Note xmlns:ex="urn:example">a &amp; b</ex:Note>"#)
+                .unwrap(),
+        );
+        let written = mpd.to_xml().unwrap();
+        assert!(written.contains(r#"<ex:Note xmlns:ex="urn:example">a &amp; b</ex:Note>"#));
+        let parsed: MPD = written.parse().unwrap();
+        assert_eq!(
+            parsed.periods()[0].extensions().attribute("dvb:id"),
+            Some("live")
+        );
+        assert_eq!(mpd, parsed);
+    }
+}
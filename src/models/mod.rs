@@ -1,3 +1,4 @@
+pub mod extensions;
 pub mod mpd;
 pub mod period;
 pub mod representations;
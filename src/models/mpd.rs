@@ -1,21 +1,61 @@
-use crate::serde_custom::{duration_iso_8601, tags};
-use chrono::{DateTime, Duration, Local};
+use crate::availability::{self, AvailabilityWindow, Presentation};
+use crate::base_url::{self, BaseURLError, ResolvedBaseURL};
+use crate::error::Error;
+use crate::fetch::{FetchError, FetchedMPD, MPDFetcher};
+use crate::patch::{self, PatchError};
+use crate::period_timeline::PeriodTimeline;
+use crate::serde_custom::{duration_iso_8601, is_default, tags};
+use crate::xml;
+use chrono::{DateTime, Duration, Local, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::{Debug, Display};
+use std::io::BufRead;
+use std::str::FromStr;
 
-use super::{period::Period, segments::BaseURL, utils::*};
+use super::{
+    extensions::Extensions,
+    period::Period,
+    representations::{AdaptationSet, Representation},
+    segments::BaseURL,
+    utils::*,
+};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum MPDType {
+    #[default]
+    Static,
     Dynamic,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug)]
+pub enum MPDError {
+    MissingMediaPresentationDuration,
+}
+
+impl fmt::Display for MPDError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MPDError::MissingMediaPresentationDuration => write!(
+                f,
+                "Invalid MPD: static MPD should define mediaPresentationDuration or the duration of its last Period"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MPDError {}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct MPD {
     // Attrib ut
+    #[serde(default, skip_serializing_if = "is_default")]
+    id: String,
+    #[serde(default = "MPDType::default", skip_serializing_if = "is_default")]
     r#type: MPDType,
     #[serde(with = "tags")]
     profiles: Vec<String>,
@@ -36,15 +76,30 @@ pub struct MPD {
     max_segment_duration: Option<Duration>,
     #[serde(with = "duration_iso_8601", default)]
     max_subsegment_duration: Option<Duration>,
+    /// Namespace declarations of the document, by prefix (empty for the default one).
+    #[serde(
+        rename = "$namespaces",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    namespaces: BTreeMap<String, String>,
+    #[serde(
+        rename = "xsi:schemaLocation",
+        default,
+        skip_serializing_if = "is_default"
+    )]
+    schema_location: String,
     // Children
-    #[serde(rename = "Period")]
-    periods: Vec<Period>,
     #[serde(rename = "ProgramInformation", default)]
     programme_information: Vec<ProgramInformation>,
     #[serde(rename = "BaseURL", default)]
     base_url: Vec<BaseURL>,
     #[serde(rename = "Location", default)]
     locations: Vec<Url>,
+    #[serde(rename = "PatchLocation", default)]
+    patch_locations: Vec<PatchLocation>,
+    #[serde(rename = "Period")]
+    periods: Vec<Period>,
     #[serde(rename = "Metrics", default)]
     metrics: Vec<Metrics>,
     #[serde(rename = "EssentialProperty", default)]
@@ -53,6 +108,159 @@ pub struct MPD {
     supplemental_property: Vec<Descriptor>,
     #[serde(rename = "UTCTiming", default)]
     utc_timing: Vec<Descriptor>,
+    #[serde(
+        rename = "$extensions",
+        default,
+        skip_serializing_if = "Extensions::is_empty"
+    )]
+    extensions: Extensions,
+}
+
+impl MPD {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn r#type(&self) -> MPDType {
+        self.r#type
+    }
+
+    pub fn profiles(&self) -> &[String] {
+        &self.profiles
+    }
+
+    pub fn availability_start_time(&self) -> Option<DateTime<Local>> {
+        self.availability_start_time
+    }
+
+    pub fn availability_end_time(&self) -> Option<DateTime<Local>> {
+        self.availability_end_time
+    }
+
+    pub fn publish_time(&self) -> Option<DateTime<Local>> {
+        self.publish_time
+    }
+
+    pub fn media_presentation_duration(&self) -> Option<Duration> {
+        self.media_presentation_duration
+    }
+
+    pub fn minimum_update_period(&self) -> Option<Duration> {
+        self.minimum_update_period
+    }
+
+    pub fn min_buffer_time(&self) -> Option<Duration> {
+        self.min_buffer_time
+    }
+
+    pub fn time_shift_buffer_depth(&self) -> Option<Duration> {
+        self.time_shift_buffer_depth
+    }
+
+    pub fn suggested_presentation_delay(&self) -> Option<Duration> {
+        self.suggested_presentation_delay
+    }
+
+    pub fn max_segment_duration(&self) -> Option<Duration> {
+        self.max_segment_duration
+    }
+
+    pub fn max_subsegment_duration(&self) -> Option<Duration> {
+        self.max_subsegment_duration
+    }
+
+    pub fn namespaces(&self) -> &BTreeMap<String, String> {
+        &self.namespaces
+    }
+
+    pub fn namespaces_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.namespaces
+    }
+
+    pub fn schema_location(&self) -> &str {
+        &self.schema_location
+    }
+
+    pub fn periods(&self) -> &[Period] {
+        &self.periods
+    }
+
+    pub fn periods_mut(&mut self) -> &mut Vec<Period> {
+        &mut self.periods
+    }
+
+    pub fn programme_information(&self) -> &[ProgramInformation] {
+        &self.programme_information
+    }
+
+    pub fn programme_information_mut(&mut self) -> &mut Vec<ProgramInformation> {
+        &mut self.programme_information
+    }
+
+    pub fn base_url(&self) -> &[BaseURL] {
+        &self.base_url
+    }
+
+    pub fn base_url_mut(&mut self) -> &mut Vec<BaseURL> {
+        &mut self.base_url
+    }
+
+    pub fn locations(&self) -> &[Url] {
+        &self.locations
+    }
+
+    pub fn locations_mut(&mut self) -> &mut Vec<Url> {
+        &mut self.locations
+    }
+
+    pub fn patch_locations(&self) -> &[PatchLocation] {
+        &self.patch_locations
+    }
+
+    pub fn patch_locations_mut(&mut self) -> &mut Vec<PatchLocation> {
+        &mut self.patch_locations
+    }
+
+    pub fn metrics(&self) -> &[Metrics] {
+        &self.metrics
+    }
+
+    pub fn metrics_mut(&mut self) -> &mut Vec<Metrics> {
+        &mut self.metrics
+    }
+
+    pub fn essential_property(&self) -> &[Descriptor] {
+        &self.essential_property
+    }
+
+    pub fn essential_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.essential_property
+    }
+
+    pub fn supplemental_property(&self) -> &[Descriptor] {
+        &self.supplemental_property
+    }
+
+    pub fn supplemental_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.supplemental_property
+    }
+
+    pub fn utc_timing(&self) -> &[Descriptor] {
+        &self.utc_timing
+    }
+
+    pub fn utc_timing_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.utc_timing
+    }
+
+    /// Attributes and children the model doesn't know.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
 }
 fn display_vec_with_commat<T>(f: &mut fmt::Formatter, vec: &Vec<T>) -> fmt::Result
 where
@@ -87,16 +295,188 @@ where
     Ok(())
 }
 
+impl MPD {
+    pub fn is_static(&self) -> bool {
+        self.r#type == MPDType::Static
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.r#type == MPDType::Dynamic
+    }
+
+    /// Period between two refreshes of the manifest.
+    /// A static MPD never changes, so `minimumUpdatePeriod` is ignored for it.
+    pub fn refresh_period(&self) -> Option<Duration> {
+        if self.is_dynamic() {
+            self.minimum_update_period
+        } else {
+            None
+        }
+    }
+
+    /// Start and end of every period, with the gaps and overlaps between them.
+    pub fn period_timeline(&self) -> PeriodTimeline {
+        PeriodTimeline::new(self)
+    }
+
+    /// Start of the period at `index` relative to the start of the presentation, see
+    /// [`period_timeline`](Self::period_timeline).
+    pub fn period_start(&self, index: usize) -> Option<Duration> {
+        self.period_timeline().periods().get(index)?.start()
+    }
+
+    /// Duration of the period at `index`, from its start and end in the
+    /// [`period_timeline`](Self::period_timeline), or its own `duration` when its start
+    /// is unknown. `None` when it can't be known, or when the next period starts before
+    /// it.
+    pub fn period_duration(&self, index: usize) -> Option<Duration> {
+        self.period_duration_in(&self.period_timeline(), index)
+    }
+
+    /// [`period_duration`](Self::period_duration) from the `timeline` of this MPD, for
+    /// callers going through every period.
+    pub(crate) fn period_duration_in(
+        &self,
+        timeline: &PeriodTimeline,
+        index: usize,
+    ) -> Option<Duration> {
+        let period = self.periods.get(index)?;
+        timeline.periods()[index]
+            .duration()
+            .or_else(|| period.duration())
+            .filter(|duration| *duration >= Duration::zero())
+    }
+
+    /// Absolute base URLs of `representation`, from `adaptation` in `period`: one per
+    /// combination of the `BaseURL` alternatives of each level, resolved against
+    /// `manifest_url`, the URL the MPD was fetched from.
+    pub fn resolve_base_urls(
+        &self,
+        manifest_url: &url::Url,
+        period: &Period,
+        adaptation: &AdaptationSet,
+        representation: &Representation,
+    ) -> Result<Vec<ResolvedBaseURL>, BaseURLError> {
+        base_url::resolve(
+            manifest_url,
+            &[
+                self.base_url(),
+                period.base_urls(),
+                adaptation.base_urls(),
+                representation.base_urls(),
+            ],
+        )
+    }
+
+    /// Segments of a `Representation`, given by its indices, available at `now` from the
+    /// first `BaseURL` of each level. `None` without `availabilityStartTime`, when its
+    /// segments can't be listed, e.g. with a `SegmentBase`, or when none is available yet.
+    pub fn availability_window(
+        &self,
+        period_index: usize,
+        adaptation_index: usize,
+        representation_index: usize,
+        now: DateTime<Utc>,
+    ) -> Option<AvailabilityWindow> {
+        let period = self.periods.get(period_index)?;
+        let adaptation = period.adaptations().get(adaptation_index)?;
+        let representation = adaptation.representations().get(representation_index)?;
+        let information = period.segment_information(adaptation, representation)?;
+        let timeline = self.period_timeline();
+        // The first `BaseURL` of each level, those of the first resolved base URL.
+        let base_url_availability_time_offset = [
+            self.base_url(),
+            period.base_urls(),
+            adaptation.base_urls(),
+            representation.base_urls(),
+        ]
+        .iter()
+        .filter_map(|base_urls| base_urls.first())
+        .map(BaseURL::availability_time_offset)
+        .sum();
+        let presentation = Presentation {
+            availability_start: self.availability_start_time?.with_timezone(&Utc),
+            period_start: timeline.periods()[period_index].start()?,
+            period_duration: self.period_duration_in(&timeline, period_index),
+            time_shift_buffer_depth: self.time_shift_buffer_depth.filter(|_| self.is_dynamic()),
+            suggested_presentation_delay: self.suggested_presentation_delay,
+            base_url_availability_time_offset,
+        };
+        availability::window(&presentation, &information, now)
+    }
+
+    /// Applies a MPD patch document, see [`PatchLocation`]. The MPD is left unchanged
+    /// when the patch is for another version of it or can't be applied.
+    pub fn apply_patch(&self, patch: &str) -> Result<MPD, PatchError> {
+        patch::apply(self, patch)
+    }
+
+    /// Downloads the MPD at `url` with a default [`MPDFetcher`].
+    pub async fn fetch(url: &str) -> Result<FetchedMPD, FetchError> {
+        MPDFetcher::new().fetch(url).await
+    }
+
+    /// Reads a MPD from a UTF-8 (or UTF-16 with BOM) document.
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<MPD, Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|error| Error::new(error.to_string()))?;
+        MPD::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<MPD, Error> {
+        let mpd: MPD = xml::from_bytes(bytes)?;
+        mpd.validate()?;
+        Ok(mpd)
+    }
+
+    /// Writes the MPD back as a XML document. Attributes holding their default value are
+    /// left out and elements follow the order of the schema, so parsing the output gives
+    /// an equal `MPD`.
+    pub fn to_xml(&self) -> Result<String, Error> {
+        xml::to_string(self)
+    }
+
+    /// Checks the constraints the schema can't express.
+    pub fn validate(&self) -> Result<(), MPDError> {
+        if self.is_static()
+            && self.media_presentation_duration.is_none()
+            && self
+                .periods
+                .last()
+                .is_none_or(|period| period.duration().is_none())
+        {
+            return Err(MPDError::MissingMediaPresentationDuration);
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MPD {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Error> {
+        let mpd: MPD = xml::from_str(source)?;
+        mpd.validate()?;
+        Ok(mpd)
+    }
+}
+
 impl fmt::Display for MPD {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "## MPD ")?;
 
-        if self.minimum_update_period.is_some() {
-            writeln!(f, "Refresh period: {}", self.minimum_update_period.unwrap())?;
+        if let Some(refresh_period) = self.refresh_period() {
+            writeln!(f, "Refresh period: {}", refresh_period)?;
         }
 
-        if self.min_buffer_time.is_some() {
-            writeln!(f, "Minimum Buffer Time: {}", self.min_buffer_time.unwrap())?;
+        if let Some(duration) = self.media_presentation_duration {
+            writeln!(f, "Duration: {}", duration)?;
+        }
+
+        if let Some(min_buffer_time) = self.min_buffer_time {
+            writeln!(f, "Minimum Buffer Time: {}", min_buffer_time)?;
         }
 
         // Customize so only `x` and `y` are denoted.
@@ -110,8 +490,8 @@ impl fmt::Display for MPD {
             display_vec_with_commat(f, &self.base_url)?;
         }
 
-        if self.publish_time.is_some() {
-            writeln!(f, "base publish_time: {}", self.publish_time.unwrap())?;
+        if let Some(publish_time) = self.publish_time {
+            writeln!(f, "base publish_time: {}", publish_time)?;
         }
 
         match (&self.availability_start_time, &self.availability_end_time) {
@@ -124,6 +504,291 @@ impl fmt::Display for MPD {
         writeln!(f, "### Periods ")?;
         display_vec_with_sections(f, &self.periods)?;
 
+        if self.is_static() {
+            let timeline = self.period_timeline();
+            for index in 0..self.periods.len() {
+                if let Some(duration) = self.period_duration_in(&timeline, index) {
+                    writeln!(f, "Period #{} duration: {}", index, duration)?;
+                }
+            }
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::ProtectionAttribut;
+
+    const STATIC_MPD: &str = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M">
+        <Period id="1" start="PT0S"></Period>
+        <Period id="2" start="PT20S"></Period>
+    </MPD>"#;
+
+    #[test]
+    fn should_default_to_static_type() {
+        let mpd: MPD = STATIC_MPD.parse().unwrap();
+        assert_eq!(mpd.r#type, MPDType::Static);
+        assert!(mpd.is_static());
+        assert!(mpd.validate().is_ok());
+    }
+
+    #[test]
+    fn should_infer_period_duration_from_next_period_and_presentation_duration() {
+        let mpd: MPD = STATIC_MPD.parse().unwrap();
+        assert_eq!(mpd.period_duration(0), Some(Duration::seconds(20)));
+        assert_eq!(mpd.period_duration(1), Some(Duration::seconds(40)));
+        assert_eq!(mpd.period_duration(2), None);
+    }
+
+    #[test]
+    fn should_infer_period_duration_from_inferred_start() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M">
+            <Period id="1" duration="PT20S"></Period>
+            <Period id="2"></Period>
+        </MPD>"#
+            .parse()
+            .unwrap();
+        assert_eq!(mpd.period_duration(1), Some(Duration::seconds(40)));
+    }
+
+    #[test]
+    fn should_reject_negative_period_durations() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M">
+            <Period id="1" start="PT30S"></Period>
+            <Period id="2" start="PT20S"></Period>
+        </MPD>"#
+            .parse()
+            .unwrap();
+        assert_eq!(mpd.period_duration(0), None);
+        assert_eq!(mpd.period_duration(1), Some(Duration::seconds(40)));
+    }
+
+    #[test]
+    fn should_infer_period_start_from_previous_period() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M">
+        <Period id="1" duration="PT15S"></Period>
+        <Period id="2" duration="PT25S"></Period>
+        <Period id="3"></Period>
+    </MPD>"#
+            .parse()
+            .unwrap();
+        assert_eq!(mpd.period_start(0), Some(Duration::zero()));
+        assert_eq!(mpd.period_start(2), Some(Duration::seconds(40)));
+        assert_eq!(mpd.period_start(3), None);
+    }
+
+    #[test]
+    fn should_ignore_minimum_update_period_for_static_mpd() {
+        let mpd: MPD = r#"<MPD type="static" profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M" minimumUpdatePeriod="PT2S">
+                <Period id="1"></Period>
+            </MPD>"#
+            .parse()
+            .unwrap();
+        assert_eq!(mpd.refresh_period(), None);
+    }
+
+    #[test]
+    fn should_read_bundled_manifest() {
+        let mpd = bundled_manifest();
+        assert!(mpd.is_dynamic());
+        assert_eq!(mpd.periods().len(), 1);
+        assert_eq!(mpd.periods()[0].adaptations().len(), 4);
+        assert_eq!(mpd.periods()[0].adaptations()[0].representations().len(), 5);
+    }
+
+    #[test]
+    fn should_read_bytes_with_bom_declaration_comments_and_cdata() {
+        let source = "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!-- generated by a packager -->
+<MPD profiles=\"urn:mpeg:dash:profile:isoff-on-demand:2011\" mediaPresentationDuration=\"PT1M\">
+    <!-- main content -->
+    <BaseURL><![CDATA[http://cdn.example.com/?a=1&b=2]]></BaseURL>
+    <Period id=\"1\"></Period>
+</MPD>
+";
+        let mpd = MPD::from_bytes(source.as_bytes()).unwrap();
+        assert_eq!(mpd.base_url()[0].url(), "http://cdn.example.com/?a=1&b=2");
+        assert_eq!(mpd, source.parse::<MPD>().unwrap());
+
+        let utf16: Vec<u8> = source
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        assert_eq!(mpd, MPD::from_bytes(&utf16).unwrap());
+    }
+
+    #[test]
+    fn should_keep_line_breaks_in_text_content() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M">
+    <Period id="1">
+        <EventStream schemeIdUri="urn:example:events">
+            <Event id="1">first line
+second line</Event>
+        </EventStream>
+        <AdaptationSet>
+            <ContentProtection schemeIdUri="urn:uuid:edef8ba9-79d6-4ace-a3c8-27dcd51d21ed">
+                <cenc:pssh>AAAAbXBzc2gAAAAA
+7e+LqXnWSs6jyCfc</cenc:pssh>
+            </ContentProtection>
+        </AdaptationSet>
+    </Period>
+</MPD>"#
+            .parse()
+            .unwrap();
+        let period = &mpd.periods()[0];
+        assert_eq!(
+            period.event_stream()[0].events()[0].content(),
+            "first line\nsecond line"
+        );
+        assert_eq!(
+            period.adaptations()[0].content_protection()[0].protection_key(),
+            &[ProtectionAttribut::CencPssh(
+                "AAAAbXBzc2gAAAAA\n7e+LqXnWSs6jyCfc".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn should_fail_to_validate_static_mpd_without_duration() {
+        let mpd: MPD = xml::from_str(
+            r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011">
+                <Period id="1" start="PT0S"></Period>
+            </MPD>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            mpd.validate().unwrap_err().to_string(),
+            "Invalid MPD: static MPD should define mediaPresentationDuration or the duration of its last Period"
+        );
+    }
+
+    /// Source of the live manifest in `resources`, shared by the tests of several modules.
+    pub(crate) const BUNDLED_MANIFEST: &str = include_str!("../../resources/hdeindex-1.mpd");
+
+    /// [`BUNDLED_MANIFEST`] read from its file.
+    pub(crate) fn bundled_manifest() -> MPD {
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/hdeindex-1.mpd");
+        let file = std::fs::File::open(path).unwrap();
+        MPD::from_reader(std::io::BufReader::new(file)).unwrap()
+    }
+
+    #[test]
+    fn should_parse_written_manifest_to_equal_mpd() {
+        let mpd = bundled_manifest();
+        let written = mpd.to_xml().unwrap();
+        assert!(written.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<MPD "));
+        assert_eq!(mpd, written.parse::<MPD>().unwrap());
+    }
+
+    #[test]
+    fn should_keep_namespace_declarations_and_schema_location() {
+        let mpd = bundled_manifest();
+        assert_eq!(mpd.namespaces()["cenc"], "urn:mpeg:cenc:2013");
+        assert_eq!(mpd.namespaces()[""], "urn:mpeg:dash:schema:mpd:2011");
+        let written = mpd.to_xml().unwrap();
+        assert!(written.contains(r#" xmlns="urn:mpeg:dash:schema:mpd:2011""#));
+        assert!(written.contains(r#" xmlns:cenc="urn:mpeg:cenc:2013""#));
+        assert!(written.contains(r#" xmlns:mspr="urn:microsoft:playready""#));
+        assert!(
+            written.contains(r#" xsi:schemaLocation="urn:mpeg:dash:schema:mpd:2011 DASH-MPD.xsd""#)
+        );
+        assert!(written.contains(r#"<ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011" value="cenc" cenc:default_KID="447d7f4a-f09f-3e48-95e4-960e536d995a"/>"#));
+    }
+
+    #[test]
+    fn should_leave_out_default_attributes() {
+        let written = bundled_manifest().to_xml().unwrap();
+        for attribute in [
+            "xlink:href=",
+            "xlink:actuate=",
+            "bitstreamSwitching=",
+            "subsegmentAlignment=",
+            "scanType=",
+            "maxPlayoutRate=",
+            "width=\"0\"",
+            "=\"\"",
+        ] {
+            assert!(!written.contains(attribute), "{} written", attribute);
+        }
+        assert!(written.contains(r#"segmentAlignment="true""#));
+        assert!(written.contains(r#"<MPD type="dynamic""#));
+    }
+
+    #[test]
+    fn should_write_elements_in_schema_order() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT1M">
+    <Period id="1" duration="PT1M">
+        <AdaptationSet>
+            <Representation id="1" bandwidth="1">
+                <SegmentBase indexRange="0-10"/>
+                <SubRepresentation level="1" bandwidth="1"/>
+                <BaseURL>video/</BaseURL>
+            </Representation>
+            <SegmentTemplate timescale="1000"/>
+            <Role schemeIdUri="urn:mpeg:dash:role:2011" value="main"/>
+            <ContentProtection schemeIdUri="urn:mpeg:dash:mp4protection:2011" value="cenc"/>
+        </AdaptationSet>
+    </Period>
+    <BaseURL>http://cdn.example.com/</BaseURL>
+</MPD>"#
+            .parse()
+            .unwrap();
+        let written = mpd.to_xml().unwrap();
+        let position = |pattern: &str| written.find(pattern).unwrap();
+        assert!(position("<BaseURL>http") < position("<Period "));
+        assert!(position("<ContentProtection ") < position("<Role "));
+        assert!(position("<Role ") < position("<SegmentTemplate "));
+        assert!(position("<SegmentTemplate ") < position("<Representation "));
+        assert!(position("<BaseURL>video/") < position("<SubRepresentation "));
+        assert!(position("<SubRepresentation ") < position("<SegmentBase "));
+        assert_eq!(mpd, written.parse::<MPD>().unwrap());
+    }
+
+    #[test]
+    fn should_escape_written_text_and_attributes() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT10S">
+    <BaseURL serviceLocation="a&amp;b &quot;c&quot;"><![CDATA[http://cdn.example.com/?a=1&b=<2>]]></BaseURL>
+    <Period id="1">
+        <EventStream schemeIdUri="urn:example:events" value="line&#10;break">
+            <Event id="1">first line
+second &lt;line&gt;</Event>
+        </EventStream>
+    </Period>
+</MPD>"#
+            .parse()
+            .unwrap();
+        let written = mpd.to_xml().unwrap();
+        assert!(written.contains("http://cdn.example.com/?a=1&amp;b=&lt;2&gt;"));
+        assert!(written.contains(r#"mediaPresentationDuration="PT10S""#));
+        assert_eq!(mpd, written.parse::<MPD>().unwrap());
+    }
+
+    #[test]
+    fn should_write_programme_information_titles_as_elements() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT10S">
+    <ProgramInformation lang="en" moreInformationURL="http://example.com/info">
+        <Title>News &amp; weather</Title>
+        <Source>Example</Source>
+        <Copyright>2024 Example</Copyright>
+    </ProgramInformation>
+    <Period id="1"></Period>
+</MPD>"#
+            .parse()
+            .unwrap();
+        let information = &mpd.programme_information()[0];
+        assert_eq!(information.lang(), "en");
+        assert_eq!(information.title(), "News & weather");
+        let written = mpd.to_xml().unwrap();
+        assert!(written.contains(
+            r#"<ProgramInformation lang="en" moreInformationURL="http://example.com/info">"#
+        ));
+        assert!(written.contains("<Title>News &amp; weather</Title>"));
+        assert!(written.contains("<Source>Example</Source>"));
+        assert!(written.contains("<Copyright>2024 Example</Copyright>"));
+        assert_eq!(mpd, written.parse::<MPD>().unwrap());
+    }
+}
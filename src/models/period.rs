@@ -1,25 +1,38 @@
-use super::{representations::AdaptationSet, segments::*, utils::*};
-use crate::serde_custom::duration_iso_8601;
+use super::{
+    extensions::Extensions,
+    representations::{AdaptationSet, Representation},
+    segments::*,
+    utils::*,
+};
+use crate::serde_custom::{duration_iso_8601, is_default};
 use chrono::Duration;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::Formatter;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Period {
     // attribut
-    #[serde(rename = "xlink:href", default)]
+    #[serde(rename = "xlink:href", default, skip_serializing_if = "is_default")]
     href: String,
-    #[serde(rename = "xlink:actuate", default = "HrefActuate::default")]
+    #[serde(
+        rename = "xlink:actuate",
+        default = "HrefActuate::default",
+        skip_serializing_if = "is_default"
+    )]
     href_actuate: HrefActuate,
-    #[serde(rename = "id", default)]
+    #[serde(rename = "id", default, skip_serializing_if = "is_default")]
     id: String,
     #[serde(rename = "start", with = "duration_iso_8601", default)]
     start: Option<Duration>,
     #[serde(rename = "duration", with = "duration_iso_8601", default)]
     duration: Option<Duration>,
-    #[serde(rename = "bitstreamSwitching", default)]
+    #[serde(
+        rename = "bitstreamSwitching",
+        default,
+        skip_serializing_if = "is_default"
+    )]
     bitstream_switching: bool,
     // child
     #[serde(rename = "BaseURL", default)]
@@ -38,18 +51,149 @@ pub struct Period {
     adaptations: Vec<AdaptationSet>,
     #[serde(rename = "Subset", default)]
     subsets: Vec<Subset>,
+    #[serde(
+        rename = "$extensions",
+        default,
+        skip_serializing_if = "Extensions::is_empty"
+    )]
+    extensions: Extensions,
+}
+
+impl Period {
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+
+    pub fn href_actuate(&self) -> HrefActuate {
+        self.href_actuate
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn start(&self) -> Option<Duration> {
+        self.start
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub fn bitstream_switching(&self) -> bool {
+        self.bitstream_switching
+    }
+
+    pub fn base_urls(&self) -> &[BaseURL] {
+        &self.base_urls
+    }
+
+    pub fn base_urls_mut(&mut self) -> &mut Vec<BaseURL> {
+        &mut self.base_urls
+    }
+
+    pub fn segment_base(&self) -> Option<&SegmentBase> {
+        self.segment_base.as_ref()
+    }
+
+    pub fn segment_base_mut(&mut self) -> Option<&mut SegmentBase> {
+        self.segment_base.as_mut()
+    }
+
+    pub fn segment_list(&self) -> Option<&SegmentList> {
+        self.segment_list.as_ref()
+    }
+
+    pub fn segment_list_mut(&mut self) -> Option<&mut SegmentList> {
+        self.segment_list.as_mut()
+    }
+
+    pub fn segment_template(&self) -> Option<&SegmentTemplate> {
+        self.segment_template.as_ref()
+    }
+
+    pub fn segment_template_mut(&mut self) -> Option<&mut SegmentTemplate> {
+        self.segment_template.as_mut()
+    }
+
+    pub fn asset_identifier(&self) -> Option<&Descriptor> {
+        self.asset_identifier.as_ref()
+    }
+
+    pub fn asset_identifier_mut(&mut self) -> Option<&mut Descriptor> {
+        self.asset_identifier.as_mut()
+    }
+
+    pub fn event_stream(&self) -> &[EventStream] {
+        &self.event_stream
+    }
+
+    pub fn event_stream_mut(&mut self) -> &mut Vec<EventStream> {
+        &mut self.event_stream
+    }
+
+    /// Segment information in effect for `representation` of `adaptation`, a set of
+    /// this period, the lower levels inheriting from the higher ones.
+    pub fn segment_information(
+        &self,
+        adaptation: &AdaptationSet,
+        representation: &Representation,
+    ) -> Option<SegmentInformation> {
+        SegmentInformation::resolve(&[
+            (
+                self.segment_base(),
+                self.segment_list(),
+                self.segment_template(),
+            ),
+            (
+                adaptation.segment_base(),
+                adaptation.segment_list(),
+                adaptation.segment_template(),
+            ),
+            (
+                representation.segment_base(),
+                representation.segment_list(),
+                representation.segment_template(),
+            ),
+        ])
+    }
+
+    pub fn adaptations(&self) -> &[AdaptationSet] {
+        &self.adaptations
+    }
+
+    pub fn adaptations_mut(&mut self) -> &mut Vec<AdaptationSet> {
+        &mut self.adaptations
+    }
+
+    pub fn subsets(&self) -> &[Subset] {
+        &self.subsets
+    }
+
+    pub fn subsets_mut(&mut self) -> &mut Vec<Subset> {
+        &mut self.subsets
+    }
+
+    /// Attributes and children the model doesn't know.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
 }
 
 impl fmt::Display for Period {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         writeln!(f, "Period ID : {}", &self.id)?;
 
-        if self.start.is_some() {
-            write!(f, "Start : {}", self.start.unwrap())?;
+        if let Some(start) = self.start {
+            write!(f, "Start : {}", start)?;
         }
 
-        if self.duration.is_some() {
-            write!(f, "Duration : {}", self.duration.unwrap())?;
+        if let Some(duration) = self.duration {
+            write!(f, "Duration : {}", duration)?;
         }
 
         Ok(())
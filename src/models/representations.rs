@@ -1,7 +1,8 @@
-use crate::serde_custom::{conditional_uint, tags};
+use crate::serde_custom::{conditional_uint, is_default, tags};
 use either::Either;
 use serde::{Deserialize, Serialize};
 
+use super::extensions::Extensions;
 use super::segments::*;
 use super::utils::*;
 
@@ -10,8 +11,12 @@ fn either_default() -> Either<u64, bool> {
     EITHER_DEFAULT
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-enum ProtectionAttribut {
+fn is_either_default(value: &Either<u64, bool>) -> bool {
+    *value == EITHER_DEFAULT
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ProtectionAttribut {
     #[serde(rename = "cenc:pssh")]
     CencPssh(String),
     #[serde(rename = "mspr:pro")]
@@ -22,92 +27,163 @@ enum ProtectionAttribut {
     DashifLaurl(String),
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ContentProtection {
     scheme_id_uri: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     value: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     id: String,
     #[serde(rename = "cenc:default_KID", default)]
     default_kid: Option<String>,
     #[serde(rename = "$value", default)]
     protection_key: Vec<ProtectionAttribut>,
+    #[serde(
+        rename = "$extensions",
+        default,
+        skip_serializing_if = "Extensions::is_empty"
+    )]
+    extensions: Extensions,
+}
+
+impl ContentProtection {
+    pub fn scheme_id_uri(&self) -> &str {
+        &self.scheme_id_uri
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn default_kid(&self) -> Option<&str> {
+        self.default_kid.as_deref()
+    }
+
+    pub fn protection_key(&self) -> &[ProtectionAttribut] {
+        &self.protection_key
+    }
+
+    pub fn protection_key_mut(&mut self) -> &mut Vec<ProtectionAttribut> {
+        &mut self.protection_key
+    }
+
+    /// Attributes and children the model doesn't know.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct AdaptationSet {
     // attribut
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     profiles: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     width: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     height: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     sar: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     frame_rate: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     audio_sampling_rate: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     mime_type: String,
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     segment_profiles: Vec<String>,
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     codecs: Vec<String>,
-    #[serde(rename = "maximumSAPPeriod", default)]
+    #[serde(
+        rename = "maximumSAPPeriod",
+        default,
+        skip_serializing_if = "is_default"
+    )]
     maximum_sap_period: f64,
-    #[serde(rename = "startWithSAP", default)]
+    #[serde(rename = "startWithSAP", default, skip_serializing_if = "is_default")]
     // value between 0 and 6
     start_with_sap: u8,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     max_playout_rate: f64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     coding_dependency: bool,
-    #[serde(default = "VideoScan::default")]
+    #[serde(default = "VideoScan::default", skip_serializing_if = "is_default")]
     scan_type: VideoScan,
-    #[serde(rename = "xlink:href", default)]
+    #[serde(rename = "xlink:href", default, skip_serializing_if = "is_default")]
     href: String,
-    #[serde(rename = "xlink:actuate", default = "HrefActuate::default")]
+    #[serde(
+        rename = "xlink:actuate",
+        default = "HrefActuate::default",
+        skip_serializing_if = "is_default"
+    )]
     href_actuate: HrefActuate,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     id: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     group: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     lang: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     content_type: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     par: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     max_bandwidth: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     min_width: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     max_width: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     min_height: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     max_height: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     min_frame_rate: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     max_frame_rate: String,
-    #[serde(with = "conditional_uint", default = "either_default")]
+    #[serde(
+        with = "conditional_uint",
+        default = "either_default",
+        skip_serializing_if = "is_either_default"
+    )]
     segment_alignment: Either<u64, bool>,
-    #[serde(with = "conditional_uint", default = "either_default")]
+    #[serde(
+        with = "conditional_uint",
+        default = "either_default",
+        skip_serializing_if = "is_either_default"
+    )]
     subsegment_alignment: Either<u64, bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     // value between 0 and 6
     subsegment_starts_with_sap: u8,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     bitstream_switching: bool,
 
     // children
+    #[serde(rename = "FramePacking", default)]
+    frame_packing: Vec<Descriptor>,
+    #[serde(rename = "AudioChannelConfiguration", default)]
+    audio_channel_configuration: Vec<Descriptor>,
+    #[serde(rename = "ContentProtection", default)]
+    content_protection: Vec<ContentProtection>,
+    #[serde(rename = "EssentialProperty", default)]
+    essential_property: Vec<Descriptor>,
+    #[serde(rename = "SupplementalProperty", default)]
+    supplemental_property: Vec<Descriptor>,
+    #[serde(rename = "InbandEventStream", default)]
+    inband_event_stream: Vec<EventStream>,
+    #[serde(rename = "Switching", default)]
+    switching: Vec<Switching>,
     #[serde(rename = "Accessibility", default)]
     accessibilities: Vec<Descriptor>,
     #[serde(rename = "Role", default)]
@@ -118,8 +194,6 @@ pub struct AdaptationSet {
     viewpoints: Vec<Descriptor>,
     #[serde(rename = "ContentComponent", default)]
     content_components: Vec<ContentComponent>,
-    #[serde(rename = "Representation", default)]
-    representations: Vec<Representation>,
     #[serde(rename = "BaseURL", default)]
     base_urls: Vec<BaseURL>,
     #[serde(rename = "SegmentBase", default)]
@@ -128,59 +202,324 @@ pub struct AdaptationSet {
     segment_list: Option<SegmentList>,
     #[serde(rename = "SegmentTemplate", default)]
     segment_template: Option<SegmentTemplate>,
-    #[serde(rename = "FramePacking", default)]
-    frame_packing: Vec<Descriptor>,
-    #[serde(rename = "AudioChannelConfiguration", default)]
-    audio_channel_configuration: Vec<Descriptor>,
-    #[serde(rename = "ContentProtection", default)]
-    content_protection: Vec<ContentProtection>,
-    #[serde(rename = "EssentialProperty", default)]
-    essential_property: Vec<Descriptor>,
-    #[serde(rename = "SupplementalProperty", default)]
-    supplemental_property: Vec<Descriptor>,
-    #[serde(rename = "InbandEventStream", default)]
-    inband_event_stream: Vec<EventStream>,
-    #[serde(rename = "Switching", default)]
-    switching: Vec<Switching>,
+    #[serde(rename = "Representation", default)]
+    representations: Vec<Representation>,
+    #[serde(
+        rename = "$extensions",
+        default,
+        skip_serializing_if = "Extensions::is_empty"
+    )]
+    extensions: Extensions,
+}
+
+impl AdaptationSet {
+    pub fn profiles(&self) -> &[String] {
+        &self.profiles
+    }
+
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn sar(&self) -> &str {
+        &self.sar
+    }
+
+    pub fn frame_rate(&self) -> &str {
+        &self.frame_rate
+    }
+
+    pub fn audio_sampling_rate(&self) -> &str {
+        &self.audio_sampling_rate
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn segment_profiles(&self) -> &[String] {
+        &self.segment_profiles
+    }
+
+    pub fn codecs(&self) -> &[String] {
+        &self.codecs
+    }
+
+    pub fn maximum_sap_period(&self) -> f64 {
+        self.maximum_sap_period
+    }
+
+    pub fn start_with_sap(&self) -> u8 {
+        self.start_with_sap
+    }
+
+    pub fn max_playout_rate(&self) -> f64 {
+        self.max_playout_rate
+    }
+
+    pub fn coding_dependency(&self) -> bool {
+        self.coding_dependency
+    }
+
+    pub fn scan_type(&self) -> VideoScan {
+        self.scan_type
+    }
+
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+
+    pub fn href_actuate(&self) -> HrefActuate {
+        self.href_actuate
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    pub fn par(&self) -> &str {
+        &self.par
+    }
+
+    pub fn max_bandwidth(&self) -> u64 {
+        self.max_bandwidth
+    }
+
+    pub fn min_width(&self) -> u64 {
+        self.min_width
+    }
+
+    pub fn max_width(&self) -> u64 {
+        self.max_width
+    }
+
+    pub fn min_height(&self) -> u64 {
+        self.min_height
+    }
+
+    pub fn max_height(&self) -> u64 {
+        self.max_height
+    }
+
+    pub fn min_frame_rate(&self) -> &str {
+        &self.min_frame_rate
+    }
+
+    pub fn max_frame_rate(&self) -> &str {
+        &self.max_frame_rate
+    }
+
+    pub fn subsegment_starts_with_sap(&self) -> u8 {
+        self.subsegment_starts_with_sap
+    }
+
+    pub fn bitstream_switching(&self) -> bool {
+        self.bitstream_switching
+    }
+
+    pub fn accessibilities(&self) -> &[Descriptor] {
+        &self.accessibilities
+    }
+
+    pub fn accessibilities_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.accessibilities
+    }
+
+    pub fn roles(&self) -> &[Descriptor] {
+        &self.roles
+    }
+
+    pub fn roles_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.roles
+    }
+
+    pub fn ratings(&self) -> &[Descriptor] {
+        &self.ratings
+    }
+
+    pub fn ratings_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.ratings
+    }
+
+    pub fn viewpoints(&self) -> &[Descriptor] {
+        &self.viewpoints
+    }
+
+    pub fn viewpoints_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.viewpoints
+    }
+
+    pub fn content_components(&self) -> &[ContentComponent] {
+        &self.content_components
+    }
+
+    pub fn content_components_mut(&mut self) -> &mut Vec<ContentComponent> {
+        &mut self.content_components
+    }
+
+    pub fn representations(&self) -> &[Representation] {
+        &self.representations
+    }
+
+    pub fn representations_mut(&mut self) -> &mut Vec<Representation> {
+        &mut self.representations
+    }
+
+    pub fn base_urls(&self) -> &[BaseURL] {
+        &self.base_urls
+    }
+
+    pub fn base_urls_mut(&mut self) -> &mut Vec<BaseURL> {
+        &mut self.base_urls
+    }
+
+    pub fn segment_base(&self) -> Option<&SegmentBase> {
+        self.segment_base.as_ref()
+    }
+
+    pub fn segment_base_mut(&mut self) -> Option<&mut SegmentBase> {
+        self.segment_base.as_mut()
+    }
+
+    pub fn segment_list(&self) -> Option<&SegmentList> {
+        self.segment_list.as_ref()
+    }
+
+    pub fn segment_list_mut(&mut self) -> Option<&mut SegmentList> {
+        self.segment_list.as_mut()
+    }
+
+    pub fn segment_template(&self) -> Option<&SegmentTemplate> {
+        self.segment_template.as_ref()
+    }
+
+    pub fn segment_template_mut(&mut self) -> Option<&mut SegmentTemplate> {
+        self.segment_template.as_mut()
+    }
+
+    pub fn frame_packing(&self) -> &[Descriptor] {
+        &self.frame_packing
+    }
+
+    pub fn frame_packing_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.frame_packing
+    }
+
+    pub fn audio_channel_configuration(&self) -> &[Descriptor] {
+        &self.audio_channel_configuration
+    }
+
+    pub fn audio_channel_configuration_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.audio_channel_configuration
+    }
+
+    pub fn content_protection(&self) -> &[ContentProtection] {
+        &self.content_protection
+    }
+
+    pub fn content_protection_mut(&mut self) -> &mut Vec<ContentProtection> {
+        &mut self.content_protection
+    }
+
+    pub fn essential_property(&self) -> &[Descriptor] {
+        &self.essential_property
+    }
+
+    pub fn essential_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.essential_property
+    }
+
+    pub fn supplemental_property(&self) -> &[Descriptor] {
+        &self.supplemental_property
+    }
+
+    pub fn supplemental_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.supplemental_property
+    }
+
+    pub fn inband_event_stream(&self) -> &[EventStream] {
+        &self.inband_event_stream
+    }
+
+    pub fn inband_event_stream_mut(&mut self) -> &mut Vec<EventStream> {
+        &mut self.inband_event_stream
+    }
+
+    pub fn switching(&self) -> &[Switching] {
+        &self.switching
+    }
+
+    pub fn switching_mut(&mut self) -> &mut Vec<Switching> {
+        &mut self.switching
+    }
+
+    /// Attributes and children the model doesn't know.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
-struct Representation {
+pub struct Representation {
     // attribut
     id: String,
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     profiles: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     width: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     height: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     sar: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     frame_rate: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     audio_sampling_rate: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     mime_type: String,
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     segment_profiles: Vec<String>,
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     codecs: Vec<String>,
-    #[serde(rename = "maximumSAPPeriod", default)]
+    #[serde(
+        rename = "maximumSAPPeriod",
+        default,
+        skip_serializing_if = "is_default"
+    )]
     maximum_sap_period: f64,
-    #[serde(rename = "startWithSAP", default)]
+    #[serde(rename = "startWithSAP", default, skip_serializing_if = "is_default")]
     // value between 0 and 6
     start_with_sap: u8,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     max_playout_rate: f64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     coding_dependency: bool,
-    #[serde(default = "VideoScan::default")]
+    #[serde(default = "VideoScan::default", skip_serializing_if = "is_default")]
     scan_type: VideoScan,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     bandwidth: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     quality_ranking: u64,
     #[serde(default)]
     dependency_id: Vec<String>,
@@ -188,16 +527,6 @@ struct Representation {
     media_stream_structure_id: Vec<String>,
 
     // children
-    #[serde(rename = "SubRepresentationType", default)]
-    sub_representations: Vec<SubRepresentation>,
-    #[serde(rename = "BaseURL", default)]
-    base_urls: Vec<BaseURL>,
-    #[serde(rename = "SegmentBase", default)]
-    segment_base: Option<SegmentBase>,
-    #[serde(rename = "SegmentList", default)]
-    segment_list: Option<SegmentList>,
-    #[serde(rename = "SegmentTemplate", default)]
-    segment_template: Option<SegmentTemplate>,
     #[serde(rename = "FramePacking", default)]
     frame_packing: Vec<Descriptor>,
     #[serde(rename = "AudioChannelConfiguration", default)]
@@ -212,47 +541,249 @@ struct Representation {
     inband_event_stream: Vec<EventStream>,
     #[serde(rename = "Switching", default)]
     switching: Vec<Switching>,
+    #[serde(rename = "BaseURL", default)]
+    base_urls: Vec<BaseURL>,
+    #[serde(rename = "SubRepresentation", default)]
+    sub_representations: Vec<SubRepresentation>,
+    #[serde(rename = "SegmentBase", default)]
+    segment_base: Option<SegmentBase>,
+    #[serde(rename = "SegmentList", default)]
+    segment_list: Option<SegmentList>,
+    #[serde(rename = "SegmentTemplate", default)]
+    segment_template: Option<SegmentTemplate>,
+    #[serde(
+        rename = "$extensions",
+        default,
+        skip_serializing_if = "Extensions::is_empty"
+    )]
+    extensions: Extensions,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "camelCase")]
+impl Representation {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn profiles(&self) -> &[String] {
+        &self.profiles
+    }
+
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn sar(&self) -> &str {
+        &self.sar
+    }
+
+    pub fn frame_rate(&self) -> &str {
+        &self.frame_rate
+    }
+
+    pub fn audio_sampling_rate(&self) -> &str {
+        &self.audio_sampling_rate
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn segment_profiles(&self) -> &[String] {
+        &self.segment_profiles
+    }
+
+    pub fn codecs(&self) -> &[String] {
+        &self.codecs
+    }
+
+    pub fn maximum_sap_period(&self) -> f64 {
+        self.maximum_sap_period
+    }
+
+    pub fn start_with_sap(&self) -> u8 {
+        self.start_with_sap
+    }
+
+    pub fn max_playout_rate(&self) -> f64 {
+        self.max_playout_rate
+    }
+
+    pub fn coding_dependency(&self) -> bool {
+        self.coding_dependency
+    }
+
+    pub fn scan_type(&self) -> VideoScan {
+        self.scan_type
+    }
+
+    pub fn bandwidth(&self) -> u64 {
+        self.bandwidth
+    }
+
+    pub fn quality_ranking(&self) -> u64 {
+        self.quality_ranking
+    }
+
+    pub fn dependency_id(&self) -> &[String] {
+        &self.dependency_id
+    }
+
+    pub fn media_stream_structure_id(&self) -> &[String] {
+        &self.media_stream_structure_id
+    }
+
+    pub fn sub_representations(&self) -> &[SubRepresentation] {
+        &self.sub_representations
+    }
+
+    pub fn sub_representations_mut(&mut self) -> &mut Vec<SubRepresentation> {
+        &mut self.sub_representations
+    }
+
+    pub fn base_urls(&self) -> &[BaseURL] {
+        &self.base_urls
+    }
+
+    pub fn base_urls_mut(&mut self) -> &mut Vec<BaseURL> {
+        &mut self.base_urls
+    }
+
+    pub fn segment_base(&self) -> Option<&SegmentBase> {
+        self.segment_base.as_ref()
+    }
+
+    pub fn segment_base_mut(&mut self) -> Option<&mut SegmentBase> {
+        self.segment_base.as_mut()
+    }
+
+    pub fn segment_list(&self) -> Option<&SegmentList> {
+        self.segment_list.as_ref()
+    }
+
+    pub fn segment_list_mut(&mut self) -> Option<&mut SegmentList> {
+        self.segment_list.as_mut()
+    }
 
-struct SubRepresentation {
+    pub fn segment_template(&self) -> Option<&SegmentTemplate> {
+        self.segment_template.as_ref()
+    }
+
+    pub fn segment_template_mut(&mut self) -> Option<&mut SegmentTemplate> {
+        self.segment_template.as_mut()
+    }
+
+    pub fn frame_packing(&self) -> &[Descriptor] {
+        &self.frame_packing
+    }
+
+    pub fn frame_packing_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.frame_packing
+    }
+
+    pub fn audio_channel_configuration(&self) -> &[Descriptor] {
+        &self.audio_channel_configuration
+    }
+
+    pub fn audio_channel_configuration_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.audio_channel_configuration
+    }
+
+    pub fn content_protection(&self) -> &[ContentProtection] {
+        &self.content_protection
+    }
+
+    pub fn content_protection_mut(&mut self) -> &mut Vec<ContentProtection> {
+        &mut self.content_protection
+    }
+
+    pub fn essential_property(&self) -> &[Descriptor] {
+        &self.essential_property
+    }
+
+    pub fn essential_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.essential_property
+    }
+
+    pub fn supplemental_property(&self) -> &[Descriptor] {
+        &self.supplemental_property
+    }
+
+    pub fn supplemental_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.supplemental_property
+    }
+
+    pub fn inband_event_stream(&self) -> &[EventStream] {
+        &self.inband_event_stream
+    }
+
+    pub fn inband_event_stream_mut(&mut self) -> &mut Vec<EventStream> {
+        &mut self.inband_event_stream
+    }
+
+    pub fn switching(&self) -> &[Switching] {
+        &self.switching
+    }
+
+    pub fn switching_mut(&mut self) -> &mut Vec<Switching> {
+        &mut self.switching
+    }
+
+    /// Attributes and children the model doesn't know.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubRepresentation {
     // attribut
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     profiles: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     width: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     height: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     sar: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     frame_rate: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     audio_sampling_rate: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     mime_type: String,
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     segment_profiles: Vec<String>,
-    #[serde(with = "tags", default)]
+    #[serde(with = "tags", default, skip_serializing_if = "Vec::is_empty")]
     codecs: Vec<String>,
-    #[serde(rename = "maximumSAPPeriod", default)]
+    #[serde(
+        rename = "maximumSAPPeriod",
+        default,
+        skip_serializing_if = "is_default"
+    )]
     maximum_sap_period: f64,
-    #[serde(rename = "startWithSAP", default)]
+    #[serde(rename = "startWithSAP", default, skip_serializing_if = "is_default")]
     // value between 0 and 6
     start_with_sap: u8,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     max_playout_rate: f64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     coding_dependency: bool,
-    #[serde(default = "VideoScan::default")]
+    #[serde(default = "VideoScan::default", skip_serializing_if = "is_default")]
     scan_type: VideoScan,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     level: u64,
     #[serde(default)]
     dependency_level: Vec<u64>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     bandwidth: u64,
     #[serde(default)]
     content_component: Vec<String>,
@@ -272,3 +803,133 @@ struct SubRepresentation {
     #[serde(rename = "Switching", default)]
     switching: Vec<Switching>,
 }
+
+impl SubRepresentation {
+    pub fn profiles(&self) -> &[String] {
+        &self.profiles
+    }
+
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn sar(&self) -> &str {
+        &self.sar
+    }
+
+    pub fn frame_rate(&self) -> &str {
+        &self.frame_rate
+    }
+
+    pub fn audio_sampling_rate(&self) -> &str {
+        &self.audio_sampling_rate
+    }
+
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    pub fn segment_profiles(&self) -> &[String] {
+        &self.segment_profiles
+    }
+
+    pub fn codecs(&self) -> &[String] {
+        &self.codecs
+    }
+
+    pub fn maximum_sap_period(&self) -> f64 {
+        self.maximum_sap_period
+    }
+
+    pub fn start_with_sap(&self) -> u8 {
+        self.start_with_sap
+    }
+
+    pub fn max_playout_rate(&self) -> f64 {
+        self.max_playout_rate
+    }
+
+    pub fn coding_dependency(&self) -> bool {
+        self.coding_dependency
+    }
+
+    pub fn scan_type(&self) -> VideoScan {
+        self.scan_type
+    }
+
+    pub fn level(&self) -> u64 {
+        self.level
+    }
+
+    pub fn dependency_level(&self) -> &[u64] {
+        &self.dependency_level
+    }
+
+    pub fn bandwidth(&self) -> u64 {
+        self.bandwidth
+    }
+
+    pub fn content_component(&self) -> &[String] {
+        &self.content_component
+    }
+
+    pub fn frame_packing(&self) -> &[Descriptor] {
+        &self.frame_packing
+    }
+
+    pub fn frame_packing_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.frame_packing
+    }
+
+    pub fn audio_channel_configuration(&self) -> &[Descriptor] {
+        &self.audio_channel_configuration
+    }
+
+    pub fn audio_channel_configuration_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.audio_channel_configuration
+    }
+
+    pub fn content_protection(&self) -> &[ContentProtection] {
+        &self.content_protection
+    }
+
+    pub fn content_protection_mut(&mut self) -> &mut Vec<ContentProtection> {
+        &mut self.content_protection
+    }
+
+    pub fn essential_property(&self) -> &[Descriptor] {
+        &self.essential_property
+    }
+
+    pub fn essential_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.essential_property
+    }
+
+    pub fn supplemental_property(&self) -> &[Descriptor] {
+        &self.supplemental_property
+    }
+
+    pub fn supplemental_property_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.supplemental_property
+    }
+
+    pub fn inband_event_stream(&self) -> &[EventStream] {
+        &self.inband_event_stream
+    }
+
+    pub fn inband_event_stream_mut(&mut self) -> &mut Vec<EventStream> {
+        &mut self.inband_event_stream
+    }
+
+    pub fn switching(&self) -> &[Switching] {
+        &self.switching
+    }
+
+    pub fn switching_mut(&mut self) -> &mut Vec<Switching> {
+        &mut self.switching
+    }
+}
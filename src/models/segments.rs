@@ -1,63 +1,389 @@
+use crate::serde_custom::is_default;
+use crate::template::{Template, TemplateError, TemplateValues};
+use chrono::Duration;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
+use super::extensions::Extensions;
 use super::utils::*;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// `S` entry of a `SegmentTimeline`, its times are in timescale units.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Segment {
-    #[serde(rename = "t", default)]
-    start: Option<f64>,
-    #[serde(default)]
-    n: f64,
-    #[serde(rename = "d")]
-    timescale: f64,
-    #[serde(rename = "t", default)]
-    repeat: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    t: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    n: Option<u64>,
+    d: u64,
+    /// Number of repetitions after the first segment, `-1` to repeat until the next `S`
+    /// or the end of the period.
+    #[serde(default, skip_serializing_if = "is_default")]
+    r: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    k: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl Segment {
+    /// Media time of the first segment, following the previous entry when absent.
+    pub fn t(&self) -> Option<u64> {
+        self.t
+    }
+
+    /// Number of the first segment, following the previous entry when absent.
+    pub fn n(&self) -> Option<u64> {
+        self.n
+    }
+
+    pub fn d(&self) -> u64 {
+        self.d
+    }
+
+    pub fn r(&self) -> i64 {
+        self.r
+    }
+
+    /// Number of segments in the segment sequence, 1 by default. It is not taken into
+    /// account when expanding the timeline, see [`TimelineSegments`].
+    pub fn k(&self) -> u64 {
+        self.k.unwrap_or(1)
+    }
+}
+
+/// Time in timescale units, converted to wall-clock time without rounding error
+/// beyond the nanosecond.
+#[derive(Debug, Clone, Copy)]
+pub struct MediaTime {
+    ticks: i64,
+    timescale: u64,
+}
+
+impl MediaTime {
+    /// A timescale of 0 is taken as 1, as for a missing `timescale` attribute.
+    pub fn new(ticks: i64, timescale: u64) -> Self {
+        MediaTime {
+            ticks,
+            timescale: timescale.max(1),
+        }
+    }
+
+    /// Last tick at or before `duration`.
+    pub fn from_duration(duration: Duration, timescale: u64) -> Self {
+        let timescale = timescale.max(1);
+        let nanos = duration_nanos(duration) * timescale as i128;
+        MediaTime::new(nanos.div_euclid(NANOS_PER_SECOND) as i64, timescale)
+    }
+
+    pub fn ticks(&self) -> i64 {
+        self.ticks
+    }
+
+    pub fn timescale(&self) -> u64 {
+        self.timescale
+    }
+
+    /// Wall-clock duration, rounded down to the nanosecond.
+    pub fn to_duration(&self) -> Duration {
+        let timescale = self.timescale as i64;
+        let seconds = self.ticks.div_euclid(timescale);
+        let remainder = self.ticks.rem_euclid(timescale) as i128;
+        Duration::seconds(seconds)
+            + Duration::nanoseconds((remainder * NANOS_PER_SECOND / timescale as i128) as i64)
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        self.ticks as f64 / self.timescale as f64
+    }
+}
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+fn duration_nanos(duration: Duration) -> i128 {
+    let seconds = duration.num_seconds();
+    let nanos = (duration - Duration::seconds(seconds))
+        .num_nanoseconds()
+        .unwrap_or(0);
+    seconds as i128 * NANOS_PER_SECOND + nanos as i128
+}
+
+/// Compares the rationals, `1/2` equals `45000/90000`.
+impl PartialEq for MediaTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for MediaTime {}
+
+impl PartialOrd for MediaTime {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MediaTime {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.ticks as i128 * other.timescale as i128)
+            .cmp(&(other.ticks as i128 * self.timescale as i128))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SegmentTimeline {
     #[serde(rename = "S")]
     segments: Vec<Segment>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl SegmentTimeline {
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn segments_mut(&mut self) -> &mut Vec<Segment> {
+        &mut self.segments
+    }
+
+    /// Expands the `S` entries into segments numbered from `start_number`, `end` being
+    /// the end of the period in timescale units relative to its start.
+    pub fn iter(
+        &self,
+        timescale: u64,
+        start_number: u64,
+        presentation_time_offset: u64,
+        end: Option<u64>,
+    ) -> TimelineSegments<'_> {
+        TimelineSegments {
+            entries: &self.segments,
+            index: 0,
+            left: Some(0),
+            number: start_number,
+            time: 0,
+            duration: 0,
+            timescale: timescale.max(1),
+            presentation_time_offset,
+            end: end.map(|end| end + presentation_time_offset),
+        }
+    }
+}
+
+/// Segment of a expanded timeline, its times are in timescale units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimelineSegment {
+    number: u64,
+    time: u64,
+    duration: u64,
+    timescale: u64,
+    presentation_time_offset: u64,
+}
+
+impl TimelineSegment {
+    pub(crate) fn new(
+        number: u64,
+        time: u64,
+        duration: u64,
+        timescale: u64,
+        presentation_time_offset: u64,
+    ) -> Self {
+        TimelineSegment {
+            number,
+            time,
+            duration,
+            timescale: timescale.max(1),
+            presentation_time_offset,
+        }
+    }
+
+    /// Value of `$Number$`.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Media time of the segment, value of `$Time$`.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    /// Start of the segment relative to the start of the period, that is its media time
+    /// minus the `presentationTimeOffset`.
+    pub fn start(&self) -> i64 {
+        self.time as i64 - self.presentation_time_offset as i64
+    }
+
+    pub fn end(&self) -> i64 {
+        self.start() + self.duration as i64
+    }
+
+    pub fn timescale(&self) -> u64 {
+        self.timescale
+    }
+
+    /// [`start`](Self::start) as a time.
+    pub fn start_time(&self) -> MediaTime {
+        MediaTime::new(self.start(), self.timescale)
+    }
+
+    pub fn end_time(&self) -> MediaTime {
+        MediaTime::new(self.end(), self.timescale)
+    }
+
+    pub fn duration_time(&self) -> MediaTime {
+        MediaTime::new(self.duration as i64, self.timescale)
+    }
+}
+
+/// Iterator over the segments described by the `S` entries of a [`SegmentTimeline`].
+///
+/// Without an end, a last `S` with `r="-1"` is repeated forever: the caller decides
+/// where to stop, e.g. at the live edge.
+///
+/// Segment sequences are not supported: an `S` with `k` greater than 1 gives one
+/// segment of duration `d` for each sequence, numbered as a single segment.
+#[derive(Debug, Clone)]
+pub struct TimelineSegments<'a> {
+    entries: &'a [Segment],
+    index: usize,
+    /// Segments left for the current entry, `None` when it repeats forever.
+    left: Option<u64>,
+    number: u64,
+    time: u64,
+    duration: u64,
+    timescale: u64,
+    presentation_time_offset: u64,
+    /// End of the period as a media time.
+    end: Option<u64>,
+}
+
+impl<'a> TimelineSegments<'a> {
+    /// Whether the `S` of the last segment repeats without a known end.
+    pub(crate) fn is_open_ended(&self) -> bool {
+        self.left.is_none()
+    }
+
+    /// Moves to the next entry with a duration, `false` once there are no more.
+    fn next_entry(&mut self) -> bool {
+        while self.left == Some(0) {
+            let entry = match self.entries.get(self.index) {
+                Some(entry) => entry,
+                None => return false,
+            };
+            self.index += 1;
+            if let Some(t) = entry.t {
+                self.time = t;
+            }
+            if let Some(n) = entry.n {
+                self.number = n;
+            }
+            self.duration = entry.d;
+            if self.duration == 0 {
+                continue;
+            }
+            self.left = if entry.r >= 0 {
+                Some(entry.r as u64 + 1)
+            } else {
+                // Repeats until the start of the next entry or the end of the period.
+                self.entries
+                    .get(self.index)
+                    .and_then(|next| next.t)
+                    .or(self.end)
+                    .map(|until| until.saturating_sub(self.time).div_ceil(self.duration))
+            };
+        }
+        true
+    }
+}
+
+impl<'a> Iterator for TimelineSegments<'a> {
+    type Item = TimelineSegment;
+
+    fn next(&mut self) -> Option<TimelineSegment> {
+        if !self.next_entry() {
+            return None;
+        }
+        if self.end.is_some_and(|end| self.time >= end) {
+            return None;
+        }
+        let segment = TimelineSegment {
+            number: self.number,
+            time: self.time,
+            duration: self.duration,
+            timescale: self.timescale,
+            presentation_time_offset: self.presentation_time_offset,
+        };
+        self.number += 1;
+        self.time += self.duration;
+        self.left = self.left.map(|left| left - 1);
+        Some(segment)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SegmentListUrl {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     media: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     media_range: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     index: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     index_range: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-struct SegmentUrl {
-    #[serde(rename = "sourceURL", default)]
+impl SegmentListUrl {
+    pub fn media(&self) -> &str {
+        &self.media
+    }
+
+    pub fn media_range(&self) -> &str {
+        &self.media_range
+    }
+
+    pub fn index(&self) -> &str {
+        &self.index
+    }
+
+    pub fn index_range(&self) -> &str {
+        &self.index_range
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SegmentUrl {
+    #[serde(rename = "sourceURL", default, skip_serializing_if = "is_default")]
     source_url: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     range: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl SegmentUrl {
+    pub fn source_url(&self) -> &str {
+        &self.source_url
+    }
+
+    pub fn range(&self) -> &str {
+        &self.range
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SegmentBase {
     // attribut
-    #[serde(default)]
-    timescale: u64,
-    #[serde(default)]
-    presentation_time_offset: f64,
-    #[serde(default)]
-    index_range: String,
-    #[serde(default)]
-    index_range_exact: bool,
-    #[serde(default)]
-    availability_time_offset: f64,
-    #[serde(default)]
-    availability_time_complete: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timescale: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    presentation_time_offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range_exact: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_offset: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_complete: Option<bool>,
     // children
     #[serde(rename = "Initialization", default)]
     initialization: Option<SegmentUrl>,
@@ -65,26 +391,69 @@ pub struct SegmentBase {
     representation_index: Option<SegmentUrl>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl SegmentBase {
+    pub fn timescale(&self) -> u64 {
+        self.timescale.unwrap_or(1)
+    }
+
+    pub fn presentation_time_offset(&self) -> u64 {
+        self.presentation_time_offset.unwrap_or_default()
+    }
+
+    pub fn index_range(&self) -> &str {
+        self.index_range.as_deref().unwrap_or_default()
+    }
+
+    pub fn index_range_exact(&self) -> bool {
+        self.index_range_exact.unwrap_or_default()
+    }
+
+    pub fn availability_time_offset(&self) -> f64 {
+        self.availability_time_offset.unwrap_or_default()
+    }
+
+    /// `true` when not given.
+    pub fn availability_time_complete(&self) -> bool {
+        self.availability_time_complete.unwrap_or(true)
+    }
+
+    pub fn initialization(&self) -> Option<&SegmentUrl> {
+        self.initialization.as_ref()
+    }
+
+    pub fn initialization_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.initialization.as_mut()
+    }
+
+    pub fn representation_index(&self) -> Option<&SegmentUrl> {
+        self.representation_index.as_ref()
+    }
+
+    pub fn representation_index_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.representation_index.as_mut()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct MultipleSegmentBaseType {
     // attribut
-    #[serde(default)]
-    timescale: u64,
-    #[serde(default)]
-    start_number: u64,
-    #[serde(default)]
-    duration: u64,
-    #[serde(default)]
-    presentation_time_offset: f64,
-    #[serde(default)]
-    index_range: String,
-    #[serde(default)]
-    index_range_exact: bool,
-    #[serde(default)]
-    availability_time_offset: f64,
-    #[serde(default)]
-    availability_time_complete: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timescale: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    presentation_time_offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range_exact: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_offset: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_complete: Option<bool>,
     // children
     #[serde(rename = "Initialization", default)]
     initialization: Option<SegmentUrl>,
@@ -96,28 +465,101 @@ pub struct MultipleSegmentBaseType {
     bitstream_switching: Option<SegmentUrl>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl MultipleSegmentBaseType {
+    pub fn timescale(&self) -> u64 {
+        self.timescale.unwrap_or(1)
+    }
+
+    /// Number of the first segment, 1 when not given.
+    pub fn start_number(&self) -> u64 {
+        self.start_number.unwrap_or(1)
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration.unwrap_or_default()
+    }
+
+    pub fn presentation_time_offset(&self) -> u64 {
+        self.presentation_time_offset.unwrap_or_default()
+    }
+
+    pub fn index_range(&self) -> &str {
+        self.index_range.as_deref().unwrap_or_default()
+    }
+
+    pub fn index_range_exact(&self) -> bool {
+        self.index_range_exact.unwrap_or_default()
+    }
+
+    pub fn availability_time_offset(&self) -> f64 {
+        self.availability_time_offset.unwrap_or_default()
+    }
+
+    /// `true` when not given.
+    pub fn availability_time_complete(&self) -> bool {
+        self.availability_time_complete.unwrap_or(true)
+    }
+
+    pub fn initialization(&self) -> Option<&SegmentUrl> {
+        self.initialization.as_ref()
+    }
+
+    pub fn initialization_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.initialization.as_mut()
+    }
+
+    pub fn representation_index(&self) -> Option<&SegmentUrl> {
+        self.representation_index.as_ref()
+    }
+
+    pub fn representation_index_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.representation_index.as_mut()
+    }
+
+    pub fn segment_timeline(&self) -> Option<&SegmentTimeline> {
+        self.segment_timeline.as_ref()
+    }
+
+    pub fn segment_timeline_mut(&mut self) -> Option<&mut SegmentTimeline> {
+        self.segment_timeline.as_mut()
+    }
+
+    pub fn bitstream_switching(&self) -> Option<&SegmentUrl> {
+        self.bitstream_switching.as_ref()
+    }
+
+    pub fn bitstream_switching_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.bitstream_switching.as_mut()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct SegmentList {
     // attribut
-    #[serde(default)]
-    timescale: u64,
-    #[serde(default)]
-    start_number: u64,
-    #[serde(default)]
-    duration: u64,
-    #[serde(default)]
-    presentation_time_offset: f64,
-    #[serde(default)]
-    index_range: String,
-    #[serde(default)]
-    index_range_exact: bool,
-    #[serde(default)]
-    availability_time_offset: f64,
-    #[serde(default)]
-    availability_time_complete: bool,
-    #[serde(rename = "xlink:href", default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timescale: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    presentation_time_offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range_exact: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_offset: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_complete: Option<bool>,
+    #[serde(rename = "xlink:href", default, skip_serializing_if = "is_default")]
     href: String,
-    #[serde(rename = "xlink:actuate", default = "HrefActuate::default")]
+    #[serde(
+        rename = "xlink:actuate",
+        default = "HrefActuate::default",
+        skip_serializing_if = "is_default"
+    )]
     href_actuate: HrefActuate,
     // children
     #[serde(rename = "Initialization", default)]
@@ -132,33 +574,126 @@ pub struct SegmentList {
     segment_url: Vec<SegmentListUrl>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl SegmentList {
+    pub fn timescale(&self) -> u64 {
+        self.timescale.unwrap_or(1)
+    }
+
+    /// Number of the first segment, 1 when not given.
+    pub fn start_number(&self) -> u64 {
+        self.start_number.unwrap_or(1)
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration.unwrap_or_default()
+    }
+
+    pub fn presentation_time_offset(&self) -> u64 {
+        self.presentation_time_offset.unwrap_or_default()
+    }
+
+    pub fn index_range(&self) -> &str {
+        self.index_range.as_deref().unwrap_or_default()
+    }
+
+    pub fn index_range_exact(&self) -> bool {
+        self.index_range_exact.unwrap_or_default()
+    }
+
+    pub fn availability_time_offset(&self) -> f64 {
+        self.availability_time_offset.unwrap_or_default()
+    }
+
+    /// `true` when not given.
+    pub fn availability_time_complete(&self) -> bool {
+        self.availability_time_complete.unwrap_or(true)
+    }
+
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+
+    pub fn href_actuate(&self) -> HrefActuate {
+        self.href_actuate
+    }
+
+    pub fn initialization(&self) -> Option<&SegmentUrl> {
+        self.initialization.as_ref()
+    }
+
+    pub fn initialization_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.initialization.as_mut()
+    }
+
+    pub fn representation_index(&self) -> Option<&SegmentUrl> {
+        self.representation_index.as_ref()
+    }
+
+    pub fn representation_index_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.representation_index.as_mut()
+    }
+
+    pub fn segment_timeline(&self) -> Option<&SegmentTimeline> {
+        self.segment_timeline.as_ref()
+    }
+
+    pub fn segment_timeline_mut(&mut self) -> Option<&mut SegmentTimeline> {
+        self.segment_timeline.as_mut()
+    }
+
+    pub fn bitstream_switching(&self) -> Option<&SegmentUrl> {
+        self.bitstream_switching.as_ref()
+    }
+
+    pub fn bitstream_switching_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.bitstream_switching.as_mut()
+    }
+
+    pub fn segment_url(&self) -> &[SegmentListUrl] {
+        &self.segment_url
+    }
+
+    pub fn segment_url_mut(&mut self) -> &mut Vec<SegmentListUrl> {
+        &mut self.segment_url
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct SegmentTemplate {
     // attribut
-    #[serde(default)]
-    timescale: u64,
-    #[serde(default)]
-    start_number: u64,
-    #[serde(default)]
-    duration: u64,
-    #[serde(default)]
-    presentation_time_offset: f64,
-    #[serde(default)]
-    index_range: String,
-    #[serde(default)]
-    index_range_exact: bool,
-    #[serde(default)]
-    availability_time_offset: f64,
-    #[serde(default)]
-    availability_time_complete: bool,
-    #[serde(default)]
-    media: String,
-    #[serde(default)]
-    index: String,
-    #[serde(default)]
-    initialization_url: String,
-    #[serde(rename = "bitstreamSwitching", default)]
-    bitstream_switching_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timescale: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    presentation_time_offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index_range_exact: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_offset: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    availability_time_complete: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index: Option<String>,
+    #[serde(
+        rename = "initialization",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    initialization_url: Option<String>,
+    #[serde(
+        rename = "bitstreamSwitching",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    bitstream_switching_url: Option<String>,
     // children
     #[serde(rename = "Initialization", default)]
     initialization: Option<SegmentUrl>,
@@ -168,25 +703,865 @@ pub struct SegmentTemplate {
     segment_timeline: Option<SegmentTimeline>,
     #[serde(rename = "BitstreamSwitching", default)]
     bitstream_switching: Option<SegmentUrl>,
+    #[serde(
+        rename = "$extensions",
+        default,
+        skip_serializing_if = "Extensions::is_empty"
+    )]
+    extensions: Extensions,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl SegmentTemplate {
+    pub fn timescale(&self) -> u64 {
+        self.timescale.unwrap_or(1)
+    }
+
+    /// Number of the first segment, 1 when not given.
+    pub fn start_number(&self) -> u64 {
+        self.start_number.unwrap_or(1)
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration.unwrap_or_default()
+    }
+
+    pub fn presentation_time_offset(&self) -> u64 {
+        self.presentation_time_offset.unwrap_or_default()
+    }
+
+    pub fn index_range(&self) -> &str {
+        self.index_range.as_deref().unwrap_or_default()
+    }
+
+    pub fn index_range_exact(&self) -> bool {
+        self.index_range_exact.unwrap_or_default()
+    }
+
+    pub fn availability_time_offset(&self) -> f64 {
+        self.availability_time_offset.unwrap_or_default()
+    }
+
+    /// `true` when not given.
+    pub fn availability_time_complete(&self) -> bool {
+        self.availability_time_complete.unwrap_or(true)
+    }
+
+    pub fn media(&self) -> &str {
+        self.media.as_deref().unwrap_or_default()
+    }
+
+    pub fn index(&self) -> &str {
+        self.index.as_deref().unwrap_or_default()
+    }
+
+    pub fn initialization_url(&self) -> &str {
+        self.initialization_url.as_deref().unwrap_or_default()
+    }
+
+    pub fn bitstream_switching_url(&self) -> &str {
+        self.bitstream_switching_url.as_deref().unwrap_or_default()
+    }
+
+    pub fn initialization(&self) -> Option<&SegmentUrl> {
+        self.initialization.as_ref()
+    }
+
+    pub fn initialization_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.initialization.as_mut()
+    }
+
+    pub fn representation_index(&self) -> Option<&SegmentUrl> {
+        self.representation_index.as_ref()
+    }
+
+    pub fn representation_index_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.representation_index.as_mut()
+    }
+
+    pub fn segment_timeline(&self) -> Option<&SegmentTimeline> {
+        self.segment_timeline.as_ref()
+    }
+
+    pub fn segment_timeline_mut(&mut self) -> Option<&mut SegmentTimeline> {
+        self.segment_timeline.as_mut()
+    }
+
+    pub fn bitstream_switching(&self) -> Option<&SegmentUrl> {
+        self.bitstream_switching.as_ref()
+    }
+
+    pub fn bitstream_switching_mut(&mut self) -> Option<&mut SegmentUrl> {
+        self.bitstream_switching.as_mut()
+    }
+
+    /// Attributes and children the model doesn't know.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    pub fn media_template(&self) -> Result<Template, TemplateError> {
+        self.media().parse()
+    }
+
+    pub fn initialization_template(&self) -> Result<Template, TemplateError> {
+        self.initialization_url().parse()
+    }
+
+    /// URL of a media segment, relative to the `BaseURL`.
+    pub fn expand_media(&self, values: &TemplateValues) -> Result<String, TemplateError> {
+        self.media_template()?.expand(values)
+    }
+
+    /// URL of the initialization segment, relative to the `BaseURL`.
+    pub fn expand_initialization(&self, values: &TemplateValues) -> Result<String, TemplateError> {
+        self.initialization_template()?.expand(values)
+    }
+
+    /// Segments of the `SegmentTimeline`, if any, up to the end of a period lasting
+    /// `period_duration` when it is known.
+    pub fn timeline_segments(
+        &self,
+        period_duration: Option<Duration>,
+    ) -> Option<TimelineSegments<'_>> {
+        // Rounded up: a segment starting within the last partial tick starts before the end.
+        let end = period_duration.map(|duration| {
+            let end = MediaTime::from_duration(duration, self.timescale());
+            (end.ticks() + i64::from(end.to_duration() < duration)).max(0) as u64
+        });
+        self.segment_timeline.as_ref().map(|timeline| {
+            timeline.iter(
+                self.timescale(),
+                self.start_number(),
+                self.presentation_time_offset(),
+                end,
+            )
+        })
+    }
+}
+
+impl SegmentBase {
+    /// Attributes and children missing from `self` are taken from `parent`.
+    fn inherit(&self, parent: &SegmentBase) -> SegmentBase {
+        SegmentBase {
+            timescale: self.timescale.or(parent.timescale),
+            presentation_time_offset: self
+                .presentation_time_offset
+                .or(parent.presentation_time_offset),
+            index_range: inherit(&self.index_range, &parent.index_range),
+            index_range_exact: self.index_range_exact.or(parent.index_range_exact),
+            availability_time_offset: self
+                .availability_time_offset
+                .or(parent.availability_time_offset),
+            availability_time_complete: self
+                .availability_time_complete
+                .or(parent.availability_time_complete),
+            initialization: inherit(&self.initialization, &parent.initialization),
+            representation_index: inherit(&self.representation_index, &parent.representation_index),
+        }
+    }
+}
+
+impl SegmentList {
+    /// Attributes and children missing from `self` are taken from `parent`, the
+    /// `SegmentURL` entries as a whole.
+    fn inherit(&self, parent: &SegmentList) -> SegmentList {
+        SegmentList {
+            timescale: self.timescale.or(parent.timescale),
+            start_number: self.start_number.or(parent.start_number),
+            duration: self.duration.or(parent.duration),
+            presentation_time_offset: self
+                .presentation_time_offset
+                .or(parent.presentation_time_offset),
+            index_range: inherit(&self.index_range, &parent.index_range),
+            index_range_exact: self.index_range_exact.or(parent.index_range_exact),
+            availability_time_offset: self
+                .availability_time_offset
+                .or(parent.availability_time_offset),
+            availability_time_complete: self
+                .availability_time_complete
+                .or(parent.availability_time_complete),
+            href: self.href.clone(),
+            href_actuate: self.href_actuate,
+            initialization: inherit(&self.initialization, &parent.initialization),
+            representation_index: inherit(&self.representation_index, &parent.representation_index),
+            segment_timeline: inherit(&self.segment_timeline, &parent.segment_timeline),
+            bitstream_switching: inherit(&self.bitstream_switching, &parent.bitstream_switching),
+            segment_url: if self.segment_url.is_empty() {
+                parent.segment_url.clone()
+            } else {
+                self.segment_url.clone()
+            },
+        }
+    }
+}
+
+impl SegmentTemplate {
+    /// Attributes and children missing from `self` are taken from `parent`.
+    fn inherit(&self, parent: &SegmentTemplate) -> SegmentTemplate {
+        SegmentTemplate {
+            timescale: self.timescale.or(parent.timescale),
+            start_number: self.start_number.or(parent.start_number),
+            duration: self.duration.or(parent.duration),
+            presentation_time_offset: self
+                .presentation_time_offset
+                .or(parent.presentation_time_offset),
+            index_range: inherit(&self.index_range, &parent.index_range),
+            index_range_exact: self.index_range_exact.or(parent.index_range_exact),
+            availability_time_offset: self
+                .availability_time_offset
+                .or(parent.availability_time_offset),
+            availability_time_complete: self
+                .availability_time_complete
+                .or(parent.availability_time_complete),
+            media: inherit(&self.media, &parent.media),
+            index: inherit(&self.index, &parent.index),
+            initialization_url: inherit(&self.initialization_url, &parent.initialization_url),
+            bitstream_switching_url: inherit(
+                &self.bitstream_switching_url,
+                &parent.bitstream_switching_url,
+            ),
+            initialization: inherit(&self.initialization, &parent.initialization),
+            representation_index: inherit(&self.representation_index, &parent.representation_index),
+            segment_timeline: inherit(&self.segment_timeline, &parent.segment_timeline),
+            bitstream_switching: inherit(&self.bitstream_switching, &parent.bitstream_switching),
+            extensions: if self.extensions.is_empty() {
+                parent.extensions.clone()
+            } else {
+                self.extensions.clone()
+            },
+        }
+    }
+}
+
+fn inherit<T: Clone>(value: &Option<T>, parent: &Option<T>) -> Option<T> {
+    value.as_ref().or(parent.as_ref()).cloned()
+}
+
+/// Segment information in effect for a `Representation`, merged from the `Period`,
+/// `AdaptationSet` and `Representation` levels.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SegmentInformation {
+    Base(SegmentBase),
+    List(SegmentList),
+    Template(SegmentTemplate),
+}
+
+/// `SegmentBase`, `SegmentList` and `SegmentTemplate` of one level of the MPD.
+pub(crate) type SegmentLevel<'a> = (
+    Option<&'a SegmentBase>,
+    Option<&'a SegmentList>,
+    Option<&'a SegmentTemplate>,
+);
+
+impl SegmentInformation {
+    /// Resolves the levels, from the `Period` down to the `Representation`: the kind is
+    /// the one given by the lowest level, a template before a list before a base, and
+    /// each level overrides the elements of that kind above it.
+    pub(crate) fn resolve(levels: &[SegmentLevel]) -> Option<SegmentInformation> {
+        let (_, list, template) = levels.iter().rev().find(|(base, list, template)| {
+            base.is_some() || list.is_some() || template.is_some()
+        })?;
+        Some(if template.is_some() {
+            SegmentInformation::Template(merge(
+                levels.iter().filter_map(|level| level.2),
+                SegmentTemplate::inherit,
+            )?)
+        } else if list.is_some() {
+            SegmentInformation::List(merge(
+                levels.iter().filter_map(|level| level.1),
+                SegmentList::inherit,
+            )?)
+        } else {
+            SegmentInformation::Base(merge(
+                levels.iter().filter_map(|level| level.0),
+                SegmentBase::inherit,
+            )?)
+        })
+    }
+
+    pub fn base(&self) -> Option<&SegmentBase> {
+        match self {
+            SegmentInformation::Base(base) => Some(base),
+            _ => None,
+        }
+    }
+
+    pub fn list(&self) -> Option<&SegmentList> {
+        match self {
+            SegmentInformation::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn template(&self) -> Option<&SegmentTemplate> {
+        match self {
+            SegmentInformation::Template(template) => Some(template),
+            _ => None,
+        }
+    }
+
+    pub fn timescale(&self) -> u64 {
+        match self {
+            SegmentInformation::Base(base) => base.timescale(),
+            SegmentInformation::List(list) => list.timescale(),
+            SegmentInformation::Template(template) => template.timescale(),
+        }
+    }
+
+    /// `timescale` given at some level, where [`timescale`](Self::timescale) falls back to 1.
+    pub(crate) fn declared_timescale(&self) -> Option<u64> {
+        match self {
+            SegmentInformation::Base(base) => base.timescale,
+            SegmentInformation::List(list) => list.timescale,
+            SegmentInformation::Template(template) => template.timescale,
+        }
+    }
+
+    pub fn presentation_time_offset(&self) -> u64 {
+        match self {
+            SegmentInformation::Base(base) => base.presentation_time_offset(),
+            SegmentInformation::List(list) => list.presentation_time_offset(),
+            SegmentInformation::Template(template) => template.presentation_time_offset(),
+        }
+    }
+
+    pub fn availability_time_offset(&self) -> f64 {
+        match self {
+            SegmentInformation::Base(base) => base.availability_time_offset(),
+            SegmentInformation::List(list) => list.availability_time_offset(),
+            SegmentInformation::Template(template) => template.availability_time_offset(),
+        }
+    }
+
+    pub fn availability_time_complete(&self) -> bool {
+        match self {
+            SegmentInformation::Base(base) => base.availability_time_complete(),
+            SegmentInformation::List(list) => list.availability_time_complete(),
+            SegmentInformation::Template(template) => template.availability_time_complete(),
+        }
+    }
+
+    pub fn initialization(&self) -> Option<&SegmentUrl> {
+        match self {
+            SegmentInformation::Base(base) => base.initialization(),
+            SegmentInformation::List(list) => list.initialization(),
+            SegmentInformation::Template(template) => template.initialization(),
+        }
+    }
+
+    /// Number of the first segment, 1 for a `SegmentBase`.
+    pub fn start_number(&self) -> u64 {
+        match self {
+            SegmentInformation::Base(_) => 1,
+            SegmentInformation::List(list) => list.start_number(),
+            SegmentInformation::Template(template) => template.start_number(),
+        }
+    }
+
+    /// Duration of the segments in timescale units when they all last the same, 0 for a
+    /// `SegmentBase`.
+    pub fn duration(&self) -> u64 {
+        match self {
+            SegmentInformation::Base(_) => 0,
+            SegmentInformation::List(list) => list.duration(),
+            SegmentInformation::Template(template) => template.duration(),
+        }
+    }
+
+    pub fn segment_timeline(&self) -> Option<&SegmentTimeline> {
+        match self {
+            SegmentInformation::Base(_) => None,
+            SegmentInformation::List(list) => list.segment_timeline(),
+            SegmentInformation::Template(template) => template.segment_timeline(),
+        }
+    }
+}
+
+/// Applies `inherit` from the top level down.
+fn merge<'a, T: Clone + 'a>(
+    mut levels: impl Iterator<Item = &'a T>,
+    inherit: fn(&T, &T) -> T,
+) -> Option<T> {
+    let top = levels.next()?.clone();
+    Some(levels.fold(top, |parent, level| inherit(level, &parent)))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BaseURL {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     service_location: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     byte_range: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     availability_time_offset: f64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     availability_time_complete: bool,
     #[serde(rename = "$value")]
     url: String,
+    #[serde(
+        rename = "$extensions",
+        default,
+        skip_serializing_if = "Extensions::is_empty"
+    )]
+    extensions: Extensions,
+}
+
+impl BaseURL {
+    pub fn service_location(&self) -> &str {
+        &self.service_location
+    }
+
+    pub fn byte_range(&self) -> &str {
+        &self.byte_range
+    }
+
+    pub fn availability_time_offset(&self) -> f64 {
+        self.availability_time_offset
+    }
+
+    pub fn availability_time_complete(&self) -> bool {
+        self.availability_time_complete
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// `dvb:priority` of the location, lower is preferred.
+    pub fn priority(&self) -> Option<u32> {
+        self.dvb_attribute("priority")
+    }
+
+    /// `dvb:weight`, share of the requests among the locations of a same priority.
+    pub fn weight(&self) -> Option<u32> {
+        self.dvb_attribute("weight")
+    }
+
+    fn dvb_attribute(&self, name: &str) -> Option<u32> {
+        self.extensions
+            .attributes()
+            .iter()
+            .find(|attribute| {
+                attribute.local_name() == name && attribute.namespace() == Some(DVB_NAMESPACE)
+            })
+            .and_then(|attribute| attribute.value().trim().parse().ok())
+    }
+
+    /// Attributes and children the model doesn't know.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
 }
 
+/// Namespace of the DVB-DASH extensions (ETSI TS 103 285).
+pub const DVB_NAMESPACE: &str = "urn:dvb:dash:dash-extensions:2014-1";
+
 impl Display for BaseURL {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} ({})", &self.url, &self.service_location)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mpd::tests::{bundled_manifest, BUNDLED_MANIFEST};
+    use crate::{TemplateValues, MPD};
+
+    fn template(source: &str) -> SegmentTemplate {
+        let mpd: MPD = format!(
+            r#"<MPD profiles="urn:mpeg:dash:profile:isoff-live:2011" mediaPresentationDuration="PT1M">
+    <Period id="1"><AdaptationSet>{}</AdaptationSet></Period>
+</MPD>"#,
+            source
+        )
+        .parse()
+        .unwrap();
+        mpd.periods()[0].adaptations()[0]
+            .segment_template()
+            .unwrap()
+            .clone()
+    }
+
+    fn expanded(segments: TimelineSegments) -> Vec<(u64, u64, u64)> {
+        segments
+            .map(|segment| (segment.number(), segment.time(), segment.duration()))
+            .collect()
+    }
+
+    #[test]
+    fn should_expand_bundled_manifest_timelines() {
+        let mpd = bundled_manifest();
+        let adaptations = mpd.periods()[0].adaptations();
+
+        let video: Vec<TimelineSegment> = adaptations[0]
+            .segment_template()
+            .unwrap()
+            .timeline_segments(None)
+            .unwrap()
+            .collect();
+        assert_eq!(video.len(), 32);
+        assert_eq!(
+            (video[0].number(), video[0].time(), video[0].duration()),
+            (3059199, 1516128710400, 518400)
+        );
+        assert_eq!(video[0].start(), 1516128710400 - 932248845827);
+        assert_eq!(
+            (video[3].number(), video[3].time(), video[3].duration()),
+            (3059202, 1516130265600, 14400)
+        );
+        assert_eq!(
+            (video[31].number(), video[31].time(), video[31].duration()),
+            (3059230, 1516141151997, 518400)
+        );
+        assert!(video
+            .windows(2)
+            .all(|pair| pair[0].end() == pair[1].start()));
+
+        for audio in &adaptations[1..] {
+            let segments: Vec<TimelineSegment> = audio
+                .segment_template()
+                .unwrap()
+                .timeline_segments(None)
+                .unwrap()
+                .collect();
+            assert_eq!(segments.len(), 32);
+            assert_eq!(segments[0].time(), 808601978880);
+            assert_eq!(segments[0].start(), 808601978880 - 497199384441);
+            assert_eq!(
+                (segments[31].number(), segments[31].time()),
+                (3059230, 808608614397)
+            );
+        }
+    }
+
+    /// Value of `name` in the attributes of the element written as `tag`.
+    fn raw_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+        let start = format!(" {}", tag).find(&format!(" {}=\"", name))? + name.len() + 2;
+        tag[start..].split('"').next()
+    }
+
+    #[test]
+    fn should_read_every_timeline_entry_of_bundled_manifest() {
+        let source = BUNDLED_MANIFEST;
+        let expected: Vec<(Option<u64>, u64, i64)> = source
+            .split("<S ")
+            .skip(1)
+            .map(|tag| &tag[..tag.find("/>").unwrap()])
+            .map(|tag| {
+                (
+                    raw_attribute(tag, "t").map(|t| t.parse().unwrap()),
+                    raw_attribute(tag, "d").unwrap().parse().unwrap(),
+                    raw_attribute(tag, "r").map_or(0, |r| r.parse().unwrap()),
+                )
+            })
+            .collect();
+        assert_eq!(expected.len(), 88);
+
+        let mpd: MPD = source.parse().unwrap();
+        let entries: Vec<(Option<u64>, u64, i64)> = mpd.periods()[0]
+            .adaptations()
+            .iter()
+            .filter_map(|adaptation| adaptation.segment_template())
+            .filter_map(|template| template.segment_timeline())
+            .flat_map(|timeline| timeline.segments())
+            .map(|entry| (entry.t(), entry.d(), entry.r()))
+            .collect();
+        assert_eq!(entries, expected);
+        assert!(entries.contains(&(Some(1516128710400), 518400, 2)));
+    }
+
+    #[test]
+    fn should_read_signed_repeat_number_and_sequence_attributes() {
+        let template = template(
+            r#"<SegmentTemplate timescale="1000"><SegmentTimeline>
+                <S t="1000" n="20" d="2000" r="1" k="4"/><S d="1000" r="-1"/>
+            </SegmentTimeline></SegmentTemplate>"#,
+        );
+        let entries = template.segment_timeline().unwrap().segments();
+        assert_eq!(
+            (
+                entries[0].t(),
+                entries[0].n(),
+                entries[0].d(),
+                entries[0].r()
+            ),
+            (Some(1000), Some(20), 2000, 1)
+        );
+        assert_eq!(entries[0].k(), 4);
+        assert_eq!(
+            (entries[1].t(), entries[1].r(), entries[1].k()),
+            (None, -1, 1)
+        );
+        assert_eq!(
+            expanded(
+                template
+                    .timeline_segments(Some(Duration::seconds(7)))
+                    .unwrap()
+            ),
+            vec![
+                (20, 1000, 2000),
+                (21, 3000, 2000),
+                (22, 5000, 1000),
+                (23, 6000, 1000)
+            ]
+        );
+    }
+
+    #[test]
+    fn should_convert_media_time_to_wall_clock_exactly() {
+        let time = MediaTime::new(1516128710400, 90000);
+        assert_eq!(
+            time.to_duration(),
+            Duration::seconds(16845874) + Duration::milliseconds(560)
+        );
+        assert_eq!(
+            MediaTime::new(808608614397, 48000).to_duration(),
+            Duration::seconds(16846012) + Duration::nanoseconds(799_937_500)
+        );
+        assert_eq!(
+            MediaTime::new(1, 3).to_duration(),
+            Duration::nanoseconds(333_333_333)
+        );
+        assert_eq!(
+            MediaTime::new(-1, 3).to_duration(),
+            Duration::nanoseconds(-333_333_334)
+        );
+        assert_eq!(MediaTime::new(45000, 90000), MediaTime::new(1, 2));
+        assert!(MediaTime::new(14400, 90000) < MediaTime::new(7681, 48000));
+        assert_eq!(
+            MediaTime::from_duration(Duration::milliseconds(1500), 90000).ticks(),
+            135000
+        );
+
+        let template = template(
+            r#"<SegmentTemplate timescale="3" presentationTimeOffset="3"><SegmentTimeline>
+                <S t="3" d="1" r="-1"/>
+            </SegmentTimeline></SegmentTemplate>"#,
+        );
+        // A segment starting at 2/3 s is within a period lasting 666666667 ns, not within
+        // one lasting 666666666 ns.
+        let segments: Vec<TimelineSegment> = template
+            .timeline_segments(Some(Duration::nanoseconds(666_666_667)))
+            .unwrap()
+            .collect();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[2].start_time(), MediaTime::new(2, 3));
+        assert_eq!(
+            segments[1].end_time().to_duration(),
+            Duration::nanoseconds(666_666_666)
+        );
+        let segments = template
+            .timeline_segments(Some(Duration::nanoseconds(666_666_666)))
+            .unwrap();
+        assert_eq!(segments.count(), 2);
+    }
+
+    fn resolved(source: &str) -> Vec<SegmentInformation> {
+        let mpd: MPD = source.parse().unwrap();
+        let period = &mpd.periods()[0];
+        period
+            .adaptations()
+            .iter()
+            .flat_map(|adaptation| {
+                adaptation
+                    .representations()
+                    .iter()
+                    .map(move |representation| {
+                        period
+                            .segment_information(adaptation, representation)
+                            .unwrap()
+                    })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_resolve_bundled_manifest_template_from_adaptation_set() {
+        let source = BUNDLED_MANIFEST;
+        let resolved = resolved(source);
+        let mpd: MPD = source.parse().unwrap();
+        let video = mpd.periods()[0].adaptations()[0]
+            .segment_template()
+            .unwrap();
+        assert_eq!(resolved[0].template(), Some(video));
+        assert_eq!(resolved[0].timescale(), 90000);
+        assert_eq!(
+            resolved.last().unwrap().presentation_time_offset(),
+            497199384441
+        );
+    }
+
+    #[test]
+    fn should_override_inherited_template_attributes_and_children() {
+        let resolved = resolved(
+            r#"<MPD profiles="urn:mpeg:dash:profile:isoff-live:2011" mediaPresentationDuration="PT1M">
+    <Period id="1">
+        <SegmentTemplate timescale="1000" media="$Number$.m4s" initialization="init.mp4" presentationTimeOffset="500"/>
+        <AdaptationSet>
+            <SegmentTemplate startNumber="10" presentationTimeOffset="0">
+                <SegmentTimeline><S t="0" d="2000" r="-1"/></SegmentTimeline>
+            </SegmentTemplate>
+            <Representation id="1" bandwidth="1"/>
+            <Representation id="2" bandwidth="2">
+                <SegmentTemplate media="$RepresentationID$/$Time$.m4s"/>
+            </Representation>
+        </AdaptationSet>
+    </Period>
+</MPD>"#,
+        );
+        let first = resolved[0].template().unwrap();
+        assert_eq!(first.timescale(), 1000);
+        assert_eq!(first.start_number(), 10);
+        assert_eq!(first.presentation_time_offset(), 0);
+        assert_eq!(first.media(), "$Number$.m4s");
+        assert_eq!(first.initialization_url(), "init.mp4");
+        assert_eq!(first.segment_timeline().unwrap().segments().len(), 1);
+
+        let second = resolved[1].template().unwrap();
+        assert_eq!(second.media(), "$RepresentationID$/$Time$.m4s");
+        assert_eq!(second.start_number(), 10);
+        assert!(second.segment_timeline().is_some());
+    }
+
+    #[test]
+    fn should_take_kind_from_lowest_level() {
+        let resolved = resolved(
+            r#"<MPD profiles="urn:mpeg:dash:profile:full:2011" mediaPresentationDuration="PT1M">
+    <Period id="1">
+        <SegmentList timescale="90000" duration="180000">
+            <Initialization sourceURL="init.mp4"/>
+        </SegmentList>
+        <AdaptationSet>
+            <SegmentTemplate media="$Number$.m4s"/>
+            <Representation id="1" bandwidth="1">
+                <SegmentList>
+                    <SegmentURL media="1.m4s"/><SegmentURL media="2.m4s"/>
+                </SegmentList>
+            </Representation>
+            <Representation id="2" bandwidth="2">
+                <SegmentBase indexRange="800-1200"/>
+            </Representation>
+            <Representation id="3" bandwidth="3"/>
+        </AdaptationSet>
+    </Period>
+</MPD>"#,
+        );
+        let list = resolved[0].list().unwrap();
+        assert_eq!(list.duration(), 180000);
+        assert_eq!(list.segment_url().len(), 2);
+        assert_eq!(
+            resolved[0].initialization().unwrap().source_url(),
+            "init.mp4"
+        );
+
+        let base = resolved[1].base().unwrap();
+        assert_eq!(base.index_range(), "800-1200");
+        assert_eq!(base.timescale(), 1);
+
+        assert_eq!(resolved[2].template().unwrap().timescale(), 1);
+    }
+
+    #[test]
+    fn should_carry_time_over_and_restart_at_explicit_time() {
+        let template = template(
+            r#"<SegmentTemplate timescale="10" presentationTimeOffset="5"><SegmentTimeline>
+                <S t="10" d="4" r="1"/><S d="2"/><S t="30" d="5"/>
+            </SegmentTimeline></SegmentTemplate>"#,
+        );
+        let segments = template.timeline_segments(None).unwrap();
+        assert_eq!(
+            expanded(segments.clone()),
+            vec![(1, 10, 4), (2, 14, 4), (3, 18, 2), (4, 30, 5)]
+        );
+        assert_eq!(segments.last().unwrap().start(), 25);
+    }
+
+    #[test]
+    fn should_repeat_until_next_entry_or_period_end() {
+        let template = template(
+            r#"<SegmentTemplate timescale="1000" startNumber="10"><SegmentTimeline>
+                <S t="0" d="2000" r="-1"/><S t="7000" d="1000" r="-1"/>
+            </SegmentTimeline></SegmentTemplate>"#,
+        );
+        assert_eq!(
+            expanded(
+                template
+                    .timeline_segments(Some(Duration::seconds(10)))
+                    .unwrap()
+            ),
+            vec![
+                (10, 0, 2000),
+                (11, 2000, 2000),
+                (12, 4000, 2000),
+                (13, 6000, 2000),
+                (14, 7000, 1000),
+                (15, 8000, 1000),
+                (16, 9000, 1000),
+            ]
+        );
+        let unbounded = template.timeline_segments(None).unwrap();
+        assert_eq!(unbounded.skip(4).take(100).count(), 100);
+    }
+
+    #[test]
+    fn should_stop_at_period_end_relative_to_presentation_time_offset() {
+        let template = template(
+            r#"<SegmentTemplate timescale="1000" presentationTimeOffset="100000"><SegmentTimeline>
+                <S t="100000" d="2000" r="-1"/>
+            </SegmentTimeline></SegmentTemplate>"#,
+        );
+        let segments: Vec<TimelineSegment> = template
+            .timeline_segments(Some(Duration::seconds(5)))
+            .unwrap()
+            .collect();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[2].start(), 4000);
+        assert_eq!(segments[2].number(), 3);
+    }
+
+    #[test]
+    fn should_read_template_attributes_and_expand_urls() {
+        let mpd: MPD = r#"<MPD profiles="urn:mpeg:dash:profile:isoff-live:2011" mediaPresentationDuration="PT1M">
+    <Period id="1">
+        <AdaptationSet>
+            <SegmentTemplate timescale="90000" startNumber="3059199" presentationTimeOffset="932248845827" initialization="$RepresentationID$/init.mp4" media="$RepresentationID$/segment_$Number%08d$.m4s"/>
+            <Representation id="videohd-1280x720" bandwidth="3150000"/>
+        </AdaptationSet>
+    </Period>
+</MPD>"#
+            .parse()
+            .unwrap();
+        let adaptation = &mpd.periods()[0].adaptations()[0];
+        let template = adaptation.segment_template().unwrap();
+        assert_eq!(template.start_number(), 3059199);
+        assert_eq!(template.presentation_time_offset(), 932248845827);
+        assert!(template.extensions().is_empty());
+
+        let representation = &adaptation.representations()[0];
+        let values = TemplateValues::new(representation.id(), representation.bandwidth());
+        assert_eq!(
+            template.expand_initialization(&values).unwrap(),
+            "videohd-1280x720/init.mp4"
+        );
+        assert_eq!(
+            template
+                .expand_media(&values.with_number(template.start_number()))
+                .unwrap(),
+            "videohd-1280x720/segment_03059199.m4s"
+        );
+
+        let written = mpd.to_xml().unwrap();
+        assert!(written.contains(r#" startNumber="3059199""#));
+        assert!(written.contains(r#" initialization="$RepresentationID$/init.mp4""#));
+    }
+}
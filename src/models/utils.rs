@@ -1,48 +1,90 @@
-use crate::serde_custom::duration_iso_8601;
+use crate::serde_custom::{duration_iso_8601, is_default, text_element};
 use chrono::Duration;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::Formatter;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub enum HrefActuate {
     OnLoad,
+    #[default]
     OnRequest,
     Other,
     None,
 }
 
-impl HrefActuate {
-    pub fn default() -> Self {
-        HrefActuate::OnRequest
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Url {
     #[serde(rename = "$value")]
     url: String,
 }
 
+impl Url {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
 impl fmt::Display for Url {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.url)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// Where patches of a dynamic MPD can be fetched (ISO/IEC 23009-1 5.15).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchLocation {
+    #[serde(default, skip_serializing_if = "is_default")]
+    service_location: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ttl: Option<f64>,
+    #[serde(rename = "$value")]
+    url: String,
+}
+
+impl PatchLocation {
+    pub fn service_location(&self) -> &str {
+        &self.service_location
+    }
+
+    /// Seconds after the `publishTime` of the MPD during which the location is valid.
+    pub fn ttl(&self) -> Option<f64> {
+        self.ttl
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Descriptor {
     scheme_id_uri: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     value: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     id: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl Descriptor {
+    pub fn scheme_id_uri(&self) -> &str {
+        &self.scheme_id_uri
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Metrics {
     metrics: String,
@@ -51,7 +93,29 @@ pub struct Metrics {
     range: Vec<Range>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl Metrics {
+    pub fn metrics(&self) -> &str {
+        &self.metrics
+    }
+
+    pub fn reporting(&self) -> &[Descriptor] {
+        &self.reporting
+    }
+
+    pub fn reporting_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.reporting
+    }
+
+    pub fn range(&self) -> &[Range] {
+        &self.range
+    }
+
+    pub fn range_mut(&mut self) -> &mut Vec<Range> {
+        &mut self.range
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Range {
     #[serde(rename = "starttime", with = "duration_iso_8601", default)]
     start_time: Option<Duration>,
@@ -59,71 +123,194 @@ pub struct Range {
     duration: Option<Duration>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "PascalCase")]
+impl Range {
+    pub fn start_time(&self) -> Option<Duration> {
+        self.start_time
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct ProgramInformation {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     lang: String,
-    #[serde(default, rename = "moreInformationURL")]
+    #[serde(
+        default,
+        rename = "moreInformationURL",
+        skip_serializing_if = "is_default"
+    )]
     more_information_url: String,
-    #[serde(default)]
+    #[serde(
+        rename = "Title",
+        default,
+        serialize_with = "text_element::serialize",
+        skip_serializing_if = "is_default"
+    )]
     title: String,
-    #[serde(default)]
+    #[serde(
+        rename = "Source",
+        default,
+        serialize_with = "text_element::serialize",
+        skip_serializing_if = "is_default"
+    )]
     source: String,
-    #[serde(default)]
+    #[serde(
+        rename = "Copyright",
+        default,
+        serialize_with = "text_element::serialize",
+        skip_serializing_if = "is_default"
+    )]
     copyright: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl ProgramInformation {
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    pub fn more_information_url(&self) -> &str {
+        &self.more_information_url
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn copyright(&self) -> &str {
+        &self.copyright
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Event {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     presentation_time: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     duration: f64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     id: u64,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     message_data: String,
+    #[serde(rename = "$value", default, skip_serializing_if = "is_default")]
+    content: String,
+}
+
+impl Event {
+    pub fn presentation_time(&self) -> u64 {
+        self.presentation_time
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn message_data(&self) -> &str {
+        &self.message_data
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct EventStream {
     // Attribut
-    #[serde(rename = "xlink:href", default)]
+    #[serde(rename = "xlink:href", default, skip_serializing_if = "is_default")]
     href: String,
-    #[serde(rename = "xlink:actuate", default = "HrefActuate::default")]
+    #[serde(
+        rename = "xlink:actuate",
+        default = "HrefActuate::default",
+        skip_serializing_if = "is_default"
+    )]
     href_actuate: HrefActuate,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     message_data: String,
     scheme_id_uri: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     value: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     timescale: u64,
 
     // children
     #[serde(default, rename = "Event")]
     events: Vec<Event>,
 }
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+
+impl EventStream {
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+
+    pub fn href_actuate(&self) -> HrefActuate {
+        self.href_actuate
+    }
+
+    pub fn message_data(&self) -> &str {
+        &self.message_data
+    }
+
+    pub fn scheme_id_uri(&self) -> &str {
+        &self.scheme_id_uri
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn timescale(&self) -> u64 {
+        self.timescale
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn events_mut(&mut self) -> &mut Vec<Event> {
+        &mut self.events
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Subset {
     contains: Vec<u64>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     id: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl Subset {
+    pub fn contains(&self) -> &[u64] {
+        &self.contains
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ContentComponent {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     id: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     lang: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     content_type: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     par: String,
 
     #[serde(rename = "Accessibility", default)]
@@ -136,29 +323,84 @@ pub struct ContentComponent {
     viewpoints: Vec<Descriptor>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl ContentComponent {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    pub fn par(&self) -> &str {
+        &self.par
+    }
+
+    pub fn accessibilities(&self) -> &[Descriptor] {
+        &self.accessibilities
+    }
+
+    pub fn accessibilities_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.accessibilities
+    }
+
+    pub fn roles(&self) -> &[Descriptor] {
+        &self.roles
+    }
+
+    pub fn roles_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.roles
+    }
+
+    pub fn ratings(&self) -> &[Descriptor] {
+        &self.ratings
+    }
+
+    pub fn ratings_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.ratings
+    }
+
+    pub fn viewpoints(&self) -> &[Descriptor] {
+        &self.viewpoints
+    }
+
+    pub fn viewpoints_mut(&mut self) -> &mut Vec<Descriptor> {
+        &mut self.viewpoints
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum VideoScan {
+    #[default]
     Progressive,
     Interlaced,
     Unknown,
 }
 
-impl VideoScan {
-    pub fn default() -> VideoScan {
-        VideoScan::Progressive
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum SwitchingType {
     Media,
     Bitstream,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Switching {
     interval: u64,
     r#type: SwitchingType,
 }
+
+impl Switching {
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn r#type(&self) -> SwitchingType {
+        self.r#type
+    }
+}
@@ -0,0 +1,770 @@
+//! Boxes of media segments.
+use super::{children, Cursor, FourCC, Mp4Error, ProtectionSystemHeader};
+
+/// `moof` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovieFragment {
+    sequence_number: u32,
+    track_fragments: Vec<TrackFragment>,
+    protection_system_headers: Vec<ProtectionSystemHeader>,
+}
+
+impl MovieFragment {
+    pub(super) fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut sequence_number = None;
+        let mut track_fragments = Vec::new();
+        let mut protection_system_headers = Vec::new();
+        for child in children(payload) {
+            let child = child?;
+            match child.box_type.as_bytes() {
+                b"mfhd" => {
+                    let mut cursor = Cursor::new(child.payload, child.box_type);
+                    cursor.full_box(0)?;
+                    sequence_number = Some(cursor.u32()?);
+                }
+                b"traf" => track_fragments.push(TrackFragment::parse(child.payload)?),
+                b"pssh" => {
+                    protection_system_headers.push(ProtectionSystemHeader::parse(child.payload)?)
+                }
+                _ => {}
+            }
+        }
+        Ok(MovieFragment {
+            sequence_number: sequence_number.ok_or(Mp4Error::MissingBox(
+                FourCC::new(b"moof"),
+                FourCC::new(b"mfhd"),
+            ))?,
+            track_fragments,
+            protection_system_headers,
+        })
+    }
+
+    pub fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    pub fn track_fragments(&self) -> &[TrackFragment] {
+        &self.track_fragments
+    }
+
+    pub fn protection_system_headers(&self) -> &[ProtectionSystemHeader] {
+        &self.protection_system_headers
+    }
+}
+
+/// `traf` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackFragment {
+    header: TrackFragmentHeader,
+    base_media_decode_time: Option<u64>,
+    runs: Vec<TrackRun>,
+    sample_encryption: Option<SampleEncryption>,
+}
+
+impl TrackFragment {
+    fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut header = None;
+        let mut base_media_decode_time = None;
+        let mut runs = Vec::new();
+        let mut sample_encryption = None;
+        for child in children(payload) {
+            let child = child?;
+            let mut cursor = Cursor::new(child.payload, child.box_type);
+            match child.box_type.as_bytes() {
+                b"tfhd" => header = Some(TrackFragmentHeader::parse(&mut cursor)?),
+                b"tfdt" => {
+                    let (version, _) = cursor.full_box(1)?;
+                    base_media_decode_time = Some(cursor.u32_or_u64(version)?);
+                }
+                b"trun" => runs.push(TrackRun::parse(&mut cursor)?),
+                b"senc" => {
+                    let (_, flags) = cursor.full_box(0)?;
+                    sample_encryption = Some(SampleEncryption {
+                        flags,
+                        data: cursor.rest().to_vec(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(TrackFragment {
+            header: header.ok_or(Mp4Error::MissingBox(
+                FourCC::new(b"traf"),
+                FourCC::new(b"tfhd"),
+            ))?,
+            base_media_decode_time,
+            runs,
+            sample_encryption,
+        })
+    }
+
+    pub fn header(&self) -> &TrackFragmentHeader {
+        &self.header
+    }
+
+    /// `tfdt` box: decode time of the first sample, in the timescale of the track.
+    pub fn base_media_decode_time(&self) -> Option<u64> {
+        self.base_media_decode_time
+    }
+
+    pub fn runs(&self) -> &[TrackRun] {
+        &self.runs
+    }
+
+    pub fn sample_encryption(&self) -> Option<&SampleEncryption> {
+        self.sample_encryption.as_ref()
+    }
+
+    /// Duration of the samples of all the runs, `default_sample_duration` being the
+    /// one of the `trex` box of the track.
+    pub fn duration(&self, default_sample_duration: u32) -> u64 {
+        let default = self
+            .header
+            .default_sample_duration
+            .unwrap_or(default_sample_duration);
+        self.runs
+            .iter()
+            .map(|run| match run.flags & 0x100 {
+                0 => u64::from(run.sample_count) * u64::from(default),
+                _ => run
+                    .samples()
+                    .map(|sample| u64::from(sample.duration.unwrap_or(default)))
+                    .sum(),
+            })
+            .sum()
+    }
+}
+
+/// `tfhd` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackFragmentHeader {
+    flags: u32,
+    track_id: u32,
+    base_data_offset: Option<u64>,
+    sample_description_index: Option<u32>,
+    default_sample_duration: Option<u32>,
+    default_sample_size: Option<u32>,
+    default_sample_flags: Option<u32>,
+}
+
+impl TrackFragmentHeader {
+    fn parse(cursor: &mut Cursor) -> Result<Self, Mp4Error> {
+        let (_, flags) = cursor.full_box(0)?;
+        let track_id = cursor.u32()?;
+        let base_data_offset = match flags & 0x1 {
+            0 => None,
+            _ => Some(cursor.u64()?),
+        };
+        let mut optional = |flag| match flags & flag {
+            0 => Ok(None),
+            _ => cursor.u32().map(Some),
+        };
+        Ok(TrackFragmentHeader {
+            flags,
+            track_id,
+            base_data_offset,
+            sample_description_index: optional(0x2)?,
+            default_sample_duration: optional(0x8)?,
+            default_sample_size: optional(0x10)?,
+            default_sample_flags: optional(0x20)?,
+        })
+    }
+
+    pub fn track_id(&self) -> u32 {
+        self.track_id
+    }
+
+    pub fn base_data_offset(&self) -> Option<u64> {
+        self.base_data_offset
+    }
+
+    pub fn sample_description_index(&self) -> Option<u32> {
+        self.sample_description_index
+    }
+
+    pub fn default_sample_duration(&self) -> Option<u32> {
+        self.default_sample_duration
+    }
+
+    pub fn default_sample_size(&self) -> Option<u32> {
+        self.default_sample_size
+    }
+
+    pub fn default_sample_flags(&self) -> Option<u32> {
+        self.default_sample_flags
+    }
+
+    pub fn duration_is_empty(&self) -> bool {
+        self.flags & 0x10000 != 0
+    }
+
+    /// Whether the data offsets of the runs are relative to the start of the `moof`
+    /// box, as required by the DASH profiles of CMAF.
+    pub fn default_base_is_moof(&self) -> bool {
+        self.flags & 0x20000 != 0
+    }
+}
+
+/// `trun` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackRun {
+    version: u8,
+    flags: u32,
+    data_offset: Option<i32>,
+    first_sample_flags: Option<u32>,
+    sample_count: u32,
+    /// Fields of the samples given by the flags, 4 bytes each.
+    sample_data: Vec<u8>,
+}
+
+impl TrackRun {
+    fn parse(cursor: &mut Cursor) -> Result<Self, Mp4Error> {
+        let (version, flags) = cursor.full_box(1)?;
+        let sample_count = cursor.u32()?;
+        let data_offset = match flags & 0x1 {
+            0 => None,
+            _ => Some(cursor.u32()? as i32),
+        };
+        let first_sample_flags = match flags & 0x4 {
+            0 => None,
+            _ => Some(cursor.u32()?),
+        };
+        let sample_size = sample_size(flags);
+        cursor.entries(sample_count, sample_size)?;
+        let sample_data = cursor.bytes(sample_count as usize * sample_size)?.to_vec();
+        Ok(TrackRun {
+            version,
+            flags,
+            data_offset,
+            first_sample_flags,
+            sample_count,
+            sample_data,
+        })
+    }
+
+    /// Offset of the data of the first sample from the base data offset.
+    pub fn data_offset(&self) -> Option<i32> {
+        self.data_offset
+    }
+
+    pub fn first_sample_flags(&self) -> Option<u32> {
+        self.first_sample_flags
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Samples of the run, read as they are iterated: when they all take the defaults,
+    /// the box holds nothing but their count.
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = TrackRunSample> + '_ {
+        let size = sample_size(self.flags);
+        (0..self.sample_count as usize).map(move |index| {
+            let mut cursor = Cursor::new(&self.sample_data[index * size..], FourCC::new(b"trun"));
+            let mut optional = |flag| match self.flags & flag {
+                0 => None,
+                _ => cursor.u32().ok(),
+            };
+            TrackRunSample {
+                duration: optional(0x100),
+                size: optional(0x200),
+                flags: optional(0x400),
+                composition_time_offset: optional(0x800).map(|offset| match self.version {
+                    0 => i64::from(offset),
+                    _ => i64::from(offset as i32),
+                }),
+            }
+        })
+    }
+}
+
+/// Bytes of each sample of a `trun` box: 4 for each of the duration, size, flags and
+/// composition time offset present.
+fn sample_size(flags: u32) -> usize {
+    4 * (flags & 0xf00).count_ones() as usize
+}
+
+/// Sample of a [`TrackRun`], its fields are `None` when the defaults of the `tfhd` or
+/// `trex` boxes apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackRunSample {
+    duration: Option<u32>,
+    size: Option<u32>,
+    flags: Option<u32>,
+    composition_time_offset: Option<i64>,
+}
+
+impl TrackRunSample {
+    pub fn duration(&self) -> Option<u32> {
+        self.duration
+    }
+
+    pub fn size(&self) -> Option<u32> {
+        self.size
+    }
+
+    pub fn flags(&self) -> Option<u32> {
+        self.flags
+    }
+
+    pub fn composition_time_offset(&self) -> Option<i64> {
+        self.composition_time_offset
+    }
+}
+
+/// `senc` box. Its entries can only be read knowing the IV size from the `tenc` box of
+/// the initialization segment, see [`SampleEncryption::samples`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleEncryption {
+    flags: u32,
+    data: Vec<u8>,
+}
+
+impl SampleEncryption {
+    pub fn uses_subsamples(&self) -> bool {
+        self.flags & 0x2 != 0
+    }
+
+    /// Entries of the samples, with IVs of `per_sample_iv_size` bytes. Fails when the
+    /// box can't hold as many entries as it counts.
+    pub fn samples(&self, per_sample_iv_size: u8) -> Result<SampleEncryptionEntries<'_>, Mp4Error> {
+        let mut cursor = Cursor::new(&self.data, FourCC::new(b"senc"));
+        let left = cursor.u32()?;
+        // The IV, then the count of subsamples.
+        let entry_size =
+            usize::from(per_sample_iv_size) + if self.uses_subsamples() { 2 } else { 0 };
+        cursor.entries(left, entry_size)?;
+        Ok(SampleEncryptionEntries {
+            cursor,
+            left,
+            per_sample_iv_size,
+            uses_subsamples: self.uses_subsamples(),
+        })
+    }
+}
+
+/// Entries of a [`SampleEncryption`] box, read as they are iterated. After an entry
+/// which can't be read, there are no more.
+pub struct SampleEncryptionEntries<'a> {
+    cursor: Cursor<'a>,
+    left: u32,
+    per_sample_iv_size: u8,
+    uses_subsamples: bool,
+}
+
+impl SampleEncryptionEntries<'_> {
+    fn entry(&mut self) -> Result<SampleEncryptionEntry, Mp4Error> {
+        let iv = self
+            .cursor
+            .bytes(usize::from(self.per_sample_iv_size))?
+            .to_vec();
+        let mut subsamples = Vec::new();
+        if self.uses_subsamples {
+            for _ in 0..self.cursor.u16()? {
+                subsamples.push((self.cursor.u16()?, self.cursor.u32()?));
+            }
+        }
+        Ok(SampleEncryptionEntry { iv, subsamples })
+    }
+}
+
+impl Iterator for SampleEncryptionEntries<'_> {
+    type Item = Result<SampleEncryptionEntry, Mp4Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        let entry = self.entry();
+        if entry.is_err() {
+            self.left = 0;
+        }
+        Some(entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.left as usize))
+    }
+}
+
+/// Entry of a [`SampleEncryption`] box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleEncryptionEntry {
+    iv: Vec<u8>,
+    subsamples: Vec<(u16, u32)>,
+}
+
+impl SampleEncryptionEntry {
+    /// Empty when a constant IV is used.
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    /// Clear and encrypted bytes of the subsamples.
+    pub fn subsamples(&self) -> &[(u16, u32)] {
+        &self.subsamples
+    }
+}
+
+/// `sidx` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentIndex {
+    reference_id: u32,
+    timescale: u32,
+    earliest_presentation_time: u64,
+    first_offset: u64,
+    references: Vec<SegmentReference>,
+}
+
+impl SegmentIndex {
+    pub(super) fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, FourCC::new(b"sidx"));
+        let (version, _) = cursor.full_box(1)?;
+        let reference_id = cursor.u32()?;
+        let timescale = cursor.u32()?;
+        let earliest_presentation_time = cursor.u32_or_u64(version)?;
+        let first_offset = cursor.u32_or_u64(version)?;
+        cursor.skip(2)?;
+        let mut references = Vec::new();
+        for _ in 0..cursor.u16()? {
+            let size = cursor.u32()?;
+            let subsegment_duration = cursor.u32()?;
+            let sap = cursor.u32()?;
+            references.push(SegmentReference {
+                reference_type: (size >> 31) as u8,
+                referenced_size: size & 0x7fff_ffff,
+                subsegment_duration,
+                starts_with_sap: sap >> 31 == 1,
+                sap_type: ((sap >> 28) & 0x7) as u8,
+                sap_delta_time: sap & 0x0fff_ffff,
+            });
+        }
+        Ok(SegmentIndex {
+            reference_id,
+            timescale,
+            earliest_presentation_time,
+            first_offset,
+            references,
+        })
+    }
+
+    pub fn reference_id(&self) -> u32 {
+        self.reference_id
+    }
+
+    pub fn timescale(&self) -> u32 {
+        self.timescale
+    }
+
+    pub fn earliest_presentation_time(&self) -> u64 {
+        self.earliest_presentation_time
+    }
+
+    /// Bytes between the end of the box and the first referenced byte.
+    pub fn first_offset(&self) -> u64 {
+        self.first_offset
+    }
+
+    pub fn references(&self) -> &[SegmentReference] {
+        &self.references
+    }
+}
+
+/// Reference of a [`SegmentIndex`] to a subsegment, or to another `sidx` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentReference {
+    reference_type: u8,
+    referenced_size: u32,
+    subsegment_duration: u32,
+    starts_with_sap: bool,
+    sap_type: u8,
+    sap_delta_time: u32,
+}
+
+impl SegmentReference {
+    /// Whether the reference is to another `sidx` box rather than to media.
+    pub fn is_index(&self) -> bool {
+        self.reference_type == 1
+    }
+
+    pub fn referenced_size(&self) -> u32 {
+        self.referenced_size
+    }
+
+    pub fn subsegment_duration(&self) -> u32 {
+        self.subsegment_duration
+    }
+
+    pub fn starts_with_sap(&self) -> bool {
+        self.starts_with_sap
+    }
+
+    pub fn sap_type(&self) -> u8 {
+        self.sap_type
+    }
+
+    pub fn sap_delta_time(&self) -> u32 {
+        self.sap_delta_time
+    }
+}
+
+/// `emsg` box: an inband event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventMessage {
+    version: u8,
+    scheme_id_uri: String,
+    value: String,
+    timescale: u32,
+    presentation_time: u64,
+    event_duration: u32,
+    id: u32,
+    message_data: Vec<u8>,
+}
+
+impl EventMessage {
+    pub(super) fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, FourCC::new(b"emsg"));
+        let (version, _) = cursor.full_box(1)?;
+        let message = if version == 0 {
+            let scheme_id_uri = cursor.string()?;
+            let value = cursor.string()?;
+            EventMessage {
+                version,
+                scheme_id_uri,
+                value,
+                timescale: cursor.u32()?,
+                presentation_time: u64::from(cursor.u32()?),
+                event_duration: cursor.u32()?,
+                id: cursor.u32()?,
+                message_data: Vec::new(),
+            }
+        } else {
+            let timescale = cursor.u32()?;
+            let presentation_time = cursor.u64()?;
+            let event_duration = cursor.u32()?;
+            let id = cursor.u32()?;
+            EventMessage {
+                version,
+                scheme_id_uri: cursor.string()?,
+                value: cursor.string()?,
+                timescale,
+                presentation_time,
+                event_duration,
+                id,
+                message_data: Vec::new(),
+            }
+        };
+        Ok(EventMessage {
+            message_data: cursor.rest().to_vec(),
+            ..message
+        })
+    }
+
+    pub fn scheme_id_uri(&self) -> &str {
+        &self.scheme_id_uri
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn timescale(&self) -> u32 {
+        self.timescale
+    }
+
+    /// Presentation time of a version 1 box.
+    pub fn presentation_time(&self) -> Option<u64> {
+        (self.version == 1).then_some(self.presentation_time)
+    }
+
+    /// Presentation time of a version 0 box, relative to the earliest presentation time
+    /// of the segment.
+    pub fn presentation_time_delta(&self) -> Option<u64> {
+        (self.version == 0).then_some(self.presentation_time)
+    }
+
+    /// 0xFFFFFFFF for an unknown duration.
+    pub fn event_duration(&self) -> u32 {
+        self.event_duration
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn message_data(&self) -> &[u8] {
+        &self.message_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{boxed, full};
+    use super::super::{parse, Mp4Box};
+    use super::*;
+
+    fn words(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn should_parse_movie_fragments() {
+        let mut senc = words(&[2]);
+        senc.extend([1; 8]);
+        senc.extend(1u16.to_be_bytes());
+        senc.extend(16u16.to_be_bytes());
+        senc.extend(100u32.to_be_bytes());
+        senc.extend([2; 8]);
+        senc.extend(0u16.to_be_bytes());
+
+        let mut traf = full(b"tfhd", 0, 0x2_0008, &words(&[1, 1000]));
+        traf.extend(full(b"tfdt", 1, 0, &90000u64.to_be_bytes()));
+        traf.extend(full(
+            b"trun",
+            1,
+            0xa05,
+            &words(&[2, 120, 0x0200_0000, 50, (-3i32) as u32, 60, 6]),
+        ));
+        traf.extend(full(b"senc", 0, 0x2, &senc));
+        let mut moof = full(b"mfhd", 0, 0, &words(&[7]));
+        moof.extend(boxed(b"traf", &traf));
+
+        let boxes = parse(&boxed(b"moof", &moof)).unwrap();
+
+        let fragment = match &boxes[0] {
+            Mp4Box::Moof(fragment) => fragment,
+            other => panic!("Unexpected box {:?}", other),
+        };
+        assert_eq!(fragment.sequence_number(), 7);
+        let track = &fragment.track_fragments()[0];
+        assert_eq!(track.header().track_id(), 1);
+        assert_eq!(track.header().default_sample_duration(), Some(1000));
+        assert!(track.header().default_base_is_moof());
+        assert_eq!(track.base_media_decode_time(), Some(90000));
+        assert_eq!(track.duration(3000), 2000);
+
+        let run = &track.runs()[0];
+        assert_eq!(run.data_offset(), Some(120));
+        assert_eq!(run.first_sample_flags(), Some(0x0200_0000));
+        assert_eq!(run.sample_count(), 2);
+        let samples: Vec<_> = run.samples().collect();
+        assert_eq!(samples[0].size(), Some(50));
+        assert_eq!(samples[0].duration(), None);
+        assert_eq!(samples[0].composition_time_offset(), Some(-3));
+        assert_eq!(samples[1].composition_time_offset(), Some(6));
+
+        let samples: Vec<_> = track
+            .sample_encryption()
+            .unwrap()
+            .samples(8)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(samples[0].iv(), [1; 8]);
+        assert_eq!(samples[0].subsamples(), [(16, 100)]);
+        assert!(samples[1].subsamples().is_empty());
+        assert!(track.sample_encryption().unwrap().samples(16).is_err());
+    }
+
+    #[test]
+    fn should_reject_sample_counts_exceeding_the_box() {
+        let trun = full(b"trun", 0, 0x300, &words(&[u32::MAX, 1000, 50]));
+        let mut traf = full(b"tfhd", 0, 0, &words(&[1]));
+        traf.extend(trun);
+        let mut moof = full(b"mfhd", 0, 0, &words(&[1]));
+        moof.extend(boxed(b"traf", &traf));
+        assert!(matches!(
+            parse(&boxed(b"moof", &moof)),
+            Err(Mp4Error::Truncated(box_type)) if box_type == FourCC::new(b"trun")
+        ));
+
+        let mut senc = words(&[u32::MAX]);
+        senc.extend([1; 8]);
+        senc.extend(0u16.to_be_bytes());
+        let mut traf = full(b"tfhd", 0, 0, &words(&[1]));
+        traf.extend(full(b"senc", 0, 0x2, &senc));
+        let mut moof = full(b"mfhd", 0, 0, &words(&[1]));
+        moof.extend(boxed(b"traf", &traf));
+        let boxes = parse(&boxed(b"moof", &moof)).unwrap();
+        let fragment = match &boxes[0] {
+            Mp4Box::Moof(fragment) => fragment,
+            other => panic!("Unexpected box {:?}", other),
+        };
+        let encryption = fragment.track_fragments()[0].sample_encryption().unwrap();
+        assert!(matches!(
+            encryption.samples(8),
+            Err(Mp4Error::Truncated(box_type)) if box_type == FourCC::new(b"senc")
+        ));
+    }
+
+    #[test]
+    fn should_count_samples_taking_the_defaults_without_reading_them() {
+        let mut traf = full(b"tfhd", 0, 0x8, &words(&[1, 1000]));
+        traf.extend(full(b"trun", 0, 0, &words(&[u32::MAX])));
+        traf.extend(full(b"senc", 0, 0, &words(&[u32::MAX])));
+        let mut moof = full(b"mfhd", 0, 0, &words(&[1]));
+        moof.extend(boxed(b"traf", &traf));
+
+        let boxes = parse(&boxed(b"moof", &moof)).unwrap();
+
+        let fragment = match &boxes[0] {
+            Mp4Box::Moof(fragment) => fragment,
+            other => panic!("Unexpected box {:?}", other),
+        };
+        let track = &fragment.track_fragments()[0];
+        let run = &track.runs()[0];
+        assert_eq!(run.sample_count(), u32::MAX);
+        assert_eq!(run.samples().len(), u32::MAX as usize);
+        assert_eq!(run.samples().nth(1000).unwrap().duration(), None);
+        assert_eq!(track.duration(0), u64::from(u32::MAX) * 1000);
+        let mut entries = track.sample_encryption().unwrap().samples(0).unwrap();
+        assert!(entries.next().unwrap().unwrap().iv().is_empty());
+    }
+
+    #[test]
+    fn should_parse_segment_indexes() {
+        let mut sidx = words(&[1, 1000]);
+        sidx.extend(5000u64.to_be_bytes());
+        sidx.extend(0u64.to_be_bytes());
+        sidx.extend(words(&[2]));
+        sidx.extend(words(&[3000, 2000, 0x9000_0000]));
+        sidx.extend(words(&[0x8000_0000 | 40, 4000, 0]));
+
+        let boxes = parse(&full(b"sidx", 1, 0, &sidx)).unwrap();
+
+        let index = match &boxes[0] {
+            Mp4Box::Sidx(index) => index,
+            other => panic!("Unexpected box {:?}", other),
+        };
+        assert_eq!(index.timescale(), 1000);
+        assert_eq!(index.earliest_presentation_time(), 5000);
+        let references = index.references();
+        assert!(!references[0].is_index());
+        assert_eq!(references[0].referenced_size(), 3000);
+        assert!(references[0].starts_with_sap());
+        assert_eq!(references[0].sap_type(), 1);
+        assert!(references[1].is_index());
+        assert_eq!(references[1].referenced_size(), 40);
+    }
+
+    #[test]
+    fn should_parse_event_messages() {
+        let mut emsg = words(&[1000]);
+        emsg.extend(12000u64.to_be_bytes());
+        emsg.extend(words(&[500, 3]));
+        emsg.extend(b"urn:mpeg:dash:event:2012\x001\0data");
+
+        let message = EventMessage::parse(&full(b"emsg", 1, 0, &emsg)[8..]).unwrap();
+
+        assert_eq!(message.scheme_id_uri(), "urn:mpeg:dash:event:2012");
+        assert_eq!(message.value(), "1");
+        assert_eq!(message.presentation_time(), Some(12000));
+        assert_eq!(message.presentation_time_delta(), None);
+        assert_eq!((message.event_duration(), message.id()), (500, 3));
+        assert_eq!(message.message_data(), b"data");
+    }
+}
@@ -0,0 +1,788 @@
+//! Boxes of initialization segments.
+use super::{children, Cursor, FourCC, Mp4Error, Uuid};
+
+/// `ftyp` or `styp` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileType {
+    major_brand: FourCC,
+    minor_version: u32,
+    compatible_brands: Vec<FourCC>,
+}
+
+impl FileType {
+    pub(super) fn parse(box_type: FourCC, payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, box_type);
+        let major_brand = cursor.fourcc()?;
+        let minor_version = cursor.u32()?;
+        let mut compatible_brands = Vec::new();
+        while cursor.remaining() >= 4 {
+            compatible_brands.push(cursor.fourcc()?);
+        }
+        Ok(FileType {
+            major_brand,
+            minor_version,
+            compatible_brands,
+        })
+    }
+
+    pub fn major_brand(&self) -> FourCC {
+        self.major_brand
+    }
+
+    pub fn minor_version(&self) -> u32 {
+        self.minor_version
+    }
+
+    pub fn compatible_brands(&self) -> &[FourCC] {
+        &self.compatible_brands
+    }
+
+    /// Whether `brand` is the major brand or one of the compatible ones.
+    pub fn has_brand(&self, brand: FourCC) -> bool {
+        self.major_brand == brand || self.compatible_brands.contains(&brand)
+    }
+}
+
+/// `moov` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    tracks: Vec<Track>,
+    extends: Option<MovieExtends>,
+    protection_system_headers: Vec<ProtectionSystemHeader>,
+}
+
+impl Movie {
+    pub(super) fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut movie = Movie {
+            tracks: Vec::new(),
+            extends: None,
+            protection_system_headers: Vec::new(),
+        };
+        for child in children(payload) {
+            let child = child?;
+            match child.box_type.as_bytes() {
+                b"trak" => movie.tracks.push(Track::parse(child.payload)?),
+                b"mvex" => movie.extends = Some(MovieExtends::parse(child.payload)?),
+                b"pssh" => movie
+                    .protection_system_headers
+                    .push(ProtectionSystemHeader::parse(child.payload)?),
+                _ => {}
+            }
+        }
+        Ok(movie)
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// `mvex` box, present when the movie is fragmented.
+    pub fn extends(&self) -> Option<&MovieExtends> {
+        self.extends.as_ref()
+    }
+
+    pub fn protection_system_headers(&self) -> &[ProtectionSystemHeader] {
+        &self.protection_system_headers
+    }
+
+    pub fn track(&self, track_id: u32) -> Option<&Track> {
+        self.tracks
+            .iter()
+            .find(|track| track.header.track_id == track_id)
+    }
+}
+
+/// `trak` box, with the boxes of its `mdia` box DASH cares about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+    header: TrackHeader,
+    media_header: MediaHeader,
+    handler_type: FourCC,
+    sample_entries: Vec<SampleEntry>,
+}
+
+impl Track {
+    fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let trak = FourCC::new(b"trak");
+        let mdia = FourCC::new(b"mdia");
+        let mut header = None;
+        let mut media = None;
+        for child in children(payload) {
+            let child = child?;
+            match child.box_type.as_bytes() {
+                b"tkhd" => header = Some(TrackHeader::parse(child.payload)?),
+                b"mdia" => media = Some(child.payload),
+                _ => {}
+            }
+        }
+        let header = header.ok_or(Mp4Error::MissingBox(trak, FourCC::new(b"tkhd")))?;
+        let media = media.ok_or(Mp4Error::MissingBox(trak, mdia))?;
+
+        let mut media_header = None;
+        let mut handler_type = None;
+        let mut sample_table = None;
+        for child in children(media) {
+            let child = child?;
+            match child.box_type.as_bytes() {
+                b"mdhd" => media_header = Some(MediaHeader::parse(child.payload)?),
+                b"hdlr" => {
+                    let mut cursor = Cursor::new(child.payload, child.box_type);
+                    cursor.full_box(0)?;
+                    cursor.skip(4)?;
+                    handler_type = Some(cursor.fourcc()?);
+                }
+                b"minf" => {
+                    for child in children(child.payload) {
+                        let child = child?;
+                        if child.box_type == "stbl" {
+                            sample_table = Some(child.payload);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let handler_type = handler_type.ok_or(Mp4Error::MissingBox(mdia, FourCC::new(b"hdlr")))?;
+        let mut sample_entries = Vec::new();
+        for child in children(sample_table.unwrap_or_default()) {
+            let child = child?;
+            if child.box_type == "stsd" {
+                sample_entries = SampleEntry::parse_all(child.payload, handler_type)?;
+            }
+        }
+        Ok(Track {
+            header,
+            media_header: media_header.ok_or(Mp4Error::MissingBox(mdia, FourCC::new(b"mdhd")))?,
+            handler_type,
+            sample_entries,
+        })
+    }
+
+    pub fn header(&self) -> &TrackHeader {
+        &self.header
+    }
+
+    pub fn media_header(&self) -> &MediaHeader {
+        &self.media_header
+    }
+
+    /// Type of the `hdlr` box: `vide`, `soun`, `text`, `subt`...
+    pub fn handler_type(&self) -> FourCC {
+        self.handler_type
+    }
+
+    /// Entries of the `stsd` box.
+    pub fn sample_entries(&self) -> &[SampleEntry] {
+        &self.sample_entries
+    }
+}
+
+/// `tkhd` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackHeader {
+    flags: u32,
+    track_id: u32,
+    duration: u64,
+    width: u32,
+    height: u32,
+}
+
+impl TrackHeader {
+    fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, FourCC::new(b"tkhd"));
+        let (version, flags) = cursor.full_box(1)?;
+        cursor.u32_or_u64(version)?;
+        cursor.u32_or_u64(version)?;
+        let track_id = cursor.u32()?;
+        cursor.skip(4)?;
+        let duration = cursor.u32_or_u64(version)?;
+        // Reserved, layer, alternate group, volume and matrix.
+        cursor.skip(8 + 8 + 36)?;
+        Ok(TrackHeader {
+            flags,
+            track_id,
+            duration,
+            width: cursor.u32()?,
+            height: cursor.u32()?,
+        })
+    }
+
+    pub fn track_id(&self) -> u32 {
+        self.track_id
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    /// In the timescale of the movie, usually 0 in a fragmented file.
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    /// Presentation width in pixels, 0 for tracks which aren't visual.
+    pub fn width(&self) -> f64 {
+        f64::from(self.width) / 65536.0
+    }
+
+    pub fn height(&self) -> f64 {
+        f64::from(self.height) / 65536.0
+    }
+}
+
+/// `mdhd` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaHeader {
+    timescale: u32,
+    duration: u64,
+    language: String,
+}
+
+impl MediaHeader {
+    fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, FourCC::new(b"mdhd"));
+        let (version, _) = cursor.full_box(1)?;
+        cursor.u32_or_u64(version)?;
+        cursor.u32_or_u64(version)?;
+        let timescale = cursor.u32()?;
+        let duration = cursor.u32_or_u64(version)?;
+        // ISO 639-2/T code, three 5 bits letters offset by 0x60.
+        let language = cursor.u16()?;
+        let language = [10, 5, 0]
+            .iter()
+            .map(|shift| char::from(((language >> shift) & 0x1f) as u8 + 0x60))
+            .collect();
+        Ok(MediaHeader {
+            timescale,
+            duration,
+            language,
+        })
+    }
+
+    pub fn timescale(&self) -> u32 {
+        self.timescale
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+}
+
+/// Entry of a `stsd` box: `avc1`, `hvc1`, `mp4a`, `encv`, `enca`, `stpp`...
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleEntry {
+    format: FourCC,
+    data_reference_index: u16,
+    dimensions: Option<(u16, u16)>,
+    channel_count: Option<u16>,
+    sample_rate: Option<u32>,
+    protection_scheme: Option<ProtectionScheme>,
+    children: Vec<FourCC>,
+}
+
+impl SampleEntry {
+    fn parse_all(payload: &[u8], handler_type: FourCC) -> Result<Vec<Self>, Mp4Error> {
+        let mut cursor = Cursor::new(payload, FourCC::new(b"stsd"));
+        cursor.full_box(1)?;
+        cursor.u32()?;
+        children(cursor.rest())
+            .map(|child| {
+                let child = child?;
+                SampleEntry::parse(child.box_type, child.payload, handler_type)
+            })
+            .collect()
+    }
+
+    fn parse(format: FourCC, payload: &[u8], handler_type: FourCC) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, format);
+        cursor.skip(6)?;
+        let mut entry = SampleEntry {
+            format,
+            data_reference_index: cursor.u16()?,
+            dimensions: None,
+            channel_count: None,
+            sample_rate: None,
+            protection_scheme: None,
+            children: Vec::new(),
+        };
+        match handler_type.as_bytes() {
+            b"vide" => {
+                cursor.skip(16)?;
+                entry.dimensions = Some((cursor.u16()?, cursor.u16()?));
+                // Resolutions, frame count, compressor name, depth.
+                cursor.skip(50)?;
+            }
+            b"soun" => {
+                cursor.skip(8)?;
+                entry.channel_count = Some(cursor.u16()?);
+                cursor.skip(6)?;
+                entry.sample_rate = Some(cursor.u32()? >> 16);
+            }
+            // The fields of the other entries aren't parsed, nor their children found.
+            _ => return Ok(entry),
+        }
+        for child in children(cursor.rest()) {
+            let child = child?;
+            match child.box_type.as_bytes() {
+                b"sinf" => entry.protection_scheme = Some(ProtectionScheme::parse(child.payload)?),
+                _ => entry.children.push(child.box_type),
+            }
+        }
+        Ok(entry)
+    }
+
+    pub fn format(&self) -> FourCC {
+        self.format
+    }
+
+    pub fn data_reference_index(&self) -> u16 {
+        self.data_reference_index
+    }
+
+    /// Width and height of a visual entry.
+    pub fn dimensions(&self) -> Option<(u16, u16)> {
+        self.dimensions
+    }
+
+    /// Channels of an audio entry.
+    pub fn channel_count(&self) -> Option<u16> {
+        self.channel_count
+    }
+
+    /// Sample rate of an audio entry, in Hz.
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    /// `sinf` box of an encrypted entry.
+    pub fn protection_scheme(&self) -> Option<&ProtectionScheme> {
+        self.protection_scheme.as_ref()
+    }
+
+    /// Types of the other children of a visual or audio entry, e.g. its `avcC` or
+    /// `esds` decoder configuration.
+    pub fn children(&self) -> &[FourCC] {
+        &self.children
+    }
+
+    /// Format of the samples before encryption: the `frma` of an encrypted entry.
+    pub fn original_format(&self) -> FourCC {
+        self.protection_scheme
+            .as_ref()
+            .map_or(self.format, ProtectionScheme::original_format)
+    }
+}
+
+/// `sinf` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectionScheme {
+    original_format: FourCC,
+    scheme_type: Option<FourCC>,
+    scheme_version: u32,
+    track_encryption: Option<TrackEncryption>,
+}
+
+impl ProtectionScheme {
+    fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut original_format = None;
+        let mut scheme = None;
+        let mut track_encryption = None;
+        for child in children(payload) {
+            let child = child?;
+            match child.box_type.as_bytes() {
+                b"frma" => {
+                    original_format = Some(Cursor::new(child.payload, child.box_type).fourcc()?)
+                }
+                b"schm" => {
+                    let mut cursor = Cursor::new(child.payload, child.box_type);
+                    cursor.full_box(0)?;
+                    scheme = Some((cursor.fourcc()?, cursor.u32()?));
+                }
+                b"schi" => {
+                    for child in children(child.payload) {
+                        let child = child?;
+                        if child.box_type == "tenc" {
+                            track_encryption = Some(TrackEncryption::parse(child.payload)?);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ProtectionScheme {
+            original_format: original_format.ok_or(Mp4Error::MissingBox(
+                FourCC::new(b"sinf"),
+                FourCC::new(b"frma"),
+            ))?,
+            scheme_type: scheme.map(|(scheme_type, _)| scheme_type),
+            scheme_version: scheme.map_or(0, |(_, version)| version),
+            track_encryption,
+        })
+    }
+
+    pub fn original_format(&self) -> FourCC {
+        self.original_format
+    }
+
+    /// `cenc`, `cbcs`, `cens` or `cbc1` for Common Encryption.
+    pub fn scheme_type(&self) -> Option<FourCC> {
+        self.scheme_type
+    }
+
+    pub fn scheme_version(&self) -> u32 {
+        self.scheme_version
+    }
+
+    pub fn track_encryption(&self) -> Option<&TrackEncryption> {
+        self.track_encryption.as_ref()
+    }
+}
+
+/// `tenc` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackEncryption {
+    crypt_byte_block: u8,
+    skip_byte_block: u8,
+    is_protected: bool,
+    per_sample_iv_size: u8,
+    key_id: Uuid,
+    constant_iv: Vec<u8>,
+}
+
+impl TrackEncryption {
+    fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, FourCC::new(b"tenc"));
+        let (version, _) = cursor.full_box(1)?;
+        cursor.skip(1)?;
+        let pattern = cursor.u8()?;
+        let (crypt_byte_block, skip_byte_block) = match version {
+            0 => (0, 0),
+            _ => (pattern >> 4, pattern & 0x0f),
+        };
+        let is_protected = cursor.u8()? == 1;
+        let per_sample_iv_size = cursor.u8()?;
+        let key_id = cursor.uuid()?;
+        let constant_iv = if is_protected && per_sample_iv_size == 0 {
+            let size = cursor.u8()?;
+            cursor.bytes(usize::from(size))?.to_vec()
+        } else {
+            Vec::new()
+        };
+        Ok(TrackEncryption {
+            crypt_byte_block,
+            skip_byte_block,
+            is_protected,
+            per_sample_iv_size,
+            key_id,
+            constant_iv,
+        })
+    }
+
+    /// Encrypted blocks of the pattern of `cens` and `cbcs`, 0 without pattern.
+    pub fn crypt_byte_block(&self) -> u8 {
+        self.crypt_byte_block
+    }
+
+    pub fn skip_byte_block(&self) -> u8 {
+        self.skip_byte_block
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.is_protected
+    }
+
+    /// Size of the IVs of the `senc` box, 0 when a constant IV is used.
+    pub fn per_sample_iv_size(&self) -> u8 {
+        self.per_sample_iv_size
+    }
+
+    /// Default key id, the `cenc:default_KID` of the MPD.
+    pub fn key_id(&self) -> Uuid {
+        self.key_id
+    }
+
+    pub fn constant_iv(&self) -> &[u8] {
+        &self.constant_iv
+    }
+}
+
+/// `mvex` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovieExtends {
+    fragment_duration: Option<u64>,
+    track_extends: Vec<TrackExtends>,
+}
+
+impl MovieExtends {
+    fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut extends = MovieExtends {
+            fragment_duration: None,
+            track_extends: Vec::new(),
+        };
+        for child in children(payload) {
+            let child = child?;
+            let mut cursor = Cursor::new(child.payload, child.box_type);
+            match child.box_type.as_bytes() {
+                b"mehd" => {
+                    let (version, _) = cursor.full_box(1)?;
+                    extends.fragment_duration = Some(cursor.u32_or_u64(version)?);
+                }
+                b"trex" => {
+                    cursor.full_box(0)?;
+                    extends.track_extends.push(TrackExtends {
+                        track_id: cursor.u32()?,
+                        default_sample_description_index: cursor.u32()?,
+                        default_sample_duration: cursor.u32()?,
+                        default_sample_size: cursor.u32()?,
+                        default_sample_flags: cursor.u32()?,
+                    });
+                }
+                _ => {}
+            }
+        }
+        Ok(extends)
+    }
+
+    /// `mehd` box: duration of the whole fragmented movie, in the timescale of the movie.
+    pub fn fragment_duration(&self) -> Option<u64> {
+        self.fragment_duration
+    }
+
+    pub fn track_extends(&self) -> &[TrackExtends] {
+        &self.track_extends
+    }
+}
+
+/// `trex` box: defaults of the samples of the fragments of a track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackExtends {
+    track_id: u32,
+    default_sample_description_index: u32,
+    default_sample_duration: u32,
+    default_sample_size: u32,
+    default_sample_flags: u32,
+}
+
+impl TrackExtends {
+    pub fn track_id(&self) -> u32 {
+        self.track_id
+    }
+
+    pub fn default_sample_description_index(&self) -> u32 {
+        self.default_sample_description_index
+    }
+
+    pub fn default_sample_duration(&self) -> u32 {
+        self.default_sample_duration
+    }
+
+    pub fn default_sample_size(&self) -> u32 {
+        self.default_sample_size
+    }
+
+    pub fn default_sample_flags(&self) -> u32 {
+        self.default_sample_flags
+    }
+}
+
+/// `pssh` box: data of a DRM system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectionSystemHeader {
+    system_id: Uuid,
+    key_ids: Vec<Uuid>,
+    data: Vec<u8>,
+}
+
+impl ProtectionSystemHeader {
+    pub(super) fn parse(payload: &[u8]) -> Result<Self, Mp4Error> {
+        let mut cursor = Cursor::new(payload, FourCC::new(b"pssh"));
+        let (version, _) = cursor.full_box(1)?;
+        let system_id = cursor.uuid()?;
+        let mut key_ids = Vec::new();
+        if version > 0 {
+            for _ in 0..cursor.u32()? {
+                key_ids.push(cursor.uuid()?);
+            }
+        }
+        let size = cursor.u32()? as usize;
+        Ok(ProtectionSystemHeader {
+            system_id,
+            key_ids,
+            data: cursor.bytes(size)?.to_vec(),
+        })
+    }
+
+    /// Id of the DRM system, as in the `urn:uuid:` scheme of the `ContentProtection`
+    /// descriptors.
+    pub fn system_id(&self) -> Uuid {
+        self.system_id
+    }
+
+    /// Key ids of a version 1 box.
+    pub fn key_ids(&self) -> &[Uuid] {
+        &self.key_ids
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::super::tests::{boxed, ftyp, full};
+    use super::super::{parse, Mp4Box};
+    use super::*;
+
+    /// `trak` box of track 2, 1280x720 and of timescale 90000, with one sample entry.
+    pub(crate) fn track(handler: &[u8; 4], entry: Vec<u8>) -> Vec<u8> {
+        let mut tkhd = vec![0; 8];
+        tkhd.extend(2u32.to_be_bytes());
+        tkhd.extend([0; 4 + 4 + 8 + 8 + 36]);
+        tkhd.extend((1280u32 << 16).to_be_bytes());
+        tkhd.extend((720u32 << 16).to_be_bytes());
+
+        let mut mdhd = vec![0; 8];
+        mdhd.extend(90000u32.to_be_bytes());
+        mdhd.extend(0u32.to_be_bytes());
+        // "eng"
+        mdhd.extend([0x15, 0xc7, 0, 0]);
+
+        let mut hdlr = vec![0; 4];
+        hdlr.extend(handler);
+        hdlr.extend([0; 12]);
+        hdlr.extend(b"handler\0");
+
+        let mut stsd = 1u32.to_be_bytes().to_vec();
+        stsd.extend(entry);
+        let stbl = boxed(b"stbl", &full(b"stsd", 0, 0, &stsd));
+        let mut mdia = full(b"mdhd", 0, 0, &mdhd);
+        mdia.extend(full(b"hdlr", 0, 0, &hdlr));
+        mdia.extend(boxed(b"minf", &stbl));
+
+        let mut trak = full(b"tkhd", 0, 3, &tkhd);
+        trak.extend(boxed(b"mdia", &mdia));
+        boxed(b"trak", &trak)
+    }
+
+    /// Fields of a visual sample entry, before its children.
+    pub(crate) fn visual_entry(width: u16, height: u16) -> Vec<u8> {
+        let mut entry = vec![0; 6];
+        entry.extend(1u16.to_be_bytes());
+        entry.extend([0; 16]);
+        entry.extend(width.to_be_bytes());
+        entry.extend(height.to_be_bytes());
+        entry.extend([0; 50]);
+        entry
+    }
+
+    /// Fields of an audio sample entry, before its children.
+    pub(crate) fn audio_entry(channel_count: u16, sample_rate: u32) -> Vec<u8> {
+        let mut entry = vec![0; 6];
+        entry.extend(1u16.to_be_bytes());
+        entry.extend([0; 8]);
+        entry.extend(channel_count.to_be_bytes());
+        entry.extend(16u16.to_be_bytes());
+        entry.extend([0; 4]);
+        entry.extend((sample_rate << 16).to_be_bytes());
+        entry
+    }
+
+    fn encrypted_video_entry() -> Vec<u8> {
+        let mut entry = visual_entry(1280, 720);
+        entry.extend(boxed(b"avcC", &[1, 2, 3]));
+
+        let mut tenc = vec![0, 0x19, 1, 16];
+        tenc.extend([0xab; 16]);
+        let mut schm = b"cbcs".to_vec();
+        schm.extend(0x10000u32.to_be_bytes());
+        let mut sinf = boxed(b"frma", b"avc1");
+        sinf.extend(full(b"schm", 0, 0, &schm));
+        sinf.extend(boxed(b"schi", &full(b"tenc", 1, 0, &tenc)));
+        entry.extend(boxed(b"sinf", &sinf));
+        boxed(b"encv", &entry)
+    }
+
+    fn pssh() -> Vec<u8> {
+        let mut pssh = [0x10; 16].to_vec();
+        pssh.extend(1u32.to_be_bytes());
+        pssh.extend([0xab; 16]);
+        pssh.extend(3u32.to_be_bytes());
+        pssh.extend([7, 8, 9]);
+        full(b"pssh", 1, 0, &pssh)
+    }
+
+    #[test]
+    fn should_parse_initialization_segments() {
+        let mut trex = 2u32.to_be_bytes().to_vec();
+        trex.extend(1u32.to_be_bytes());
+        trex.extend(3000u32.to_be_bytes());
+        trex.extend([0; 8]);
+        let mut moov = track(b"vide", encrypted_video_entry());
+        moov.extend(boxed(b"mvex", &full(b"trex", 0, 0, &trex)));
+        moov.extend(pssh());
+        let mut data = ftyp(b"ftyp");
+        data.extend(boxed(b"moov", &moov));
+
+        let boxes = parse(&data).unwrap();
+
+        let movie = match &boxes[1] {
+            Mp4Box::Moov(movie) => movie,
+            other => panic!("Unexpected box {:?}", other),
+        };
+        let track = movie.track(2).unwrap();
+        assert!(track.header().is_enabled());
+        assert_eq!(
+            (track.header().width(), track.header().height()),
+            (1280.0, 720.0)
+        );
+        assert_eq!(track.media_header().timescale(), 90000);
+        assert_eq!(track.media_header().language(), "eng");
+        assert_eq!(track.handler_type(), "vide");
+
+        let entry = &track.sample_entries()[0];
+        assert_eq!(entry.format(), "encv");
+        assert_eq!(entry.original_format(), "avc1");
+        assert_eq!(entry.dimensions(), Some((1280, 720)));
+        assert_eq!(entry.children(), [FourCC::new(b"avcC")]);
+        let scheme = entry.protection_scheme().unwrap();
+        assert_eq!(scheme.scheme_type(), Some(FourCC::new(b"cbcs")));
+        let encryption = scheme.track_encryption().unwrap();
+        assert_eq!(
+            (encryption.crypt_byte_block(), encryption.skip_byte_block()),
+            (1, 9)
+        );
+        assert!(encryption.is_protected());
+        assert_eq!(encryption.per_sample_iv_size(), 16);
+        assert_eq!(encryption.key_id(), Uuid::new([0xab; 16]));
+
+        let trex = &movie.extends().unwrap().track_extends()[0];
+        assert_eq!(trex.track_id(), 2);
+        assert_eq!(trex.default_sample_duration(), 3000);
+
+        let pssh = &movie.protection_system_headers()[0];
+        assert_eq!(
+            pssh.system_id().to_string(),
+            "10101010-1010-1010-1010-101010101010"
+        );
+        assert_eq!(pssh.key_ids(), [Uuid::new([0xab; 16])]);
+        assert_eq!(pssh.data(), [7, 8, 9]);
+    }
+
+    #[test]
+    fn should_parse_audio_sample_entries() {
+        let mut entry = audio_entry(2, 48000);
+        entry.extend(boxed(b"esds", &[0; 4]));
+
+        let track = Track::parse(&track(b"soun", boxed(b"mp4a", &entry))[8..]).unwrap();
+
+        let entry = &track.sample_entries()[0];
+        assert_eq!(entry.channel_count(), Some(2));
+        assert_eq!(entry.sample_rate(), Some(48000));
+        assert_eq!(entry.original_format(), "mp4a");
+    }
+}
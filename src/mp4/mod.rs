@@ -0,0 +1,618 @@
+//! Parsing of the ISO BMFF (ISO/IEC 14496-12) boxes of DASH initialization and media
+//! segments, and of their Common Encryption (ISO/IEC 23001-7) and DASH
+//! (ISO/IEC 23009-1 5.10.3.3) extensions.
+//!
+//! Boxes are read from byte slices with [`parse`], or from async readers with
+//! [`Mp4Reader`]. Only the boxes useful to DASH are parsed, the others are kept as
+//! [`Mp4Box::Other`] with their size.
+use std::fmt;
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+mod fragment;
+mod init;
+
+pub use fragment::{
+    EventMessage, MovieFragment, SampleEncryption, SampleEncryptionEntries, SampleEncryptionEntry,
+    SegmentIndex, SegmentReference, TrackFragment, TrackFragmentHeader, TrackRun, TrackRunSample,
+};
+#[cfg(test)]
+pub(crate) use init::tests as init_tests;
+pub use init::{
+    FileType, MediaHeader, Movie, MovieExtends, ProtectionScheme, ProtectionSystemHeader,
+    SampleEntry, Track, TrackEncryption, TrackExtends, TrackHeader,
+};
+
+/// Largest box read in memory by [`Mp4Reader`], `mdat` and unknown boxes aside.
+pub(crate) const MAX_BOX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Top-level boxes which are parsed, the others are skipped.
+const PARSED_BOXES: [&[u8; 4]; 7] = [
+    b"ftyp", b"styp", b"moov", b"moof", b"sidx", b"emsg", b"pssh",
+];
+
+/// Four-character code of a box type or a brand.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FourCC([u8; 4]);
+
+impl FourCC {
+    pub const fn new(code: &[u8; 4]) -> Self {
+        FourCC(*code)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+}
+
+impl fmt::Display for FourCC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            match byte {
+                0x20..=0x7e => write!(f, "{}", byte as char)?,
+                _ => write!(f, "\\x{:02x}", byte)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for FourCC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FourCC({})", self)
+    }
+}
+
+impl PartialEq<&str> for FourCC {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+/// 16 bytes identifier: a DRM system id or a key id, written as a UUID.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+    pub const fn new(bytes: [u8; 16]) -> Self {
+        Uuid(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if [4, 6, 8, 10].contains(&index) {
+                f.write_str("-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Uuid({})", self)
+    }
+}
+
+#[derive(Debug)]
+pub enum Mp4Error {
+    /// The box, or the data, ends before the fields of this box.
+    Truncated(FourCC),
+    /// The size of the box is smaller than its header or larger than its parent.
+    InvalidSize(FourCC, u64),
+    UnsupportedVersion(FourCC, u8),
+    /// A box lacks a mandatory child: the box, the child.
+    MissingBox(FourCC, FourCC),
+    Io(io::Error),
+}
+
+impl fmt::Display for Mp4Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mp4Error::Truncated(box_type) => write!(f, "Truncated {} box", box_type),
+            Mp4Error::InvalidSize(box_type, size) => {
+                write!(f, "Invalid size of {} box: {}", box_type, size)
+            }
+            Mp4Error::UnsupportedVersion(box_type, version) => {
+                write!(f, "Unsupported version of {} box: {}", box_type, version)
+            }
+            Mp4Error::MissingBox(parent, child) => {
+                write!(f, "Missing {} box in {} box", child, parent)
+            }
+            Mp4Error::Io(error) => write!(f, "Can't read boxes: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Mp4Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Mp4Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Mp4Error {
+    fn from(error: io::Error) -> Self {
+        Mp4Error::Io(error)
+    }
+}
+
+/// Top-level box of a segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mp4Box {
+    Ftyp(FileType),
+    Styp(FileType),
+    Moov(Movie),
+    Moof(MovieFragment),
+    Sidx(SegmentIndex),
+    Emsg(EventMessage),
+    Pssh(ProtectionSystemHeader),
+    /// Media data, only its size is kept.
+    Mdat(u64),
+    Other(FourCC, u64),
+}
+
+impl Mp4Box {
+    pub fn box_type(&self) -> FourCC {
+        match self {
+            Mp4Box::Ftyp(_) => FourCC::new(b"ftyp"),
+            Mp4Box::Styp(_) => FourCC::new(b"styp"),
+            Mp4Box::Moov(_) => FourCC::new(b"moov"),
+            Mp4Box::Moof(_) => FourCC::new(b"moof"),
+            Mp4Box::Sidx(_) => FourCC::new(b"sidx"),
+            Mp4Box::Emsg(_) => FourCC::new(b"emsg"),
+            Mp4Box::Pssh(_) => FourCC::new(b"pssh"),
+            Mp4Box::Mdat(_) => FourCC::new(b"mdat"),
+            Mp4Box::Other(box_type, _) => *box_type,
+        }
+    }
+
+    /// Parses the payload of a box of type `box_type` and total size `size`.
+    fn parse(box_type: FourCC, size: u64, payload: &[u8]) -> Result<Mp4Box, Mp4Error> {
+        Ok(match box_type.as_bytes() {
+            b"ftyp" => Mp4Box::Ftyp(FileType::parse(box_type, payload)?),
+            b"styp" => Mp4Box::Styp(FileType::parse(box_type, payload)?),
+            b"moov" => Mp4Box::Moov(Movie::parse(payload)?),
+            b"moof" => Mp4Box::Moof(MovieFragment::parse(payload)?),
+            b"sidx" => Mp4Box::Sidx(SegmentIndex::parse(payload)?),
+            b"emsg" => Mp4Box::Emsg(EventMessage::parse(payload)?),
+            b"pssh" => Mp4Box::Pssh(ProtectionSystemHeader::parse(payload)?),
+            b"mdat" => Mp4Box::Mdat(size),
+            _ => Mp4Box::Other(box_type, size),
+        })
+    }
+}
+
+/// Top-level boxes of a segment, or of several concatenated ones.
+pub fn parse(data: &[u8]) -> Result<Vec<Mp4Box>, Mp4Error> {
+    children(data)
+        .map(|child| {
+            let child = child?;
+            Mp4Box::parse(child.box_type, child.size, child.payload)
+        })
+        .collect()
+}
+
+/// Type and total size of the box starting `data`, `None` while its header is incomplete.
+pub(crate) fn box_header(data: &[u8]) -> Option<(FourCC, u64)> {
+    let mut header = Cursor::new(data, FourCC::new(b"\0\0\0\0"));
+    let size = header.u32().ok()?;
+    let box_type = header.fourcc().ok()?;
+    let size = match size {
+        0 => data.len() as u64,
+        1 => header.u64().ok()?,
+        size => u64::from(size),
+    };
+    Some((box_type, size))
+}
+
+/// Reads the top-level boxes of a segment one at a time, skipping the media data.
+#[derive(Debug)]
+pub struct Mp4Reader<R> {
+    reader: R,
+}
+
+impl<R: AsyncRead + Unpin> Mp4Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Mp4Reader { reader }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Next box, `None` at the end of the data.
+    pub async fn next(&mut self) -> Result<Option<Mp4Box>, Mp4Error> {
+        let mut header = [0; 8];
+        let read = read_up_to(&mut self.reader, &mut header).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        let box_type = FourCC::new(&[header[4], header[5], header[6], header[7]]);
+        if read < header.len() {
+            return Err(Mp4Error::Truncated(box_type));
+        }
+        let mut header_size = 8;
+        let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            0 => None,
+            1 => {
+                header_size += 8;
+                Some(
+                    self.reader
+                        .read_u64()
+                        .await
+                        .map_err(|error| truncated(error, box_type))?,
+                )
+            }
+            size => Some(u64::from(size)),
+        };
+        if box_type == "uuid" {
+            header_size += 16;
+            let mut user_type = [0; 16];
+            self.reader
+                .read_exact(&mut user_type)
+                .await
+                .map_err(|error| truncated(error, box_type))?;
+        }
+        if size.is_some_and(|size| size < header_size) {
+            return Err(Mp4Error::InvalidSize(box_type, size.unwrap_or_default()));
+        }
+
+        if !PARSED_BOXES.contains(&box_type.as_bytes()) {
+            let skipped = match size {
+                Some(size) => {
+                    let payload = size - header_size;
+                    let mut payload_reader = (&mut self.reader).take(payload);
+                    let skipped =
+                        tokio::io::copy(&mut payload_reader, &mut tokio::io::sink()).await?;
+                    if skipped < payload {
+                        return Err(Mp4Error::Truncated(box_type));
+                    }
+                    skipped
+                }
+                None => tokio::io::copy(&mut self.reader, &mut tokio::io::sink()).await?,
+            };
+            return Mp4Box::parse(box_type, skipped + header_size, &[]).map(Some);
+        }
+
+        let payload = match size {
+            Some(size) if size > MAX_BOX_SIZE => return Err(Mp4Error::InvalidSize(box_type, size)),
+            Some(size) => {
+                let mut payload = vec![0; (size - header_size) as usize];
+                self.reader
+                    .read_exact(&mut payload)
+                    .await
+                    .map_err(|error| truncated(error, box_type))?;
+                payload
+            }
+            None => {
+                let mut payload = Vec::new();
+                (&mut self.reader)
+                    .take(MAX_BOX_SIZE)
+                    .read_to_end(&mut payload)
+                    .await?;
+                payload
+            }
+        };
+        let size = payload.len() as u64 + header_size;
+        Mp4Box::parse(box_type, size, &payload).map(Some)
+    }
+
+    /// All the remaining boxes.
+    pub async fn read_all(&mut self) -> Result<Vec<Mp4Box>, Mp4Error> {
+        let mut boxes = Vec::new();
+        while let Some(parsed) = self.next().await? {
+            boxes.push(parsed);
+        }
+        Ok(boxes)
+    }
+}
+
+/// Fills `buffer` unless the data ends first, returns the bytes read.
+async fn read_up_to<R: AsyncRead + Unpin>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]).await? {
+            0 => break,
+            count => read += count,
+        }
+    }
+    Ok(read)
+}
+
+fn truncated(error: io::Error, box_type: FourCC) -> Mp4Error {
+    match error.kind() {
+        io::ErrorKind::UnexpectedEof => Mp4Error::Truncated(box_type),
+        _ => Mp4Error::Io(error),
+    }
+}
+
+/// Box within a byte slice.
+struct RawBox<'a> {
+    box_type: FourCC,
+    /// Total size, header included.
+    size: u64,
+    payload: &'a [u8],
+}
+
+/// Boxes following each other in `data`, e.g. the children of a container box.
+fn children(data: &[u8]) -> impl Iterator<Item = Result<RawBox<'_>, Mp4Error>> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let result = split_box(rest);
+        rest = match &result {
+            Ok((_, next)) => next,
+            Err(_) => &[],
+        };
+        Some(result.map(|(raw, _)| raw))
+    })
+}
+
+/// First box of `data`, and the data after it.
+fn split_box(data: &[u8]) -> Result<(RawBox<'_>, &[u8]), Mp4Error> {
+    let mut header = Cursor::new(data, FourCC::new(b"\0\0\0\0"));
+    let size = header.u32()?;
+    let box_type = FourCC::new(&header.array()?);
+    header.box_type = box_type;
+    let size = match size {
+        0 => data.len() as u64,
+        1 => header.u64()?,
+        size => u64::from(size),
+    };
+    if box_type == "uuid" {
+        header.skip(16)?;
+    }
+    let header_size = header.position() as u64;
+    if size < header_size || size > data.len() as u64 {
+        return Err(if size < header_size {
+            Mp4Error::InvalidSize(box_type, size)
+        } else {
+            Mp4Error::Truncated(box_type)
+        });
+    }
+    let (current, rest) = data.split_at(size as usize);
+    Ok((
+        RawBox {
+            box_type,
+            size,
+            payload: &current[header_size as usize..],
+        },
+        rest,
+    ))
+}
+
+/// Reader of the fields of a box payload.
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// Type of the box read, for the errors.
+    box_type: FourCC,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8], box_type: FourCC) -> Self {
+        Cursor {
+            data,
+            position: 0,
+            box_type,
+        }
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    /// Fails unless `count` entries of at least `size` bytes can remain, so that a
+    /// corrupt count doesn't make the entries read one by one until the data ends.
+    fn entries(&self, count: u32, size: usize) -> Result<(), Mp4Error> {
+        match (count as usize).checked_mul(size) {
+            Some(total) if total <= self.remaining() => Ok(()),
+            _ => Err(Mp4Error::Truncated(self.box_type)),
+        }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], Mp4Error> {
+        if self.remaining() < count {
+            return Err(Mp4Error::Truncated(self.box_type));
+        }
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Mp4Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), Mp4Error> {
+        self.bytes(count).map(|_| ())
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+
+    fn u8(&mut self) -> Result<u8, Mp4Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Mp4Error> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn u24(&mut self) -> Result<u32, Mp4Error> {
+        let [a, b, c] = self.array()?;
+        Ok(u32::from_be_bytes([0, a, b, c]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Mp4Error> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, Mp4Error> {
+        self.array().map(u64::from_be_bytes)
+    }
+
+    /// `u32` in version 0 of a box, `u64` from version 1.
+    fn u32_or_u64(&mut self, version: u8) -> Result<u64, Mp4Error> {
+        match version {
+            0 => self.u32().map(u64::from),
+            _ => self.u64(),
+        }
+    }
+
+    fn fourcc(&mut self) -> Result<FourCC, Mp4Error> {
+        self.array().map(|code| FourCC::new(&code))
+    }
+
+    fn uuid(&mut self) -> Result<Uuid, Mp4Error> {
+        self.array().map(Uuid::new)
+    }
+
+    /// Null-terminated UTF-8 string; a missing terminator ends it with the payload.
+    fn string(&mut self) -> Result<String, Mp4Error> {
+        let rest = &self.data[self.position..];
+        let length = rest.iter().position(|byte| *byte == 0);
+        let text = String::from_utf8_lossy(&rest[..length.unwrap_or(rest.len())]).to_string();
+        self.position += length.map_or(rest.len(), |length| length + 1);
+        Ok(text)
+    }
+
+    /// Version and flags of a full box, the version being checked against `max`.
+    fn full_box(&mut self, max_version: u8) -> Result<(u8, u32), Mp4Error> {
+        let version = self.u8()?;
+        if version > max_version {
+            return Err(Mp4Error::UnsupportedVersion(self.box_type, version));
+        }
+        Ok((version, self.u24()?))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Box of type `box_type` holding `payload`.
+    pub(crate) fn boxed(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    /// Full box of type `box_type` holding `payload` after its version and flags.
+    pub(crate) fn full(box_type: &[u8; 4], version: u8, flags: u32, payload: &[u8]) -> Vec<u8> {
+        let mut content = flags.to_be_bytes().to_vec();
+        content[0] = version;
+        content.extend_from_slice(payload);
+        boxed(box_type, &content)
+    }
+
+    pub(crate) fn ftyp(box_type: &[u8; 4]) -> Vec<u8> {
+        boxed(box_type, b"msdh\0\0\0\0msdhmsix")
+    }
+
+    #[test]
+    fn should_parse_top_level_boxes() {
+        let mut data = ftyp(b"styp");
+        data.extend(boxed(b"free", &[0; 4]));
+        // 64 bits size.
+        data.extend([0, 0, 0, 1]);
+        data.extend(b"mdat");
+        data.extend(20u64.to_be_bytes());
+        data.extend([1, 2, 3, 4]);
+
+        let boxes = parse(&data).unwrap();
+
+        let brands = match &boxes[0] {
+            Mp4Box::Styp(file_type) => file_type,
+            other => panic!("Unexpected box {:?}", other),
+        };
+        assert_eq!(brands.major_brand(), "msdh");
+        assert_eq!(
+            brands.compatible_brands(),
+            [FourCC::new(b"msdh"), FourCC::new(b"msix")]
+        );
+        assert_eq!(boxes[1], Mp4Box::Other(FourCC::new(b"free"), 12));
+        assert_eq!(boxes[2], Mp4Box::Mdat(20));
+    }
+
+    #[test]
+    fn should_reject_truncated_boxes() {
+        let mut data = ftyp(b"ftyp");
+        data.truncate(data.len() - 2);
+
+        assert!(matches!(parse(&data), Err(Mp4Error::Truncated(box_type)) if box_type == "ftyp"));
+        assert!(matches!(
+            parse(&[0, 0, 0, 4, b'f', b'r', b'e', b'e']),
+            Err(Mp4Error::InvalidSize(_, 4))
+        ));
+        assert!(matches!(
+            parse(&full(b"sidx", 2, 0, &[])),
+            Err(Mp4Error::UnsupportedVersion(_, 2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_read_boxes_from_async_readers() {
+        let mut data = ftyp(b"styp");
+        data.extend(boxed(b"mdat", &[7; 100]));
+        data.extend(boxed(b"emsg", &{
+            let mut payload = vec![0; 4];
+            payload.extend(b"urn:test\0\0");
+            payload.extend([0; 16]);
+            payload
+        }));
+
+        let mut reader = Mp4Reader::new(&data[..]);
+        assert!(matches!(
+            reader.next().await.unwrap(),
+            Some(Mp4Box::Styp(_))
+        ));
+        assert_eq!(reader.next().await.unwrap(), Some(Mp4Box::Mdat(108)));
+        let boxes = reader.read_all().await.unwrap();
+        assert!(
+            matches!(&boxes[..], [Mp4Box::Emsg(message)] if message.scheme_id_uri() == "urn:test")
+        );
+        assert_eq!(reader.next().await.unwrap(), None);
+
+        let truncated = &data[..data.len() - 1];
+        let mut reader = Mp4Reader::new(truncated);
+        let error = reader.read_all().await.unwrap_err();
+        assert!(matches!(error, Mp4Error::Truncated(box_type) if box_type == "emsg"));
+    }
+
+    #[test]
+    fn should_write_uuids() {
+        let system_id = Uuid::new([
+            0xed, 0xef, 0x8b, 0xa9, 0x79, 0xd6, 0x4a, 0xce, 0xa3, 0xc8, 0x27, 0xdc, 0xd5, 0x1d,
+            0x21, 0xed,
+        ]);
+
+        assert_eq!(
+            system_id.to_string(),
+            "edef8ba9-79d6-4ace-a3c8-27dcd51d21ed"
+        );
+    }
+}
@@ -0,0 +1,631 @@
+//! MPD patches (ISO/IEC 23009-1 5.15): `add`, `replace` and `remove` operations of
+//! RFC 5261 applied to the XML of a MPD.
+use std::fmt;
+
+use chrono::{DateTime, Local};
+
+use crate::error::Error;
+use crate::models::mpd::MPD;
+use crate::xml::{split_name, Element, Node};
+
+#[derive(Debug)]
+pub enum PatchError {
+    /// The patch document, or the patched MPD, can't be read.
+    Parse(Error),
+    NotAPatch(String),
+    MissingAttribute(&'static str),
+    /// `mpdId` doesn't match the `id` of the MPD.
+    MPDIdMismatch {
+        mpd: String,
+        patch: String,
+    },
+    /// `originalPublishTime` doesn't match the `publishTime` of the MPD.
+    PublishTimeMismatch {
+        mpd: Option<DateTime<Local>>,
+        patch: DateTime<Local>,
+    },
+    /// `publishTime` of the patch isn't after its `originalPublishTime`.
+    OutdatedPatch,
+    InvalidSelector(String),
+    /// The selector of an operation matches no node, or several.
+    Unmatched(String, usize),
+    InvalidOperation(String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Parse(error) => write!(f, "Invalid patch: {}", error),
+            PatchError::NotAPatch(name) => write!(f, "Invalid patch: root element is {}", name),
+            PatchError::MissingAttribute(name) => {
+                write!(f, "Invalid patch: missing attribute {}", name)
+            }
+            PatchError::MPDIdMismatch { mpd, patch } => {
+                write!(f, "Patch of MPD {:?} can't apply to MPD {:?}", patch, mpd)
+            }
+            PatchError::PublishTimeMismatch { mpd, patch } => match mpd {
+                Some(mpd) => write!(
+                    f,
+                    "Patch of the MPD published at {} can't apply to the MPD published at {}",
+                    patch, mpd
+                ),
+                None => write!(
+                    f,
+                    "Patch of the MPD published at {} can't apply to a MPD without publishTime",
+                    patch
+                ),
+            },
+            PatchError::OutdatedPatch => {
+                write!(
+                    f,
+                    "Invalid patch: publishTime isn't after originalPublishTime"
+                )
+            }
+            PatchError::InvalidSelector(selector) => write!(f, "Invalid selector {}", selector),
+            PatchError::Unmatched(selector, count) => {
+                write!(
+                    f,
+                    "Selector {} matches {} nodes instead of one",
+                    selector, count
+                )
+            }
+            PatchError::InvalidOperation(reason) => {
+                write!(f, "Invalid patch operation: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatchError::Parse(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Applies the patch document `patch` to `mpd`, the result takes the `publishTime` of
+/// the patch.
+pub(crate) fn apply(mpd: &MPD, patch: &str) -> Result<MPD, PatchError> {
+    let patch = Element::parse(patch).map_err(PatchError::Parse)?;
+    if split_name(&patch.name).1 != "Patch" {
+        return Err(PatchError::NotAPatch(patch.name));
+    }
+    let required = |name: &'static str| {
+        patch
+            .attribute(name)
+            .ok_or(PatchError::MissingAttribute(name))
+    };
+    let publish_time = |name: &'static str| {
+        required(name)?
+            .trim()
+            .parse::<DateTime<Local>>()
+            .map_err(|error| {
+                PatchError::Parse(
+                    Error::new(error.to_string())
+                        .with_path(vec!["Patch".to_string(), format!("@{}", name)]),
+                )
+            })
+    };
+
+    let mpd_id = required("mpdId")?;
+    if mpd_id != mpd.id() {
+        return Err(PatchError::MPDIdMismatch {
+            mpd: mpd.id().to_string(),
+            patch: mpd_id.to_string(),
+        });
+    }
+    let original = publish_time("originalPublishTime")?;
+    if mpd.publish_time() != Some(original) {
+        return Err(PatchError::PublishTimeMismatch {
+            mpd: mpd.publish_time(),
+            patch: original,
+        });
+    }
+    if publish_time("publishTime")? <= original {
+        return Err(PatchError::OutdatedPatch);
+    }
+
+    let source = mpd.to_xml().map_err(PatchError::Parse)?;
+    let mut root = Element::parse(&source).map_err(PatchError::Parse)?;
+    for operation in patch.elements() {
+        execute(&mut root, operation)?;
+    }
+    root.set_attribute("publishTime", required("publishTime")?);
+
+    let mut patched = String::new();
+    root.write(&mut patched, 0);
+    patched.parse().map_err(PatchError::Parse)
+}
+
+fn execute(root: &mut Element, operation: &Element) -> Result<(), PatchError> {
+    let name = split_name(&operation.name).1;
+    let selector = operation
+        .attribute("sel")
+        .ok_or_else(|| PatchError::InvalidOperation(format!("{} without sel", name)))?;
+    let (path, target) = Selector::parse(selector)?.select(root)?;
+    match (name, target) {
+        ("add", Target::Element) => match operation.attribute("type") {
+            Some(kind) => match kind.strip_prefix('@') {
+                Some(attribute) => {
+                    let element = element_mut(root, &path);
+                    if element.attribute(attribute).is_some() {
+                        return Err(PatchError::InvalidOperation(format!(
+                            "{} already has the attribute {}",
+                            selector, attribute
+                        )));
+                    }
+                    element.set_attribute(attribute, &operation.text());
+                    Ok(())
+                }
+                None => Err(PatchError::InvalidOperation(format!(
+                    "unsupported type {}",
+                    kind
+                ))),
+            },
+            None => {
+                let nodes = content(operation);
+                match operation.attribute("pos") {
+                    None => element_mut(root, &path).children.extend(nodes),
+                    Some("prepend") => {
+                        element_mut(root, &path).children.splice(0..0, nodes);
+                    }
+                    Some(pos @ ("before" | "after")) => {
+                        let (index, parent) = path.split_last().ok_or_else(|| {
+                            PatchError::InvalidOperation("the root element has no sibling".into())
+                        })?;
+                        let index = if pos == "before" { *index } else { index + 1 };
+                        element_mut(root, parent)
+                            .children
+                            .splice(index..index, nodes);
+                    }
+                    Some(pos) => {
+                        return Err(PatchError::InvalidOperation(format!(
+                            "unknown position {}",
+                            pos
+                        )))
+                    }
+                }
+                Ok(())
+            }
+        },
+        ("replace", Target::Element) => {
+            let (index, parent) = path.split_last().ok_or_else(|| {
+                PatchError::InvalidOperation("the root element can't be replaced".into())
+            })?;
+            let mut elements = operation.elements();
+            match (elements.next(), elements.next()) {
+                (Some(element), None) => {
+                    element_mut(root, parent).children[*index] = Node::Element(element.clone());
+                    Ok(())
+                }
+                _ => Err(PatchError::InvalidOperation(format!(
+                    "replacement of {} should be one element",
+                    selector
+                ))),
+            }
+        }
+        ("replace", Target::Attribute(name)) => {
+            element_mut(root, &path).set_attribute(&name, &operation.text());
+            Ok(())
+        }
+        ("replace", Target::Text) => {
+            let element = element_mut(root, &path);
+            element
+                .children
+                .retain(|node| matches!(node, Node::Element(_) | Node::Comment(_)));
+            element.children.insert(0, Node::Text(operation.text()));
+            Ok(())
+        }
+        ("remove", Target::Element) => {
+            let (index, parent) = path.split_last().ok_or_else(|| {
+                PatchError::InvalidOperation("the root element can't be removed".into())
+            })?;
+            element_mut(root, parent).children.remove(*index);
+            Ok(())
+        }
+        ("remove", Target::Attribute(name)) => {
+            element_mut(root, &path)
+                .attributes
+                .retain(|attribute| attribute.name != name);
+            Ok(())
+        }
+        ("remove", Target::Text) => {
+            element_mut(root, &path)
+                .children
+                .retain(|node| matches!(node, Node::Element(_) | Node::Comment(_)));
+            Ok(())
+        }
+        ("add", _) => Err(PatchError::InvalidOperation(format!(
+            "add should select an element, not {}",
+            selector
+        ))),
+        (name, _) => Err(PatchError::InvalidOperation(format!(
+            "unknown operation {}",
+            name
+        ))),
+    }
+}
+
+/// Nodes inserted by an `add`, without the whitespace between elements.
+fn content(operation: &Element) -> Vec<Node> {
+    operation
+        .children
+        .iter()
+        .filter(|node| !matches!(node, Node::Text(text) if text.trim().is_empty()))
+        .cloned()
+        .collect()
+}
+
+/// Element at `path`, the indices of the children to go through from `root`.
+fn element_mut<'a>(root: &'a mut Element, path: &[usize]) -> &'a mut Element {
+    path.iter()
+        .fold(root, |element, index| match &mut element.children[*index] {
+            Node::Element(child) => child,
+            _ => unreachable!("paths only go through elements"),
+        })
+}
+
+/// Node selected at the end of the path.
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Element,
+    Attribute(String),
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    /// 1-based position among the elements matched so far.
+    Position(usize),
+    Attributes(Vec<(String, String)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    name: String,
+    predicates: Vec<Predicate>,
+}
+
+impl Step {
+    fn matches_name(&self, element: &Element) -> bool {
+        self.name == "*" || split_name(&self.name).1 == split_name(&element.name).1
+    }
+}
+
+/// Subset of XPath used by RFC 5261 selectors: an absolute path of element names, with
+/// `[n]` and `[@name='value' and ...]` predicates, ending with an element, an `@attribute`
+/// or `text()`. Prefixes are ignored, names match on their local part.
+#[derive(Debug, Clone, PartialEq)]
+struct Selector {
+    source: String,
+    steps: Vec<Step>,
+    target: Target,
+}
+
+impl Selector {
+    fn parse(source: &str) -> Result<Selector, PatchError> {
+        let invalid = || PatchError::InvalidSelector(source.to_string());
+        let path = source.trim().strip_prefix('/').ok_or_else(invalid)?;
+        let parts = split_outside_quotes(path, '/');
+        let (last, steps) = parts.split_last().ok_or_else(invalid)?;
+        let (steps, target) = if let Some(attribute) = last.strip_prefix('@') {
+            (steps, Target::Attribute(attribute.to_string()))
+        } else if *last == "text()" {
+            (steps, Target::Text)
+        } else {
+            (&parts[..], Target::Element)
+        };
+        let steps = steps
+            .iter()
+            .map(|step| Selector::parse_step(step).ok_or_else(invalid))
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Selector {
+            source: source.to_string(),
+            steps,
+            target,
+        })
+    }
+
+    fn parse_step(step: &str) -> Option<Step> {
+        let (name, mut rest) = match step.find('[') {
+            Some(index) => step.split_at(index),
+            None => (step, ""),
+        };
+        if name.is_empty() || name.contains(['@', '(', ']']) {
+            return None;
+        }
+        let mut predicates = Vec::new();
+        while !rest.is_empty() {
+            let close = closing_bracket(rest)?;
+            let predicate = rest[1..close].trim();
+            rest = &rest[close + 1..];
+            if let Ok(position) = predicate.parse::<usize>() {
+                predicates.push(Predicate::Position(position));
+                continue;
+            }
+            let conditions = split_outside_quotes(predicate, ' ')
+                .into_iter()
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let attributes = conditions
+                .split(" and ")
+                .map(|condition| {
+                    let (name, value) = condition.split_once('=')?;
+                    let name = name.trim().strip_prefix('@')?;
+                    let value = value.trim();
+                    let unquoted = value
+                        .strip_prefix('\'')
+                        .and_then(|value| value.strip_suffix('\''))
+                        .or_else(|| {
+                            value
+                                .strip_prefix('"')
+                                .and_then(|value| value.strip_suffix('"'))
+                        })?;
+                    Some((name.to_string(), unquoted.to_string()))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            predicates.push(Predicate::Attributes(attributes));
+        }
+        Some(Step {
+            name: name.to_string(),
+            predicates,
+        })
+    }
+
+    /// Path to the only node matched in `root`.
+    fn select(&self, root: &Element) -> Result<(Vec<usize>, Target), PatchError> {
+        let mut matched: Vec<(Vec<usize>, &Element)> = vec![(Vec::new(), root)];
+        for (depth, step) in self.steps.iter().enumerate() {
+            let mut next = Vec::new();
+            for (path, element) in matched {
+                let candidates: Vec<(Vec<usize>, &Element)> = if depth == 0 {
+                    vec![(path, element)]
+                } else {
+                    element
+                        .children
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(index, node)| match node {
+                            Node::Element(child) => {
+                                let mut path = path.clone();
+                                path.push(index);
+                                Some((path, child))
+                            }
+                            _ => None,
+                        })
+                        .collect()
+                };
+                let mut candidates: Vec<_> = candidates
+                    .into_iter()
+                    .filter(|(_, element)| step.matches_name(element))
+                    .collect();
+                for predicate in &step.predicates {
+                    candidates = match predicate {
+                        Predicate::Position(position) => candidates
+                            .into_iter()
+                            .nth(position.wrapping_sub(1))
+                            .into_iter()
+                            .collect(),
+                        Predicate::Attributes(attributes) => candidates
+                            .into_iter()
+                            .filter(|(_, element)| {
+                                attributes.iter().all(|(name, value)| {
+                                    element.attribute(name) == Some(value.as_str())
+                                })
+                            })
+                            .collect(),
+                    };
+                }
+                next.extend(candidates);
+            }
+            matched = next;
+        }
+        if let Target::Attribute(name) = &self.target {
+            matched.retain(|(_, element)| element.attribute(name).is_some());
+        }
+        match matched.len() {
+            1 => Ok((matched.remove(0).0, self.target.clone())),
+            count => Err(PatchError::Unmatched(self.source.clone(), count)),
+        }
+    }
+}
+
+/// Splits `text` on `separator`, except within quotes and brackets.
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, char) in text.char_indices() {
+        match (char, quote) {
+            ('\'' | '"', None) => quote = Some(char),
+            (char, Some(open)) if char == open => quote = None,
+            (_, Some(_)) => {}
+            ('[', None) => depth += 1,
+            (']', None) => depth -= 1,
+            (char, None) if char == separator && depth == 0 => {
+                parts.push(&text[start..index]);
+                start = index + char.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Index of the `]` closing the predicate `text` starts with.
+fn closing_bracket(text: &str) -> Option<usize> {
+    if !text.starts_with('[') {
+        return None;
+    }
+    let mut quote = None;
+    for (index, char) in text.char_indices().skip(1) {
+        match (char, quote) {
+            ('\'' | '"', None) => quote = Some(char),
+            (char, Some(open)) if char == open => quote = None,
+            (']', None) => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MPD_SOURCE: &str = r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" id="live" type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z" publishTime="2024-01-01T00:01:00Z" minimumUpdatePeriod="PT2S">
+    <PatchLocation ttl="60">patch.mpp?publishTime=2024-01-01T00:01:00Z</PatchLocation>
+    <Period id="1" start="PT0S">
+        <AdaptationSet id="1" mimeType="video/mp4">
+            <SegmentTemplate timescale="10" media="$Time$.m4s">
+                <SegmentTimeline>
+                    <S t="0" d="20" r="2"/>
+                </SegmentTimeline>
+            </SegmentTemplate>
+            <Representation id="video" bandwidth="1000"/>
+        </AdaptationSet>
+    </Period>
+</MPD>"#;
+
+    fn patch(operations: &str) -> String {
+        format!(
+            r#"<Patch xmlns="urn:mpeg:dash:schema:mpd-patch:2020" mpdId="live" originalPublishTime="2024-01-01T00:01:00Z" publishTime="2024-01-01T00:01:02Z">{}</Patch>"#,
+            operations
+        )
+    }
+
+    fn mpd() -> MPD {
+        MPD_SOURCE.parse().unwrap()
+    }
+
+    #[test]
+    fn should_parse_patch_location() {
+        let mpd = mpd();
+        assert_eq!(mpd.id(), "live");
+        let location = &mpd.patch_locations()[0];
+        assert_eq!(location.ttl(), Some(60.0));
+        assert_eq!(location.url(), "patch.mpp?publishTime=2024-01-01T00:01:00Z");
+        assert_eq!(mpd.to_xml().unwrap().parse::<MPD>().unwrap(), mpd);
+    }
+
+    #[test]
+    fn should_add_replace_and_remove_nodes() {
+        let patched = mpd()
+            .apply_patch(&patch(
+                r#"
+                <add sel="/MPD/Period[@id='1']/AdaptationSet[@id='1']/SegmentTemplate/SegmentTimeline"><S d="10"/></add>
+                <replace sel="/MPD/Period[@id='1']/AdaptationSet[1]/SegmentTemplate/SegmentTimeline/S[1]/@r">3</replace>
+                <add sel="/MPD/Period[1]/AdaptationSet/Representation[@id='video']" type="@width">1920</add>
+                <add sel="/MPD/Period" pos="after"><Period id="2" start="PT100S"/></add>
+                <remove sel="/MPD/@minimumUpdatePeriod"/>
+                <replace sel="/MPD/PatchLocation/text()">patch.mpp?publishTime=2024-01-01T00:01:02Z</replace>
+                "#,
+            ))
+            .unwrap();
+
+        assert_eq!(patched.publish_time(), "2024-01-01T00:01:02Z".parse().ok());
+        assert_eq!(patched.minimum_update_period(), None);
+        assert_eq!(
+            patched.patch_locations()[0].url(),
+            "patch.mpp?publishTime=2024-01-01T00:01:02Z"
+        );
+        let periods = patched.periods();
+        assert_eq!(periods.len(), 2);
+        assert_eq!(periods[1].id(), "2");
+        let adaptation = &periods[0].adaptations()[0];
+        assert_eq!(adaptation.representations()[0].width(), 1920);
+        let timeline = adaptation
+            .segment_template()
+            .unwrap()
+            .segment_timeline()
+            .unwrap();
+        let times: Vec<_> = timeline.iter(10, 1, 0, None).map(|s| s.time()).collect();
+        assert_eq!(times, vec![0, 20, 40, 60, 80]);
+    }
+
+    #[test]
+    fn should_replace_and_remove_elements() {
+        let patched = mpd()
+            .apply_patch(&patch(
+                r#"<replace sel="/MPD/Period/AdaptationSet/SegmentTemplate/SegmentTimeline/S"><S t="100" d="5"/></replace>
+                <remove sel="/MPD/Period/AdaptationSet/Representation"/>
+                <add sel="/MPD/Period/AdaptationSet" pos="prepend"><Representation id="audio" bandwidth="64"/></add>"#,
+            ))
+            .unwrap();
+        let adaptation = &patched.periods()[0].adaptations()[0];
+        assert_eq!(adaptation.representations().len(), 1);
+        assert_eq!(adaptation.representations()[0].id(), "audio");
+        let entries = adaptation
+            .segment_template()
+            .unwrap()
+            .segment_timeline()
+            .unwrap()
+            .segments();
+        assert_eq!((entries[0].t(), entries[0].d()), (Some(100), 5));
+    }
+
+    #[test]
+    fn should_check_mpd_id_and_publish_times() {
+        let mpd = mpd();
+        let other = patch("").replace(r#"mpdId="live""#, r#"mpdId="other""#);
+        assert!(matches!(
+            mpd.apply_patch(&other),
+            Err(PatchError::MPDIdMismatch { .. })
+        ));
+        let stale = patch("").replace(
+            "originalPublishTime=\"2024-01-01T00:01:00Z",
+            "originalPublishTime=\"2024-01-01T00:00:58Z",
+        );
+        assert!(matches!(
+            mpd.apply_patch(&stale),
+            Err(PatchError::PublishTimeMismatch { .. })
+        ));
+        let outdated = patch("").replace(
+            "publishTime=\"2024-01-01T00:01:02Z",
+            "publishTime=\"2024-01-01T00:01:00Z",
+        );
+        assert!(matches!(
+            mpd.apply_patch(&outdated),
+            Err(PatchError::OutdatedPatch)
+        ));
+        assert!(matches!(
+            mpd.apply_patch(&patch(r#"<remove sel="/MPD/Period[@id='3']"/>"#)),
+            Err(PatchError::Unmatched(_, 0))
+        ));
+        assert!(matches!(
+            mpd.apply_patch(&patch(r#"<remove sel="MPD/Period"/>"#)),
+            Err(PatchError::InvalidSelector(_))
+        ));
+        assert!(matches!(
+            mpd.apply_patch(MPD_SOURCE),
+            Err(PatchError::NotAPatch(_))
+        ));
+    }
+
+    #[test]
+    fn should_parse_selectors() {
+        let selector =
+            Selector::parse("/MPD/Period[@id='a/b' and @start=\"PT0S\"][2]/@id").unwrap();
+        assert_eq!(selector.target, Target::Attribute("id".to_string()));
+        assert_eq!(
+            selector.steps[1].predicates,
+            vec![
+                Predicate::Attributes(vec![
+                    ("id".to_string(), "a/b".to_string()),
+                    ("start".to_string(), "PT0S".to_string())
+                ]),
+                Predicate::Position(2)
+            ]
+        );
+        assert!(Selector::parse("/MPD/Period[@id='1'").is_err());
+        assert!(Selector::parse("/@id").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Start and end of the periods of a MPD (ISO/IEC 23009-1 5.3.2.1).
+use chrono::Duration;
+
+use crate::models::mpd::MPD;
+
+/// Span of a period in the presentation, relative to its start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeriodTiming {
+    index: usize,
+    id: String,
+    start: Option<Duration>,
+    end: Option<Duration>,
+}
+
+impl PeriodTiming {
+    /// Position of the period in the MPD.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// `None` for an early available period of a dynamic MPD, not yet scheduled.
+    pub fn start(&self) -> Option<Duration> {
+        self.start
+    }
+
+    /// `None` while the period goes on, e.g. the last period of a live stream.
+    pub fn end(&self) -> Option<Duration> {
+        self.end
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        Some(self.end? - self.start?)
+    }
+
+    pub fn contains(&self, time: Duration) -> bool {
+        self.start.is_some_and(|start| start <= time) && self.end.is_none_or(|end| time < end)
+    }
+}
+
+/// Inconsistency between a period and the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodDiscontinuity {
+    /// Nothing is presented between the end of the period at `after` and the start of
+    /// the next one.
+    Gap { after: usize, duration: Duration },
+    /// The period at `after` lasts past the start of the next one.
+    Overlap { after: usize, duration: Duration },
+}
+
+/// Timing of every period of a MPD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeriodTimeline {
+    periods: Vec<PeriodTiming>,
+    discontinuities: Vec<PeriodDiscontinuity>,
+}
+
+impl PeriodTimeline {
+    /// Resolves the start of each period from its `start`, or from the end of the
+    /// previous period, and then its end from its `duration`, or from the start of the
+    /// next period, or from `mediaPresentationDuration` for the last one. A period whose
+    /// start can't be resolved has no end either.
+    pub fn new(mpd: &MPD) -> Self {
+        let periods = mpd.periods();
+        let mut starts: Vec<Option<Duration>> = Vec::with_capacity(periods.len());
+        for (index, period) in periods.iter().enumerate() {
+            let start = match (period.start(), index) {
+                (Some(start), _) => Some(start),
+                (None, 0) if mpd.is_static() => Some(Duration::zero()),
+                (None, 0) => None,
+                (None, _) => starts[index - 1]
+                    .zip(periods[index - 1].duration())
+                    .map(|(start, duration)| start + duration),
+            };
+            starts.push(start);
+        }
+
+        let mut timings = Vec::with_capacity(periods.len());
+        let mut discontinuities = Vec::new();
+        for (index, period) in periods.iter().enumerate() {
+            let start = starts[index];
+            let next_start = starts.get(index + 1).copied().flatten();
+            let own_end = start
+                .zip(period.duration())
+                .map(|(start, duration)| start + duration);
+            let end = match (own_end, next_start) {
+                _ if start.is_none() => None,
+                (Some(end), Some(next)) => {
+                    if end < next {
+                        discontinuities.push(PeriodDiscontinuity::Gap {
+                            after: index,
+                            duration: next - end,
+                        });
+                    } else if end > next {
+                        discontinuities.push(PeriodDiscontinuity::Overlap {
+                            after: index,
+                            duration: end - next,
+                        });
+                    }
+                    Some(end)
+                }
+                (Some(end), None) => Some(end),
+                (None, Some(next)) => Some(next),
+                (None, None) if index + 1 == periods.len() => mpd.media_presentation_duration(),
+                (None, None) => None,
+            };
+            timings.push(PeriodTiming {
+                index,
+                id: period.id().to_string(),
+                start,
+                end,
+            });
+        }
+
+        PeriodTimeline {
+            periods: timings,
+            discontinuities,
+        }
+    }
+
+    pub fn periods(&self) -> &[PeriodTiming] {
+        &self.periods
+    }
+
+    /// Gaps and overlaps, in the order of the periods.
+    pub fn discontinuities(&self) -> &[PeriodDiscontinuity] {
+        &self.discontinuities
+    }
+
+    /// Period presented at `time`, relative to the start of the presentation. When
+    /// periods overlap, the later one wins as it starts after the earlier.
+    pub fn period_at(&self, time: Duration) -> Option<&PeriodTiming> {
+        self.periods
+            .iter()
+            .rev()
+            .find(|period| period.contains(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline(periods: &str, attributes: &str) -> PeriodTimeline {
+        let mpd: MPD = format!(
+            r#"<MPD profiles="urn:mpeg:dash:profile:isoff-live:2011" {}>{}</MPD>"#,
+            attributes, periods
+        )
+        .parse()
+        .unwrap();
+        mpd.period_timeline()
+    }
+
+    fn spans(timeline: &PeriodTimeline) -> Vec<(Option<i64>, Option<i64>)> {
+        timeline
+            .periods()
+            .iter()
+            .map(|period| {
+                (
+                    period.start().map(|start| start.num_seconds()),
+                    period.end().map(|end| end.num_seconds()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_derive_missing_starts_and_ends() {
+        let timeline = timeline(
+            r#"<Period id="main" duration="PT30S"/><Period id="ad" duration="PT15S"/><Period id="back" start="PT45S"/><Period id="end"/>"#,
+            r#"mediaPresentationDuration="PT2M""#,
+        );
+        assert_eq!(
+            spans(&timeline),
+            vec![
+                (Some(0), Some(30)),
+                (Some(30), Some(45)),
+                (Some(45), None),
+                (None, None)
+            ]
+        );
+        assert!(timeline.discontinuities().is_empty());
+        assert_eq!(timeline.periods()[1].id(), "ad");
+        assert_eq!(
+            timeline.periods()[1].duration(),
+            Some(Duration::seconds(15))
+        );
+    }
+
+    #[test]
+    fn should_end_the_last_period_with_the_presentation() {
+        let timeline = timeline(
+            r#"<Period id="main" duration="PT30S"/><Period id="end"/>"#,
+            r#"type="static" mediaPresentationDuration="PT2M""#,
+        );
+        assert_eq!(
+            spans(&timeline),
+            vec![(Some(0), Some(30)), (Some(30), Some(120))]
+        );
+    }
+
+    #[test]
+    fn should_flag_gaps_and_overlaps() {
+        let timeline = timeline(
+            r#"<Period id="1" start="PT0S" duration="PT10S"/><Period id="2" start="PT12S" duration="PT10S"/><Period id="3" start="PT20S"/>"#,
+            r#"mediaPresentationDuration="PT30S""#,
+        );
+        assert_eq!(
+            timeline.discontinuities(),
+            &[
+                PeriodDiscontinuity::Gap {
+                    after: 0,
+                    duration: Duration::seconds(2)
+                },
+                PeriodDiscontinuity::Overlap {
+                    after: 1,
+                    duration: Duration::seconds(2)
+                },
+            ]
+        );
+        assert_eq!(spans(&timeline)[2], (Some(20), Some(30)));
+    }
+
+    #[test]
+    fn should_find_period_containing_time() {
+        let timeline = timeline(
+            r#"<Period id="1" start="PT0S" duration="PT10S"/><Period id="2" start="PT12S" duration="PT10S"/><Period id="3" start="PT20S"/>"#,
+            r#"mediaPresentationDuration="PT30S""#,
+        );
+        let id = |seconds: i64| {
+            timeline
+                .period_at(Duration::seconds(seconds))
+                .map(PeriodTiming::id)
+        };
+        assert_eq!(id(0), Some("1"));
+        assert_eq!(id(10), None);
+        assert_eq!(id(12), Some("2"));
+        assert_eq!(id(21), Some("3"));
+        assert_eq!(id(30), None);
+    }
+
+    #[test]
+    fn should_leave_early_available_period_of_dynamic_mpd_unscheduled() {
+        let timeline = timeline(
+            r#"<Period id="next"/>"#,
+            r#"type="dynamic" availabilityStartTime="2024-01-01T00:00:00Z""#,
+        );
+        assert_eq!(spans(&timeline), vec![(None, None)]);
+        assert_eq!(timeline.period_at(Duration::zero()), None);
+    }
+}
@@ -0,0 +1,964 @@
+//! Recording of a dynamic MPD into a static one covering the recorded segments.
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Duration;
+
+use crate::archive::{
+    elements_mut, extension, file_name, range, remove_attributes, remove_children, write,
+    ARCHIVED_MANIFEST, SEGMENT_ELEMENTS,
+};
+use crate::base_url::{BaseURLError, ResolvedBaseURL, SegmentLocation};
+use crate::download::SegmentDownloader;
+use crate::error::Error;
+use crate::fetch::{FetchError, FetchedMPD, MPDFetcher};
+use crate::models::mpd::MPD;
+use crate::models::segments::{MediaTime, SegmentInformation, TimelineSegment};
+use crate::serde_custom::duration_iso_8601;
+use crate::template::{TemplateError, TemplateValues};
+use crate::watcher::{MPDChange, MPDWatcher};
+use crate::xml::{Element, Node};
+
+/// Attributes of a dynamic MPD which a static one doesn't have.
+const DYNAMIC_ATTRIBUTES: [&str; 4] = [
+    "minimumUpdatePeriod",
+    "timeShiftBufferDepth",
+    "suggestedPresentationDelay",
+    "availabilityEndTime",
+];
+
+#[derive(Debug)]
+pub enum RecordError {
+    // Boxed, it is much larger than the other variants.
+    Fetch(Box<FetchError>),
+    /// The MPD is static, there is nothing to follow: it can be archived instead.
+    Static(String),
+    BaseURL(BaseURLError),
+    Template(TemplateError),
+    /// The `Representation` has neither `SegmentTemplate` nor `SegmentList` to locate
+    /// its segments.
+    UnlocatableSegments(String),
+    /// The recording stopped before any segment was recorded.
+    NoSegments(String),
+    Io(PathBuf, io::Error),
+    /// The recorded MPD can't be written.
+    InvalidMPD(Error),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Fetch(error) => error.fmt(f),
+            RecordError::Static(url) => write!(f, "Can't record a static MPD: {}", url),
+            RecordError::BaseURL(error) => error.fmt(f),
+            RecordError::Template(error) => error.fmt(f),
+            RecordError::UnlocatableSegments(id) => {
+                write!(f, "Can't locate the segments of Representation {}", id)
+            }
+            RecordError::NoSegments(url) => write!(f, "No segment recorded from {}", url),
+            RecordError::Io(path, error) => write!(f, "Can't write {}: {}", path.display(), error),
+            RecordError::InvalidMPD(error) => write!(f, "Invalid recorded MPD: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RecordError::Fetch(error) => Some(error.as_ref()),
+            RecordError::BaseURL(error) => Some(error),
+            RecordError::Template(error) => Some(error),
+            RecordError::Io(_, error) => Some(error),
+            RecordError::InvalidMPD(error) => Some(error),
+            RecordError::Static(_)
+            | RecordError::UnlocatableSegments(_)
+            | RecordError::NoSegments(_) => None,
+        }
+    }
+}
+
+impl From<BaseURLError> for RecordError {
+    fn from(error: BaseURLError) -> Self {
+        RecordError::BaseURL(error)
+    }
+}
+
+impl From<TemplateError> for RecordError {
+    fn from(error: TemplateError) -> Self {
+        RecordError::Template(error)
+    }
+}
+
+/// What [`Recorder::record`] wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingReport {
+    manifest: PathBuf,
+    segments: usize,
+    failed: usize,
+    bytes: u64,
+    duration: Duration,
+}
+
+impl RecordingReport {
+    /// Path of the static MPD.
+    pub fn manifest(&self) -> &Path {
+        &self.manifest
+    }
+
+    /// Media segments recorded, across all the `Representation`s.
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+
+    /// Segments which couldn't be downloaded, left as gaps in the timelines.
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Bytes written, initialization segments included.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Presentation time covered by the recording.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Records a live presentation: follows a dynamic MPD, downloads the segments listed
+/// by the `SegmentTimeline`s or `SegmentList`s of the chosen `Representation`s, those
+/// of the time shift buffer first, then each new one as it appears.
+///
+/// After each refresh, a static MPD covering the recorded range is written as
+/// [`ARCHIVED_MANIFEST`], its periods starting with the first recorded segment. The
+/// segments are stored as `<period id>/<representation id>/<$Time$>`, so the numbering
+/// of the live stream, and its discontinuities, don't matter.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    fetcher: MPDFetcher,
+    downloader: SegmentDownloader,
+    representations: Vec<String>,
+    max_duration: Option<std::time::Duration>,
+    retry_delay: Option<std::time::Duration>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder::default()
+    }
+
+    pub fn with_fetcher(mut self, fetcher: MPDFetcher) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    pub fn with_downloader(mut self, downloader: SegmentDownloader) -> Self {
+        self.downloader = downloader;
+        self
+    }
+
+    /// Records only the `Representation`s with these ids, every one by default.
+    pub fn with_representations<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.representations = ids.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Stops once the recording covers `duration`, instead of when the presentation
+    /// ends.
+    pub fn with_max_duration(mut self, duration: std::time::Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// See [`MPDWatcher::with_retry_delay`].
+    pub fn with_retry_delay(mut self, delay: std::time::Duration) -> Self {
+        self.retry_delay = Some(delay);
+        self
+    }
+
+    /// Records until the presentation ends or the maximum duration is reached.
+    pub async fn record(
+        &self,
+        url: &str,
+        directory: &Path,
+    ) -> Result<RecordingReport, RecordError> {
+        self.record_until(url, directory, std::future::pending())
+            .await
+    }
+
+    /// Same as [`record`](Self::record), stopping as well when `stop` completes, e.g.
+    /// on Ctrl-C. The segments being downloaded are waited for.
+    pub async fn record_until<F>(
+        &self,
+        url: &str,
+        directory: &Path,
+        stop: F,
+    ) -> Result<RecordingReport, RecordError>
+    where
+        F: Future<Output = ()>,
+    {
+        let mut watcher = MPDWatcher::new(url).with_fetcher(self.fetcher.clone());
+        if let Some(delay) = self.retry_delay {
+            watcher = watcher.with_retry_delay(delay);
+        }
+        let max_duration = self
+            .max_duration
+            .and_then(|max| Duration::from_std(max).ok());
+        let manifest = directory.join(ARCHIVED_MANIFEST);
+        let mut recording = Recording::default();
+        tokio::pin!(stop);
+        loop {
+            let next = tokio::select! {
+                next = watcher.next() => next,
+                _ = &mut stop => None,
+            };
+            let changes = match next {
+                Some(Ok(changes)) => changes,
+                Some(Err(error)) if watcher.mpd().is_none() => {
+                    return Err(RecordError::Fetch(Box::new(error)))
+                }
+                // The watcher fetches again after a while.
+                Some(Err(_)) => continue,
+                None => break,
+            };
+            let fetched = watcher.mpd().expect("the changes come from a fetched MPD");
+            if fetched.mpd().is_static() && recording.periods.is_empty() {
+                return Err(RecordError::Static(fetched.url().to_string()));
+            }
+            self.record_changes(&mut recording, fetched, changes, directory)
+                .await?;
+
+            if let Some(mpd) = recording.manifest(fetched.mpd()) {
+                let xml = mpd
+                    .and_then(|mpd| mpd.to_xml())
+                    .map_err(RecordError::InvalidMPD)?;
+                write(&manifest, xml.as_bytes())
+                    .await
+                    .map_err(|error| RecordError::Io(manifest.clone(), error))?;
+            }
+            if max_duration.is_some_and(|max| recording.duration() >= max) {
+                break;
+            }
+        }
+
+        if recording.segments == 0 {
+            return Err(RecordError::NoSegments(url.to_string()));
+        }
+        Ok(RecordingReport {
+            manifest,
+            segments: recording.segments,
+            failed: recording.failed,
+            bytes: recording.bytes,
+            duration: recording.duration(),
+        })
+    }
+
+    /// Downloads the new segments of the chosen `Representation`s, and the
+    /// initialization segments of those seen for the first time.
+    async fn record_changes(
+        &self,
+        recording: &mut Recording,
+        fetched: &FetchedMPD,
+        changes: Vec<MPDChange>,
+        directory: &Path,
+    ) -> Result<(), RecordError> {
+        let mpd = fetched.mpd();
+        recording.snapshot(mpd).map_err(RecordError::InvalidMPD)?;
+        let timeline = mpd.period_timeline();
+
+        // Location, path in the directory, and track and segment for media segments.
+        let mut downloads = Vec::new();
+        for change in changes {
+            let (period_id, representation_id, segments) = match change {
+                MPDChange::NewSegments {
+                    period_id,
+                    representation_id,
+                    segments,
+                } => (period_id, representation_id, segments),
+                _ => continue,
+            };
+            if !self.representations.is_empty()
+                && !self.representations.contains(&representation_id)
+            {
+                continue;
+            }
+            let (period_index, adaptation_index, index) =
+                match position(mpd, &period_id, &representation_id) {
+                    Some(position) => position,
+                    None => continue,
+                };
+            let period = &mpd.periods()[period_index];
+            let adaptation = &period.adaptations()[adaptation_index];
+            let representation = &adaptation.representations()[index];
+            let information = period
+                .segment_information(adaptation, representation)
+                .filter(|information| information.base().is_none())
+                .ok_or_else(|| RecordError::UnlocatableSegments(representation_id.clone()))?;
+            let base = fetched
+                .resolve_base_urls(period_index, adaptation_index, index)?
+                .swap_remove(0);
+            let values = TemplateValues::new(&representation_id, representation.bandwidth());
+
+            let track = match recording.track(&period_id, &representation_id) {
+                Some(track) => track,
+                None => {
+                    let directory = format!(
+                        "{}/{}",
+                        file_name(&period_id, "period"),
+                        file_name(&representation_id, "representation")
+                    );
+                    recording.tracks.push(Track {
+                        period_id: period_id.clone(),
+                        representation_id: representation_id.clone(),
+                        directory,
+                        period_start: timeline.periods()[period_index]
+                            .start()
+                            .unwrap_or_else(Duration::zero),
+                        timescale: information.timescale(),
+                        presentation_time_offset: information.presentation_time_offset(),
+                        extension: None,
+                        initialization: None,
+                        segments: Vec::new(),
+                    });
+                    recording.tracks.len() - 1
+                }
+            };
+            // Until it is written, the initialization segment is downloaded again with
+            // each version of the MPD.
+            if recording.tracks[track].initialization.is_none() {
+                if let Some(location) = initialization(&information, &base, &values)? {
+                    let path = format!(
+                        "{}/init{}",
+                        recording.tracks[track].directory,
+                        extension(location.url().path())
+                    );
+                    downloads.push((location, path, Download::Initialization(track)));
+                }
+            }
+
+            for segment in segments {
+                let recorded = &recording.tracks[track];
+                if recorded
+                    .segments
+                    .iter()
+                    .any(|recorded| recorded.time() == segment.time())
+                {
+                    continue;
+                }
+                let location = locate(&information, &base, &values, &representation_id, &segment)?;
+                let recorded = &mut recording.tracks[track];
+                let extension = recorded
+                    .extension
+                    .get_or_insert_with(|| extension(location.url().path()).to_string());
+                let path = format!("{}/{}{}", recorded.directory, segment.time(), extension);
+                downloads.push((location, path, Download::Segment(track, segment)));
+            }
+        }
+
+        let locations: Vec<_> = downloads
+            .iter()
+            .map(|(location, _, _)| location.clone())
+            .collect();
+        let results = self.downloader.download_all(&locations).await;
+        for ((_, path, download), result) in downloads.into_iter().zip(results) {
+            let content = match result {
+                Ok(content) => content,
+                Err(_) => {
+                    recording.failed += 1;
+                    continue;
+                }
+            };
+            let written = directory.join(&path);
+            write(&written, &content)
+                .await
+                .map_err(|error| RecordError::Io(written, error))?;
+            recording.bytes += content.len() as u64;
+            match download {
+                Download::Initialization(track) => {
+                    recording.tracks[track].initialization = Some(path);
+                }
+                Download::Segment(track, segment) => {
+                    let segments = &mut recording.tracks[track].segments;
+                    let index =
+                        segments.partition_point(|recorded| recorded.time() < segment.time());
+                    segments.insert(index, segment);
+                    recording.segments += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What was recorded so far.
+#[derive(Default)]
+struct Recording {
+    /// Last version of each period seen, with its start, including those which left
+    /// the time shift buffer since.
+    periods: Vec<(String, Duration, Element)>,
+    tracks: Vec<Track>,
+    segments: usize,
+    failed: usize,
+    bytes: u64,
+}
+
+/// What a downloaded file is, for the track at an index of [`Recording::tracks`].
+enum Download {
+    Initialization(usize),
+    Segment(usize, TimelineSegment),
+}
+
+/// Segments recorded for a `Representation` of a period.
+struct Track {
+    period_id: String,
+    representation_id: String,
+    /// Directory of its files, relative to the recording's.
+    directory: String,
+    period_start: Duration,
+    timescale: u64,
+    presentation_time_offset: u64,
+    /// Extension of the media segments, known with the first one.
+    extension: Option<String>,
+    /// Path of the initialization segment, once written.
+    initialization: Option<String>,
+    /// Sorted by time.
+    segments: Vec<TimelineSegment>,
+}
+
+impl Recording {
+    fn track(&self, period_id: &str, representation_id: &str) -> Option<usize> {
+        self.tracks.iter().position(|track| {
+            track.period_id == period_id && track.representation_id == representation_id
+        })
+    }
+
+    fn snapshot(&mut self, mpd: &MPD) -> Result<(), Error> {
+        let root = Element::parse(&mpd.to_xml()?)?;
+        let timeline = mpd.period_timeline();
+        for (index, element) in root
+            .elements()
+            .filter(|element| element.name == "Period")
+            .enumerate()
+        {
+            let id = mpd.periods()[index].id().to_string();
+            let start = timeline.periods()[index]
+                .start()
+                .unwrap_or_else(Duration::zero);
+            let snapshot = (id, start, element.clone());
+            match self
+                .periods
+                .iter_mut()
+                .find(|(known, _, _)| *known == snapshot.0)
+            {
+                Some(period) => *period = snapshot,
+                None => self.periods.push(snapshot),
+            }
+        }
+        Ok(())
+    }
+
+    /// Start of the first segment recorded and end of the last one, in presentation
+    /// time.
+    fn range(&self) -> Option<(Duration, Duration)> {
+        let start = self
+            .tracks
+            .iter()
+            .filter_map(|track| {
+                let first = track.segments.first()?;
+                Some(track.period_start + first.start_time().to_duration())
+            })
+            .min()?;
+        let end = self
+            .tracks
+            .iter()
+            .filter_map(|track| {
+                let last = track.segments.last()?;
+                Some(track.period_start + last.end_time().to_duration())
+            })
+            .max()?;
+        Some((start, end))
+    }
+
+    fn duration(&self) -> Duration {
+        self.range()
+            .map_or_else(Duration::zero, |(start, end)| end - start)
+    }
+
+    /// Static MPD of the recording, from `mpd` the last version of the dynamic one.
+    /// `None` until a segment is recorded.
+    fn manifest(&self, mpd: &MPD) -> Option<Result<MPD, Error>> {
+        let (start, end) = self.range()?;
+        // To the millisecond, as the durations written.
+        let start = Duration::milliseconds(start.num_milliseconds());
+        Some(self.rewrite(mpd, start, end))
+    }
+
+    fn rewrite(&self, mpd: &MPD, start: Duration, end: Duration) -> Result<MPD, Error> {
+        let mut root = Element::parse(&mpd.to_xml()?)?;
+        root.set_attribute("type", "static");
+        remove_attributes(&mut root, &DYNAMIC_ATTRIBUTES);
+        root.set_attribute(
+            "mediaPresentationDuration",
+            &duration_iso_8601::format(end - start),
+        );
+        remove_children(
+            &mut root,
+            &["BaseURL", "Location", "PatchLocation", "Period"],
+        );
+
+        let mut periods: Vec<_> = self.periods.iter().collect();
+        periods.sort_by_key(|(_, period_start, _)| *period_start);
+        for (id, period_start, element) in periods {
+            let tracks: Vec<&Track> = self
+                .tracks
+                .iter()
+                .filter(|track| track.period_id == *id && !track.segments.is_empty())
+                .collect();
+            if tracks.is_empty() {
+                continue;
+            }
+            let recorded = |representation: &Element| {
+                tracks.iter().copied().find(|track| {
+                    representation.attribute("id") == Some(track.representation_id.as_str())
+                })
+            };
+
+            let mut period = element.clone();
+            remove_children(&mut period, &SEGMENT_ELEMENTS);
+            remove_attributes(&mut period, &["duration", "xlink:href", "xlink:actuate"]);
+            period.set_attribute(
+                "start",
+                &duration_iso_8601::format((*period_start - start).max(Duration::zero())),
+            );
+            // A period starting before the recording is cut by the offset of its segments.
+            let cut = (start - *period_start).max(Duration::zero());
+            for adaptation in elements_mut(&mut period, "AdaptationSet") {
+                remove_children(adaptation, &SEGMENT_ELEMENTS);
+                adaptation.children.retain(|node| match node {
+                    Node::Element(child) if child.name == "Representation" => {
+                        recorded(child).is_some()
+                    }
+                    _ => true,
+                });
+                for representation in elements_mut(adaptation, "Representation") {
+                    if let Some(track) = recorded(representation) {
+                        remove_children(representation, &SEGMENT_ELEMENTS);
+                        representation
+                            .children
+                            .push(Node::Element(track.template(cut)?));
+                    }
+                }
+            }
+            period.children.retain(|node| {
+                !matches!(node, Node::Element(child)
+                    if child.name == "AdaptationSet" && child.elements().all(|c| c.name != "Representation"))
+            });
+            root.children.push(Node::Element(period));
+        }
+        let mut source = String::new();
+        root.write(&mut source, 0);
+        source.parse()
+    }
+}
+
+impl Track {
+    /// `SegmentTemplate` of the recorded files, its period starting `cut` after the
+    /// original one.
+    fn template(&self, cut: Duration) -> Result<Element, Error> {
+        let presentation_time_offset = self.presentation_time_offset
+            + MediaTime::from_duration(cut, self.timescale).ticks().max(0) as u64;
+        let initialization = self
+            .initialization
+            .as_ref()
+            .map(|path| format!(r#" initialization="{}""#, path))
+            .unwrap_or_default();
+        Element::parse(&format!(
+            r#"<SegmentTemplate timescale="{}" presentationTimeOffset="{}" media="{}/$Time${}"{}><SegmentTimeline>{}</SegmentTimeline></SegmentTemplate>"#,
+            self.timescale,
+            presentation_time_offset,
+            self.directory,
+            self.extension.as_deref().unwrap_or_default(),
+            initialization,
+            timeline(&self.segments),
+        ))
+    }
+}
+
+/// `S` elements of sorted segments: runs of segments of the same duration are
+/// repeated, and `t` is only given after a gap.
+fn timeline(segments: &[TimelineSegment]) -> String {
+    let mut entries: Vec<(Option<u64>, u64, u64)> = Vec::new();
+    let mut end = None;
+    for segment in segments {
+        match entries.last_mut() {
+            Some((_, duration, repeat))
+                if end == Some(segment.time()) && *duration == segment.duration() =>
+            {
+                *repeat += 1
+            }
+            _ => {
+                let time = Some(segment.time()).filter(|time| end != Some(*time));
+                entries.push((time, segment.duration(), 0));
+            }
+        }
+        end = Some(segment.time() + segment.duration());
+    }
+    entries
+        .into_iter()
+        .map(|(time, duration, repeat)| {
+            let time = time.map(|time| format!(r#" t="{}""#, time));
+            let repeat = Some(repeat)
+                .filter(|repeat| *repeat > 0)
+                .map(|repeat| format!(r#" r="{}""#, repeat));
+            format!(
+                r#"<S{} d="{}"{}/>"#,
+                time.unwrap_or_default(),
+                duration,
+                repeat.unwrap_or_default()
+            )
+        })
+        .collect()
+}
+
+/// Indices of the period, adaptation set and representation with these ids.
+fn position(mpd: &MPD, period_id: &str, representation_id: &str) -> Option<(usize, usize, usize)> {
+    let period_index = mpd
+        .periods()
+        .iter()
+        .position(|period| period.id() == period_id)?;
+    mpd.periods()[period_index]
+        .adaptations()
+        .iter()
+        .enumerate()
+        .find_map(|(adaptation_index, adaptation)| {
+            adaptation
+                .representations()
+                .iter()
+                .position(|representation| representation.id() == representation_id)
+                .map(|index| (period_index, adaptation_index, index))
+        })
+}
+
+fn initialization(
+    information: &SegmentInformation,
+    base: &ResolvedBaseURL,
+    values: &TemplateValues,
+) -> Result<Option<SegmentLocation>, RecordError> {
+    if let Some(template) = information.template() {
+        if !template.initialization_url().is_empty() {
+            let reference = template.expand_initialization(values)?;
+            return Ok(Some(base.locate(&reference, None)?));
+        }
+    }
+    match information.initialization() {
+        Some(initialization) => Ok(Some(
+            base.locate(initialization.source_url(), range(initialization.range())?)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn locate(
+    information: &SegmentInformation,
+    base: &ResolvedBaseURL,
+    values: &TemplateValues,
+    representation_id: &str,
+    segment: &TimelineSegment,
+) -> Result<SegmentLocation, RecordError> {
+    match information {
+        SegmentInformation::Template(template) => {
+            let values = values
+                .clone()
+                .with_number(segment.number())
+                .with_time(segment.time());
+            Ok(base.locate(&template.expand_media(&values)?, None)?)
+        }
+        SegmentInformation::List(list) => {
+            let segment_url = segment
+                .number()
+                .checked_sub(list.start_number())
+                .and_then(|index| list.segment_url().get(index as usize))
+                .ok_or_else(|| RecordError::UnlocatableSegments(representation_id.to_string()))?;
+            Ok(base.locate(segment_url.media(), range(segment_url.media_range())?)?)
+        }
+        SegmentInformation::Base(_) => Err(RecordError::UnlocatableSegments(
+            representation_id.to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn manifest(attributes: &str, periods: &str) -> String {
+        format!(
+            r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" {} profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z" minimumUpdatePeriod="PT0S" timeShiftBufferDepth="PT30S" minBufferTime="PT2S">{}</MPD>"#,
+            attributes, periods
+        )
+    }
+
+    fn period(id: &str, start: u32, media: &str, start_number: u64, timeline: &str) -> String {
+        format!(
+            r#"<Period id="{}" start="PT{}S"><AdaptationSet mimeType="video/mp4"><SegmentTemplate timescale="10" startNumber="{}" media="{}" initialization="$RepresentationID$/init.mp4"><SegmentTimeline>{}</SegmentTimeline></SegmentTemplate><Representation id="video" bandwidth="1"/><Representation id="audio" bandwidth="1"/></AdaptationSet></Period>"#,
+            id, start, start_number, media, timeline
+        )
+    }
+
+    #[tokio::test]
+    async fn should_record_until_the_presentation_ends() {
+        let versions = [
+            manifest(
+                r#"type="dynamic""#,
+                &period(
+                    "1",
+                    0,
+                    "$RepresentationID$/$Number$.m4s",
+                    1,
+                    r#"<S t="100" d="20" r="1"/>"#,
+                ),
+            ),
+            // The numbering restarts, and a new period begins.
+            manifest(
+                r#"type="dynamic""#,
+                &(period(
+                    "1",
+                    0,
+                    "$RepresentationID$/$Number$.m4s",
+                    10,
+                    r#"<S t="120" d="20" r="1"/>"#,
+                ) + &period(
+                    "2",
+                    20,
+                    "p2/$RepresentationID$/$Number$.m4s",
+                    1,
+                    r#"<S t="0" d="20"/>"#,
+                )),
+            ),
+            manifest(
+                r#"type="static" mediaPresentationDuration="PT22S""#,
+                &period(
+                    "2",
+                    20,
+                    "p2/$RepresentationID$/$Number$.m4s",
+                    1,
+                    r#"<S t="0" d="20"/>"#,
+                ),
+            ),
+        ];
+        let fetches = AtomicUsize::new(0);
+        let (root, requests) = serve(move |request| match request.path.as_str() {
+            "/live.mpd" => {
+                let fetch = fetches.fetch_add(1, Ordering::SeqCst);
+                Response::ok(versions[fetch.min(versions.len() - 1)].clone())
+            }
+            path => Response::ok(path),
+        })
+        .await;
+        let directory = std::env::temp_dir().join(format!("dash-record-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let report = Recorder::new()
+            .with_representations(["video"])
+            .with_retry_delay(std::time::Duration::from_millis(10))
+            .record(root.join("live.mpd").unwrap().as_str(), &directory)
+            .await
+            .unwrap();
+
+        assert_eq!(report.segments(), 4);
+        assert_eq!(report.failed(), 0);
+        assert_eq!(report.duration(), Duration::seconds(12));
+        let read = |path: &str| std::fs::read_to_string(directory.join(path)).unwrap();
+        assert_eq!(read("1/video/init.mp4"), "/video/init.mp4");
+        assert_eq!(read("1/video/120.m4s"), "/video/2.m4s");
+        assert_eq!(read("1/video/140.m4s"), "/video/11.m4s");
+        assert_eq!(read("2/video/0.m4s"), "/p2/video/1.m4s");
+        assert!(requests
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|request| !request.path.contains("audio")));
+
+        let mpd: MPD = read(ARCHIVED_MANIFEST).parse().unwrap();
+        assert!(mpd.is_static());
+        assert_eq!(mpd.minimum_update_period(), None);
+        assert_eq!(
+            mpd.media_presentation_duration(),
+            Some(Duration::seconds(12))
+        );
+        assert_eq!(mpd.periods().len(), 2);
+        let first = &mpd.periods()[0];
+        assert_eq!(first.start(), Some(Duration::zero()));
+        let representations = first.adaptations()[0].representations();
+        assert_eq!(representations.len(), 1);
+        let template = representations[0].segment_template().unwrap();
+        assert_eq!(template.media(), "1/video/$Time$.m4s");
+        assert_eq!(template.initialization_url(), "1/video/init.mp4");
+        assert_eq!(template.presentation_time_offset(), 100);
+        let times: Vec<_> = template
+            .timeline_segments(None)
+            .unwrap()
+            .map(|segment| segment.time())
+            .collect();
+        assert_eq!(times, [100, 120, 140]);
+        assert_eq!(mpd.periods()[1].start(), Some(Duration::seconds(10)));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_record_open_ended_timelines_up_to_the_live_edge() {
+        // The live edge is a bit more than 9 s after the start: 4 segments of 2 s.
+        let start = (chrono::Utc::now() - Duration::seconds(9))
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let timeline = r#"<S t="0" d="20" r="-1"/>"#;
+        let versions = [
+            manifest(
+                r#"type="dynamic""#,
+                &period("1", 0, "$RepresentationID$/$Number$.m4s", 1, timeline),
+            ),
+            manifest(
+                r#"type="static" mediaPresentationDuration="PT8S""#,
+                &period("1", 0, "$RepresentationID$/$Number$.m4s", 1, timeline),
+            ),
+        ]
+        .map(|version| version.replace("2024-01-01T00:00:00Z", &start));
+        let fetches = AtomicUsize::new(0);
+        let (root, _) = serve(move |request| match request.path.as_str() {
+            "/live.mpd" => {
+                let fetch = fetches.fetch_add(1, Ordering::SeqCst);
+                Response::ok(versions[fetch.min(versions.len() - 1)].clone())
+            }
+            path => Response::ok(path),
+        })
+        .await;
+        let directory =
+            std::env::temp_dir().join(format!("dash-record-live-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let report = Recorder::new()
+            .with_representations(["video"])
+            .with_retry_delay(std::time::Duration::from_millis(10))
+            .record(root.join("live.mpd").unwrap().as_str(), &directory)
+            .await
+            .unwrap();
+
+        assert_eq!(report.segments(), 4);
+        assert_eq!(report.duration(), Duration::seconds(8));
+        let read = |path: &str| std::fs::read_to_string(directory.join(path)).unwrap();
+        assert_eq!(read("1/video/60.m4s"), "/video/4.m4s");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_download_again_a_failed_initialization_segment() {
+        let versions = [
+            manifest(
+                r#"type="dynamic""#,
+                &period(
+                    "1",
+                    0,
+                    "$RepresentationID$/$Number$.m4s",
+                    1,
+                    r#"<S t="0" d="20"/>"#,
+                ),
+            ),
+            manifest(
+                r#"type="static" mediaPresentationDuration="PT4S""#,
+                &period(
+                    "1",
+                    0,
+                    "$RepresentationID$/$Number$.m4s",
+                    1,
+                    r#"<S t="0" d="20" r="1"/>"#,
+                ),
+            ),
+        ];
+        let fetches = AtomicUsize::new(0);
+        let initializations = AtomicUsize::new(0);
+        let (root, _) = serve(move |request| match request.path.as_str() {
+            "/live.mpd" => {
+                let fetch = fetches.fetch_add(1, Ordering::SeqCst);
+                Response::ok(versions[fetch.min(versions.len() - 1)].clone())
+            }
+            "/video/init.mp4" if initializations.fetch_add(1, Ordering::SeqCst) == 0 => {
+                Response::not_found()
+            }
+            path => Response::ok(path),
+        })
+        .await;
+        let directory =
+            std::env::temp_dir().join(format!("dash-record-init-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let report = Recorder::new()
+            .with_downloader(SegmentDownloader::new().with_max_retries(0))
+            .with_representations(["video"])
+            .with_retry_delay(std::time::Duration::from_millis(10))
+            .record(root.join("live.mpd").unwrap().as_str(), &directory)
+            .await
+            .unwrap();
+
+        assert_eq!(report.segments(), 2);
+        assert_eq!(report.failed(), 1);
+        let read = |path: &str| std::fs::read_to_string(directory.join(path)).unwrap();
+        assert_eq!(read("1/video/init.mp4"), "/video/init.mp4");
+        let mpd: MPD = read(ARCHIVED_MANIFEST).parse().unwrap();
+        let representation = &mpd.periods()[0].adaptations()[0].representations()[0];
+        assert_eq!(
+            representation
+                .segment_template()
+                .unwrap()
+                .initialization_url(),
+            "1/video/init.mp4"
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn should_consolidate_the_timeline() {
+        let segments: Vec<_> = [(0, 20), (20, 20), (40, 20), (60, 10), (90, 10), (100, 10)]
+            .iter()
+            .enumerate()
+            .map(|(number, (time, duration))| {
+                TimelineSegment::new(number as u64, *time, *duration, 10, 0)
+            })
+            .collect();
+
+        assert_eq!(
+            timeline(&segments),
+            r#"<S t="0" d="20" r="2"/><S d="10"/><S t="90" d="10" r="1"/>"#
+        );
+    }
+
+    #[tokio::test]
+    async fn should_refuse_static_mpds() {
+        let (root, _) = serve(|_| {
+            Response::ok(manifest(
+                r#"type="static" mediaPresentationDuration="PT2S""#,
+                &period("1", 0, "$Number$.m4s", 1, r#"<S t="0" d="20"/>"#),
+            ))
+        })
+        .await;
+
+        let result = Recorder::new()
+            .record(
+                root.join("vod.mpd").unwrap().as_str(),
+                &std::env::temp_dir().join("dash-record-static"),
+            )
+            .await;
+
+        assert!(matches!(result, Err(RecordError::Static(_))));
+    }
+}
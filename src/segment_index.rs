@@ -0,0 +1,487 @@
+//! Subsegments of single-file `Representation`s, from the `sidx` boxes given by their
+//! `SegmentBase` (ISO/IEC 23009-1 6.3.4.4, ISO/IEC 14496-12 8.16.3).
+use std::fmt;
+
+use crate::base_url::{BaseURLError, ByteRange, ResolvedBaseURL};
+use crate::download::{DownloadError, SegmentDownloader};
+use crate::models::segments::{MediaTime, SegmentBase};
+use crate::mp4::{self, FourCC, Mp4Box, Mp4Error, SegmentIndex};
+
+#[derive(Debug)]
+pub enum SegmentIndexError {
+    BaseURL(BaseURLError),
+    /// The `SegmentBase` has neither `indexRange` nor `RepresentationIndex`.
+    NoIndex,
+    Download(DownloadError),
+    Mp4(Mp4Error),
+    /// No `sidx` box where the index is expected, at the given URL.
+    MissingSegmentIndex(String),
+    /// A `sidx` box of a separate index segment references another one, the offsets of
+    /// the media can't be known.
+    UnsupportedHierarchy(String),
+    /// The offsets or times of a `sidx` box don't fit in 64 bits.
+    Overflow,
+}
+
+impl fmt::Display for SegmentIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SegmentIndexError::BaseURL(error) => error.fmt(f),
+            SegmentIndexError::NoIndex => {
+                write!(f, "SegmentBase without indexRange nor RepresentationIndex")
+            }
+            SegmentIndexError::Download(error) => error.fmt(f),
+            SegmentIndexError::Mp4(error) => write!(f, "Invalid segment index: {}", error),
+            SegmentIndexError::MissingSegmentIndex(url) => {
+                write!(f, "No sidx box in the index of {}", url)
+            }
+            SegmentIndexError::UnsupportedHierarchy(url) => {
+                write!(f, "Unsupported hierarchical sidx boxes in {}", url)
+            }
+            SegmentIndexError::Overflow => write!(f, "Overflowing offsets or times in sidx box"),
+        }
+    }
+}
+
+impl std::error::Error for SegmentIndexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SegmentIndexError::BaseURL(error) => Some(error),
+            SegmentIndexError::Download(error) => Some(error),
+            SegmentIndexError::Mp4(error) => Some(error),
+            SegmentIndexError::NoIndex
+            | SegmentIndexError::MissingSegmentIndex(_)
+            | SegmentIndexError::UnsupportedHierarchy(_)
+            | SegmentIndexError::Overflow => None,
+        }
+    }
+}
+
+impl From<BaseURLError> for SegmentIndexError {
+    fn from(error: BaseURLError) -> Self {
+        SegmentIndexError::BaseURL(error)
+    }
+}
+
+impl From<DownloadError> for SegmentIndexError {
+    fn from(error: DownloadError) -> Self {
+        SegmentIndexError::Download(error)
+    }
+}
+
+impl From<Mp4Error> for SegmentIndexError {
+    fn from(error: Mp4Error) -> Self {
+        SegmentIndexError::Mp4(error)
+    }
+}
+
+/// Subsegment of a single-file `Representation`, addressable like a segment of a
+/// `SegmentList`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subsegment {
+    range: ByteRange,
+    earliest_presentation_time: u64,
+    start: MediaTime,
+    duration: MediaTime,
+    starts_with_sap: bool,
+    sap_type: u8,
+    sap_delta_time: u32,
+}
+
+impl Subsegment {
+    /// Bytes of the subsegment in the media file.
+    pub fn range(&self) -> ByteRange {
+        self.range
+    }
+
+    /// Media time, in the timescale of the `sidx` box.
+    pub fn earliest_presentation_time(&self) -> u64 {
+        self.earliest_presentation_time
+    }
+
+    /// Start relative to the start of the period, `presentationTimeOffset` removed.
+    pub fn start(&self) -> MediaTime {
+        self.start
+    }
+
+    pub fn duration(&self) -> MediaTime {
+        self.duration
+    }
+
+    pub fn starts_with_sap(&self) -> bool {
+        self.starts_with_sap
+    }
+
+    /// 0 when unknown.
+    pub fn sap_type(&self) -> u8 {
+        self.sap_type
+    }
+
+    pub fn sap_delta_time(&self) -> u32 {
+        self.sap_delta_time
+    }
+}
+
+/// Lists the subsegments of `Representation`s described by a `SegmentBase`: downloads
+/// the `sidx` box at its `indexRange`, or its `RepresentationIndex`, and follows the
+/// references to other `sidx` boxes.
+#[derive(Debug, Clone, Default)]
+pub struct SegmentIndexResolver {
+    downloader: SegmentDownloader,
+}
+
+/// Resource holding `sidx` boxes: the media file, or a separate index segment.
+struct IndexSource<'a> {
+    base: &'a ResolvedBaseURL,
+    reference: &'a str,
+    separate: bool,
+}
+
+/// `presentationTimeOffset` of the `SegmentBase`, in its timescale.
+#[derive(Clone, Copy)]
+struct Offset {
+    ticks: u64,
+    timescale: u64,
+}
+
+impl SegmentIndexResolver {
+    pub fn new() -> Self {
+        SegmentIndexResolver::default()
+    }
+
+    /// Downloads the indexes with `downloader`, retrying as it does.
+    pub fn with_downloader(mut self, downloader: SegmentDownloader) -> Self {
+        self.downloader = downloader;
+        self
+    }
+
+    /// Subsegments of the media at `base`, the resolved `BaseURL` of the
+    /// `Representation`, in presentation order.
+    ///
+    /// When `indexRangeExact` is false the `sidx` box may go past `indexRange`, the rest
+    /// of it is then downloaded.
+    pub async fn resolve(
+        &self,
+        base: &ResolvedBaseURL,
+        segment_base: &SegmentBase,
+    ) -> Result<Vec<Subsegment>, SegmentIndexError> {
+        let (source, range) = match segment_base.representation_index() {
+            Some(index) => {
+                let range = match index.range() {
+                    "" => None,
+                    range => Some(range.parse()?),
+                };
+                let source = IndexSource {
+                    base,
+                    reference: index.source_url(),
+                    separate: !index.source_url().is_empty(),
+                };
+                (source, range)
+            }
+            None if !segment_base.index_range().is_empty() => {
+                let source = IndexSource {
+                    base,
+                    reference: "",
+                    separate: false,
+                };
+                (source, Some(segment_base.index_range().parse()?))
+            }
+            None => return Err(SegmentIndexError::NoIndex),
+        };
+        let offset = Offset {
+            ticks: segment_base.presentation_time_offset(),
+            timescale: segment_base.timescale(),
+        };
+        let (index, end) = self.segment_index(&source, range).await?;
+        let anchor = if source.separate { 0 } else { end };
+        let mut subsegments = Vec::new();
+        self.expand(&source, &index, anchor, offset, &mut subsegments)
+            .await?;
+        Ok(subsegments)
+    }
+
+    /// First `sidx` box of `range` of `source`, and the offset of the byte following it.
+    async fn segment_index(
+        &self,
+        source: &IndexSource<'_>,
+        range: Option<ByteRange>,
+    ) -> Result<(SegmentIndex, u64), SegmentIndexError> {
+        let location = source.base.locate(source.reference, range)?;
+        let first = range.map_or(0, |range| range.first());
+        let mut data = self.downloader.download(&location).await?;
+        let missing = || SegmentIndexError::MissingSegmentIndex(location.url().to_string());
+        let (box_type, size) = match mp4::box_header(&data) {
+            Some((box_type, size)) if box_type == "sidx" => (box_type, size),
+            _ => return Err(missing()),
+        };
+        if size > mp4::MAX_BOX_SIZE {
+            return Err(Mp4Error::InvalidSize(box_type, size).into());
+        }
+        let end = first.checked_add(size).ok_or(SegmentIndexError::Overflow)?;
+        if size > data.len() as u64 {
+            let rest = ByteRange::new(first + data.len() as u64, Some(end - 1));
+            let rest = source.base.locate(source.reference, Some(rest))?;
+            data.extend(self.downloader.download(&rest).await?);
+        }
+        let boxes = mp4::parse(
+            data.get(..size as usize)
+                .ok_or(Mp4Error::Truncated(box_type))?,
+        )?;
+        match boxes.into_iter().next() {
+            Some(Mp4Box::Sidx(index)) => Ok((index, end)),
+            _ => Err(missing()),
+        }
+    }
+
+    /// Appends the subsegments referenced by `index` to `subsegments`, the `sidx` box
+    /// ending at `anchor`.
+    async fn expand(
+        &self,
+        source: &IndexSource<'_>,
+        index: &SegmentIndex,
+        anchor: u64,
+        offset: Offset,
+        subsegments: &mut Vec<Subsegment>,
+    ) -> Result<(), SegmentIndexError> {
+        let timescale = u64::from(index.timescale());
+        let pto = (i128::from(offset.ticks) * i128::from(timescale)
+            / i128::from(offset.timescale.max(1))) as i64;
+        let mut position = anchor
+            .checked_add(index.first_offset())
+            .ok_or(SegmentIndexError::Overflow)?;
+        let mut time = index.earliest_presentation_time();
+        for reference in index.references() {
+            let size = u64::from(reference.referenced_size());
+            // An empty reference has no range, and would make a child `sidx` read again.
+            if size == 0 {
+                return Err(Mp4Error::InvalidSize(FourCC::new(b"sidx"), 0).into());
+            }
+            let last = position
+                .checked_add(size - 1)
+                .ok_or(SegmentIndexError::Overflow)?;
+            if reference.is_index() {
+                if source.separate {
+                    let url = source.base.locate(source.reference, None)?;
+                    return Err(SegmentIndexError::UnsupportedHierarchy(
+                        url.url().to_string(),
+                    ));
+                }
+                // Only the header of the referenced box is needed to download the rest.
+                let header = position.checked_add(7).ok_or(SegmentIndexError::Overflow)?;
+                let header = ByteRange::new(position, Some(header));
+                let (child, end) = self.segment_index(source, Some(header)).await?;
+                Box::pin(self.expand(source, &child, end, offset, subsegments)).await?;
+            } else {
+                subsegments.push(Subsegment {
+                    range: ByteRange::new(position, Some(last)),
+                    earliest_presentation_time: time,
+                    start: MediaTime::new(time as i64 - pto, timescale),
+                    duration: MediaTime::new(i64::from(reference.subsegment_duration()), timescale),
+                    starts_with_sap: reference.starts_with_sap(),
+                    sap_type: reference.sap_type(),
+                    sap_delta_time: reference.sap_delta_time(),
+                });
+            }
+            position = last.checked_add(1).ok_or(SegmentIndexError::Overflow)?;
+            time = time
+                .checked_add(u64::from(reference.subsegment_duration()))
+                .ok_or(SegmentIndexError::Overflow)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mpd::MPD;
+    use crate::models::segments::SegmentInformation;
+    use crate::mp4::tests::{ftyp, full};
+    use crate::test_server::{serve, Response};
+    use url::Url;
+
+    /// Version 1 `sidx` box, references given by (is index, size, duration).
+    fn sidx(earliest_presentation_time: u64, references: &[(bool, u32, u32)]) -> Vec<u8> {
+        let mut payload = 1u32.to_be_bytes().to_vec();
+        payload.extend(1000u32.to_be_bytes());
+        payload.extend(earliest_presentation_time.to_be_bytes());
+        payload.extend(0u64.to_be_bytes());
+        payload.extend(0u16.to_be_bytes());
+        payload.extend((references.len() as u16).to_be_bytes());
+        for (is_index, size, duration) in references {
+            payload.extend((u32::from(*is_index) << 31 | size).to_be_bytes());
+            payload.extend(duration.to_be_bytes());
+            payload.extend(0x9000_0000u32.to_be_bytes());
+        }
+        full(b"sidx", 1, 0, &payload)
+    }
+
+    fn segment_base(mpd: &str) -> SegmentBase {
+        let mpd: MPD = mpd.parse().unwrap();
+        let period = &mpd.periods()[0];
+        let adaptation = &period.adaptations()[0];
+        let representation = &adaptation.representations()[0];
+        match period.segment_information(adaptation, representation) {
+            Some(SegmentInformation::Base(base)) => base,
+            other => panic!("Unexpected segment information {:?}", other),
+        }
+    }
+
+    fn mpd(segment_base: &str) -> String {
+        format!(
+            r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" profiles="urn:mpeg:dash:profile:isoff-on-demand:2011" mediaPresentationDuration="PT10S" minBufferTime="PT2S">
+  <Period><AdaptationSet><Representation id="v" bandwidth="1">{}</Representation></AdaptationSet></Period>
+</MPD>"#,
+            segment_base
+        )
+    }
+
+    #[tokio::test]
+    async fn should_resolve_hierarchical_segment_indexes() {
+        // ftyp, root sidx, then each child sidx followed by its media.
+        let first = sidx(2000, &[(false, 100, 2000)]);
+        let second = sidx(4000, &[(false, 50, 1000), (false, 60, 1500)]);
+        let mut file = ftyp(b"ftyp");
+        let start = file.len();
+        file.extend(sidx(
+            2000,
+            &[
+                (true, first.len() as u32 + 100, 2000),
+                (true, second.len() as u32 + 110, 2500),
+            ],
+        ));
+        let root_end = file.len() as u64;
+        file.extend(&first);
+        file.extend([0; 100]);
+        file.extend(&second);
+        file.extend([0; 110]);
+        let (root, _) = serve(move |_| Response::ok(file.clone())).await;
+
+        // The index range is too short: the rest of the box is downloaded.
+        let segment_base = segment_base(&mpd(&format!(
+            r#"<SegmentBase indexRange="{}-{}" timescale="1" presentationTimeOffset="1"/>"#,
+            start,
+            start + 20
+        )));
+        let base = ResolvedBaseURL::new(root.join("video.mp4").unwrap());
+        let subsegments = SegmentIndexResolver::new()
+            .resolve(&base, &segment_base)
+            .await
+            .unwrap();
+
+        let first_media = root_end + first.len() as u64;
+        let second_media = first_media + 100 + second.len() as u64;
+        let ranges: Vec<_> = subsegments.iter().map(Subsegment::range).collect();
+        assert_eq!(
+            ranges,
+            [
+                ByteRange::new(first_media, Some(first_media + 99)),
+                ByteRange::new(second_media, Some(second_media + 49)),
+                ByteRange::new(second_media + 50, Some(second_media + 109)),
+            ]
+        );
+        let starts: Vec<_> = subsegments.iter().map(|s| s.start().ticks()).collect();
+        assert_eq!(starts, [1000, 3000, 4000]);
+        assert_eq!(subsegments[2].duration(), MediaTime::new(1500, 1000));
+        assert!(subsegments[0].starts_with_sap());
+        assert_eq!(subsegments[0].sap_type(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_resolve_separate_representation_indexes() {
+        let index = sidx(0, &[(false, 300, 4000), (false, 200, 4000)]);
+        let (root, requests) = serve(move |request| match request.path.as_str() {
+            "/index.sidx" => Response::ok(index.clone()),
+            _ => Response::not_found(),
+        })
+        .await;
+
+        let segment_base = segment_base(&mpd(
+            r#"<SegmentBase><RepresentationIndex sourceURL="index.sidx"/></SegmentBase>"#,
+        ));
+        let base = ResolvedBaseURL::new(root.join("video.mp4").unwrap());
+        let subsegments = SegmentIndexResolver::new()
+            .resolve(&base, &segment_base)
+            .await
+            .unwrap();
+
+        assert_eq!(subsegments[0].range(), ByteRange::new(0, Some(299)));
+        assert_eq!(subsegments[1].range(), ByteRange::new(300, Some(499)));
+        assert_eq!(subsegments[1].start(), MediaTime::new(4000, 1000));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_empty_references() {
+        let index = sidx(0, &[(false, 300, 4000), (false, 0, 4000)]);
+        let (root, _) = serve(move |_| Response::ok(index.clone())).await;
+
+        let segment_base = segment_base(&mpd(
+            r#"<SegmentBase><RepresentationIndex sourceURL="index.sidx"/></SegmentBase>"#,
+        ));
+        let base = ResolvedBaseURL::new(root.join("video.mp4").unwrap());
+        let error = SegmentIndexResolver::new()
+            .resolve(&base, &segment_base)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            SegmentIndexError::Mp4(Mp4Error::InvalidSize(box_type, 0)) if box_type == FourCC::new(b"sidx")
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_refuse_oversized_segment_indexes() {
+        // A 64-bit largesize, the rest of the box must not be requested.
+        let mut header = 1u32.to_be_bytes().to_vec();
+        header.extend(b"sidx");
+        header.extend(u64::MAX.to_be_bytes());
+        let (root, requests) = serve(move |_| Response::ok(header.clone())).await;
+
+        let segment_base = segment_base(&mpd(
+            r#"<SegmentBase><RepresentationIndex sourceURL="index.sidx"/></SegmentBase>"#,
+        ));
+        let base = ResolvedBaseURL::new(root.join("video.mp4").unwrap());
+        let error = SegmentIndexResolver::new()
+            .resolve(&base, &segment_base)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            SegmentIndexError::Mp4(Mp4Error::InvalidSize(box_type, u64::MAX)) if box_type == FourCC::new(b"sidx")
+        ));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_overflowing_times() {
+        let index = sidx(u64::MAX, &[(false, 300, 4000), (false, 200, 4000)]);
+        let (root, _) = serve(move |_| Response::ok(index.clone())).await;
+
+        let segment_base = segment_base(&mpd(
+            r#"<SegmentBase><RepresentationIndex sourceURL="index.sidx"/></SegmentBase>"#,
+        ));
+        let base = ResolvedBaseURL::new(root.join("video.mp4").unwrap());
+        let error = SegmentIndexResolver::new()
+            .resolve(&base, &segment_base)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, SegmentIndexError::Overflow));
+    }
+
+    #[tokio::test]
+    async fn should_refuse_segment_bases_without_index() {
+        let segment_base = segment_base(&mpd(r#"<SegmentBase timescale="1"/>"#));
+        let base = ResolvedBaseURL::new(Url::parse("http://localhost/video.mp4").unwrap());
+
+        let error = SegmentIndexResolver::new()
+            .resolve(&base, &segment_base)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, SegmentIndexError::NoIndex));
+    }
+}
@@ -0,0 +1,254 @@
+//! Choice of a base URL among the CDNs of a MPD (ETSI TS 103 285 10.8.2).
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::base_url::ResolvedBaseURL;
+
+/// Picks the base URL to request segments from and keeps track of the failing
+/// locations.
+///
+/// A candidate is chosen, in this order:
+/// - among the non blacklisted ones, the first at the preferred `serviceLocation`,
+/// - otherwise among those with the lowest `dvb:priority`, at random in proportion to
+///   their `dvb:weight`.
+///
+/// A location is blacklisted after `max_failures` consecutive failures, so the next
+/// selection fails over to another one. Candidates without `serviceLocation` are
+/// identified by the origin of their URL.
+#[derive(Debug, Clone)]
+pub struct BaseURLSelector {
+    preferred_location: Option<String>,
+    max_failures: u32,
+    failures: HashMap<String, u32>,
+    seed: u64,
+}
+
+impl Default for BaseURLSelector {
+    fn default() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        BaseURLSelector {
+            preferred_location: None,
+            max_failures: 1,
+            failures: HashMap::new(),
+            seed,
+        }
+    }
+}
+
+impl BaseURLSelector {
+    pub fn new() -> Self {
+        BaseURLSelector::default()
+    }
+
+    pub fn with_preferred_location(mut self, service_location: &str) -> Self {
+        self.preferred_location = Some(service_location.to_string());
+        self
+    }
+
+    /// Consecutive failures after which a location is blacklisted, 1 by default.
+    pub fn with_max_failures(mut self, max_failures: u32) -> Self {
+        self.max_failures = max_failures.max(1);
+        self
+    }
+
+    /// Seed of the weighted selection, to make it reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Base URL to use among `candidates`, `None` when they are all blacklisted.
+    pub fn select<'a>(&mut self, candidates: &'a [ResolvedBaseURL]) -> Option<&'a ResolvedBaseURL> {
+        let available: Vec<&ResolvedBaseURL> = candidates
+            .iter()
+            .filter(|candidate| !self.is_blacklisted(&location(candidate)))
+            .collect();
+        if let Some(preferred) = self.preferred_location.as_deref() {
+            if let Some(candidate) = available
+                .iter()
+                .find(|candidate| candidate.service_location() == preferred)
+            {
+                return Some(candidate);
+            }
+        }
+
+        let priority = available
+            .iter()
+            .map(|candidate| candidate.priority())
+            .min()?;
+        let group: Vec<&ResolvedBaseURL> = available
+            .into_iter()
+            .filter(|candidate| candidate.priority() == priority)
+            .collect();
+        let total: u64 = group
+            .iter()
+            .map(|candidate| candidate.weight() as u64)
+            .sum();
+        if total == 0 {
+            return group.first().copied();
+        }
+        let mut pick = self.next_random() % total;
+        group.into_iter().find(|candidate| {
+            let weight = candidate.weight() as u64;
+            if pick < weight {
+                true
+            } else {
+                pick -= weight;
+                false
+            }
+        })
+    }
+
+    /// Records a failed request to `candidate`, `true` when its location gets
+    /// blacklisted.
+    pub fn report_failure(&mut self, candidate: &ResolvedBaseURL) -> bool {
+        let failures = self.failures.entry(location(candidate)).or_insert(0);
+        *failures += 1;
+        *failures == self.max_failures
+    }
+
+    /// Records a successful request to `candidate`, which clears its failures.
+    pub fn report_success(&mut self, candidate: &ResolvedBaseURL) {
+        self.failures.remove(&location(candidate));
+    }
+
+    /// Whether `location`, a `serviceLocation` or the origin of a URL, is blacklisted.
+    pub fn is_blacklisted(&self, location: &str) -> bool {
+        self.failures
+            .get(location)
+            .is_some_and(|failures| *failures >= self.max_failures)
+    }
+
+    /// Forgets every failure, e.g. once all the locations are blacklisted.
+    pub fn reset(&mut self) {
+        self.failures.clear();
+    }
+
+    /// splitmix64, enough to spread the requests.
+    fn next_random(&mut self) -> u64 {
+        self.seed = self.seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.seed;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+}
+
+fn location(candidate: &ResolvedBaseURL) -> String {
+    match candidate.service_location() {
+        "" => candidate.url().origin().ascii_serialization(),
+        service_location => service_location.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MPD;
+    use url::Url;
+
+    const SOURCE: &str = r#"<MPD xmlns:dvb="urn:dvb:dash:dash-extensions:2014-1" profiles="urn:dvb:dash:profile:dvb-dash:2014" type="dynamic" availabilityStartTime="2024-01-01T00:00:00Z">
+    <BaseURL serviceLocation="cdn-a" dvb:priority="1" dvb:weight="3">https://a.example.com/live/</BaseURL>
+    <BaseURL serviceLocation="cdn-b" dvb:priority="1" dvb:weight="1">https://b.example.com/live/</BaseURL>
+    <BaseURL serviceLocation="backup" dvb:priority="2">https://backup.example.com/live/</BaseURL>
+    <Period id="1">
+        <AdaptationSet>
+            <Representation id="1" bandwidth="1"/>
+        </AdaptationSet>
+    </Period>
+</MPD>"#;
+
+    fn candidates() -> Vec<ResolvedBaseURL> {
+        let mpd: MPD = SOURCE.parse().unwrap();
+        let period = &mpd.periods()[0];
+        let adaptation = &period.adaptations()[0];
+        mpd.resolve_base_urls(
+            &Url::parse("https://manifests.example.com/live.mpd").unwrap(),
+            period,
+            adaptation,
+            &adaptation.representations()[0],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn should_read_dvb_priority_and_weight() {
+        let candidates = candidates();
+        let attributes: Vec<(&str, u32, u32)> = candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    candidate.service_location(),
+                    candidate.priority(),
+                    candidate.weight(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            attributes,
+            vec![("cdn-a", 1, 3), ("cdn-b", 1, 1), ("backup", 2, 1)]
+        );
+        let mpd: MPD = SOURCE.parse().unwrap();
+        assert!(mpd
+            .to_xml()
+            .unwrap()
+            .contains(r#"<BaseURL serviceLocation="cdn-a" dvb:priority="1" dvb:weight="3">"#));
+    }
+
+    #[test]
+    fn should_select_by_weight_among_highest_priority() {
+        let candidates = candidates();
+        let mut selector = BaseURLSelector::new().with_seed(7);
+        let mut counts = HashMap::new();
+        for _ in 0..4000 {
+            let selected = selector.select(&candidates).unwrap();
+            *counts.entry(selected.service_location()).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get("backup"), None);
+        let a = counts["cdn-a"];
+        assert!((2800..3200).contains(&a), "cdn-a selected {} times", a);
+    }
+
+    #[test]
+    fn should_prefer_service_location() {
+        let candidates = candidates();
+        let mut selector = BaseURLSelector::new().with_preferred_location("backup");
+        assert_eq!(
+            selector.select(&candidates).unwrap().service_location(),
+            "backup"
+        );
+    }
+
+    #[test]
+    fn should_fail_over_after_repeated_errors() {
+        let candidates = candidates();
+        let mut selector = BaseURLSelector::new()
+            .with_preferred_location("cdn-a")
+            .with_max_failures(2);
+        let cdn_a = &candidates[0];
+        assert!(!selector.report_failure(cdn_a));
+        selector.report_success(cdn_a);
+        assert!(!selector.report_failure(cdn_a));
+        assert_eq!(selector.select(&candidates), Some(cdn_a));
+        assert!(selector.report_failure(cdn_a));
+        assert!(selector.is_blacklisted("cdn-a"));
+        assert_eq!(
+            selector.select(&candidates).unwrap().service_location(),
+            "cdn-b"
+        );
+
+        selector.report_failure(&candidates[1]);
+        selector.report_failure(&candidates[1]);
+        assert_eq!(
+            selector.select(&candidates).unwrap().service_location(),
+            "backup"
+        );
+        selector.report_failure(&candidates[2]);
+        selector.report_failure(&candidates[2]);
+        assert_eq!(selector.select(&candidates), None);
+        selector.reset();
+        assert_eq!(selector.select(&candidates), Some(cdn_a));
+    }
+}
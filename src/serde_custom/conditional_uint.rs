@@ -1,5 +1,7 @@
 use either::*;
-use serde::{de, Deserialize, Deserializer, Serializer};
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub const NAME: &str = "conditional_uint";
 
 pub fn deserialize<'de, D>(deserializer: D) -> Result<Either<u64, bool>, D::Error>
 where
@@ -10,7 +12,9 @@ where
         "true" => Ok(Right(true)),
         "false" => Ok(Right(false)),
         value => {
-            let value = value.parse::<u64>().map_err(de::Error::custom)?;
+            let value = value
+                .parse::<u64>()
+                .map_err(|error| super::rejected(NAME, format_args!("{} > {}", error, value)))?;
             Ok(Left(value))
         }
     }
@@ -1,5 +1,7 @@
 use chrono::Duration;
-use serde::{self, de, Deserialize, Deserializer, Serializer};
+use serde::{self, Deserialize, Deserializer, Serializer};
+
+pub const NAME: &str = "duration_iso_8601";
 
 const WEEK_AS_MS: i64 = 604800000;
 const DAYS_AS_MS: i64 = 86400000;
@@ -19,7 +21,7 @@ enum Iso8601Error {
 
 impl std::fmt::Display for Iso8601Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return match self {
+        match self {
             Iso8601Error::InvalidFormat(message, value) => {
                 write!(f, "Invalid ISO_8601 Duration: {} > {}", message, value)
             }
@@ -58,7 +60,7 @@ impl std::fmt::Display for Iso8601Error {
                     message, value
                 )
             }
-        };
+        }
     }
 }
 
@@ -76,7 +78,7 @@ impl Iso8601 {
             ));
         }
         for char in str_sequence.chars() {
-            if char.is_digit(10) {
+            if char.is_ascii_digit() {
                 temp.push(char);
                 continue;
             }
@@ -84,76 +86,48 @@ impl Iso8601 {
             match (char, time) {
                 ('.', _) => temp.push(char),
                 ('S' | 'H', false) => {
-                    if !time {
-                        return Err(Iso8601Error::InvalidTokenPeriod(
-                            char,
-                            str_sequence.to_string(),
-                        ));
-                    }
+                    return Err(Iso8601Error::InvalidTokenPeriod(
+                        char,
+                        str_sequence.to_string(),
+                    ));
                 }
                 ('Y' | 'W' | 'D', true) => {
-                    if !time {
-                        return Err(Iso8601Error::InvalidTokenTime(
-                            char,
-                            str_sequence.to_string(),
-                        ));
-                    }
+                    return Err(Iso8601Error::InvalidTokenTime(
+                        char,
+                        str_sequence.to_string(),
+                    ));
                 }
                 ('Y', false) => {
-                    if time {
-                        return Err(Iso8601Error::InvalidTokenTime(
-                            char,
-                            str_sequence.to_string(),
-                        ));
-                    }
                     return Err(Iso8601Error::NotImplemented(
                         "Year (Y)".into(),
                         str_sequence.to_string(),
                     ));
                 }
                 ('W', false) => {
-                    if time {
-                        return Err(Iso8601Error::InvalidTokenTime(
-                            char,
-                            str_sequence.to_string(),
-                        ));
-                    }
                     milliseconds = temp.parse::<f64>().map_err(|_| {
                         Iso8601Error::InvalidNumberFormat("weeks".into(), str_sequence.to_string())
                     })? * WEEK_AS_MS as f64;
                     temp = String::new();
                 }
                 ('D', false) => {
-                    if time {
-                        return Err(Iso8601Error::InvalidTokenTime(
-                            char,
-                            str_sequence.to_string(),
-                        ));
-                    }
                     milliseconds += temp.parse::<f64>().map_err(|_| {
                         Iso8601Error::InvalidNumberFormat("days".into(), str_sequence.to_string())
                     })? * DAYS_AS_MS as f64;
                     temp = String::new();
                 }
                 ('H', true) => {
-                    if !time {
-                        return Err(Iso8601Error::InvalidTokenPeriod(
-                            char,
-                            str_sequence.to_string(),
-                        ));
-                    }
                     milliseconds += temp.parse::<f64>().map_err(|_| {
                         Iso8601Error::InvalidNumberFormat("hours".into(), str_sequence.to_string())
                     })? * HOUR_AS_MS as f64;
                     temp = String::new();
                 }
+                ('M', false) => {
+                    return Err(Iso8601Error::NotImplemented(
+                        "Month (M)".into(),
+                        str_sequence.to_string(),
+                    ));
+                }
                 ('M', true) => {
-                    if !time {
-                        return Err(Iso8601Error::NotImplemented(
-                            "Month (M)".into(),
-                            str_sequence.to_string(),
-                        ));
-                    }
                     milliseconds += temp.parse::<f64>().map_err(|_| {
                         Iso8601Error::InvalidNumberFormat(
                             "minutes".into(),
@@ -203,6 +177,9 @@ impl Iso8601 {
         }
 
         if duration <= 0 {
+            if week == 0 && days == 0 {
+                iso_duration.push_str("T0S");
+            }
             return iso_duration;
         }
 
@@ -219,16 +196,24 @@ impl Iso8601 {
             iso_duration.push_str(&minutes.to_string());
             iso_duration.push('M');
         }
-        let seconds = duration as f64 / SECONDS_AS_MS as f64;
-        if seconds > 0.0 {
-            let x: &[_] = &['0', '.'];
-            iso_duration.push_str(format!("{:.3}", &seconds).trim_matches(x));
+        if duration > 0 {
+            let seconds = format!(
+                "{}.{:03}",
+                duration / SECONDS_AS_MS,
+                duration % SECONDS_AS_MS
+            );
+            iso_duration.push_str(seconds.trim_end_matches('0').trim_end_matches('.'));
             iso_duration.push('S');
         }
         iso_duration
     }
 }
 
+/// `duration` as a `xs:duration`, to the millisecond.
+pub(crate) fn format(duration: Duration) -> String {
+    Iso8601::to_string(duration)
+}
+
 pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -252,7 +237,7 @@ where
     }
 
     Ok(Some(
-        Iso8601::parse_str(str_sequence).map_err(de::Error::custom)?,
+        Iso8601::parse_str(str_sequence).map_err(|error| super::rejected(NAME, error))?,
     ))
 }
 
@@ -306,6 +291,9 @@ mod tests {
             Iso8601::to_string(Duration::milliseconds(43564)),
             "PT43.564S"
         );
+        assert_eq!(Iso8601::to_string(Duration::seconds(10)), "PT10S");
+        assert_eq!(Iso8601::to_string(Duration::milliseconds(500)), "PT0.5S");
+        assert_eq!(Iso8601::to_string(Duration::zero()), "PT0S");
     }
 
     #[test]
@@ -385,6 +373,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_tell_months_from_minutes_by_zone() {
+        assert_eq!(
+            Iso8601::parse_str("P1DT2M").unwrap(),
+            Duration::milliseconds(86_520_000)
+        );
+        assert_eq!(
+            Iso8601::parse_str("P1M2D").unwrap_err().to_string(),
+            "Not Implemented ISO_8601 Duration: Month (M) > P1M2D"
+        );
+    }
+
+    #[test]
+    fn should_fail_to_parse_date_tokens_after_time_ones() {
+        assert_eq!(
+            Iso8601::parse_str("P1DT2H3D").unwrap_err().to_string(),
+            "Invalid ISO_8601 Duration: D is not available in Time zone > P1DT2H3D"
+        );
+    }
+
     #[test]
     fn should_fail_to_parse_invalid_token() {
         assert_eq!(
@@ -1,3 +1,37 @@
+use serde::de;
+use std::cell::RefCell;
+use std::fmt::Display;
+
 pub mod conditional_uint;
 pub mod duration_iso_8601;
 pub mod tags;
+pub mod text_element;
+
+thread_local! {
+    /// Last rejection of a custom deserializer and its reason: the generic error it builds
+    /// can't carry the name of the deserializer.
+    static REJECTION: RefCell<Option<(&'static str, String)>> = const { RefCell::new(None) };
+}
+
+/// Error of a custom deserializer, which records its name so the parser can tell which
+/// deserializer rejected the value, see [`rejected_by`].
+pub(crate) fn rejected<E: de::Error>(deserializer: &'static str, reason: impl Display) -> E {
+    let reason = reason.to_string();
+    REJECTION.with(|rejection| *rejection.borrow_mut() = Some((deserializer, reason.clone())));
+    E::custom(reason)
+}
+
+/// Takes the custom deserializer which raised the error with `message` through
+/// [`rejected`]. A rejection recorded for another error, e.g. one dropped by another
+/// format, is discarded.
+pub(crate) fn rejected_by(message: &str) -> Option<&'static str> {
+    REJECTION
+        .with(|rejection| rejection.borrow_mut().take())
+        .filter(|(_, reason)| reason == message)
+        .map(|(deserializer, _)| deserializer)
+}
+
+/// Attributes holding their default value are left out when writing a MPD.
+pub(crate) fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
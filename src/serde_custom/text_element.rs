@@ -0,0 +1,12 @@
+use serde::ser::{SerializeStruct, Serializer};
+
+/// Writes a text field as a child element holding the text, where a plain `String` would
+/// be written as an attribute.
+pub fn serialize<S>(text: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut element = serializer.serialize_struct("TextElement", 1)?;
+    element.serialize_field("$value", text)?;
+    element.end()
+}
@@ -0,0 +1,364 @@
+//! Expansion of the URL templates of a `SegmentTemplate` (ISO/IEC 23009-1 5.3.9.4.4).
+use std::fmt;
+use std::str::FromStr;
+
+/// Identifier which can be substituted in a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Identifier {
+    RepresentationID,
+    Number,
+    Time,
+    Bandwidth,
+    SubNumber,
+}
+
+impl Identifier {
+    fn from_name(name: &str) -> Option<Identifier> {
+        match name {
+            "RepresentationID" => Some(Identifier::RepresentationID),
+            "Number" => Some(Identifier::Number),
+            "Time" => Some(Identifier::Time),
+            "Bandwidth" => Some(Identifier::Bandwidth),
+            "SubNumber" => Some(Identifier::SubNumber),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Identifier::RepresentationID => "RepresentationID",
+            Identifier::Number => "Number",
+            Identifier::Time => "Time",
+            Identifier::Bandwidth => "Bandwidth",
+            Identifier::SubNumber => "SubNumber",
+        }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${}$", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A `$` opens an identifier which is never closed.
+    UnterminatedIdentifier(usize, String),
+    UnknownIdentifier(String, String),
+    /// The format tag is not of the form `%0<width>d`.
+    InvalidFormat(String, String),
+    /// `$RepresentationID$` can't be formatted.
+    FormatNotAllowed(Identifier, String),
+    /// The template uses an identifier no value was given for.
+    MissingValue(Identifier, String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnterminatedIdentifier(offset, template) => write!(
+                f,
+                "Invalid template: identifier at offset {} is not closed by $ > {}",
+                offset, template
+            ),
+            TemplateError::UnknownIdentifier(name, template) => write!(
+                f,
+                "Invalid template: ${}$ is not an identifier > {}",
+                name, template
+            ),
+            TemplateError::InvalidFormat(format, template) => write!(
+                f,
+                "Invalid template: {} is not a format tag (%0<width>d) > {}",
+                format, template
+            ),
+            TemplateError::FormatNotAllowed(identifier, template) => write!(
+                f,
+                "Invalid template: {} does not accept a format tag > {}",
+                identifier, template
+            ),
+            TemplateError::MissingValue(identifier, template) => write!(
+                f,
+                "Can't expand template: no value for {} > {}",
+                identifier, template
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Values substituted to the identifiers of a template.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateValues {
+    representation_id: Option<String>,
+    number: Option<u64>,
+    time: Option<u64>,
+    bandwidth: Option<u64>,
+    sub_number: Option<u64>,
+}
+
+impl TemplateValues {
+    /// Values known for every segment of a representation.
+    pub fn new(representation_id: &str, bandwidth: u64) -> Self {
+        TemplateValues {
+            representation_id: Some(representation_id.to_string()),
+            bandwidth: Some(bandwidth),
+            ..TemplateValues::default()
+        }
+    }
+
+    pub fn with_number(mut self, number: u64) -> Self {
+        self.number = Some(number);
+        self
+    }
+
+    pub fn with_time(mut self, time: u64) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn with_sub_number(mut self, sub_number: u64) -> Self {
+        self.sub_number = Some(sub_number);
+        self
+    }
+
+    fn number_of(&self, identifier: Identifier) -> Option<u64> {
+        match identifier {
+            Identifier::Number => self.number,
+            Identifier::Time => self.time,
+            Identifier::Bandwidth => self.bandwidth,
+            Identifier::SubNumber => self.sub_number,
+            Identifier::RepresentationID => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// Identifier and the minimal width of its value, padded with zeros.
+    Identifier(Identifier, usize),
+}
+
+/// Parsed template, such as `$RepresentationID$/segment_$Number%05d$.m4s`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn uses(&self, identifier: Identifier) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Identifier(used, _) if *used == identifier))
+    }
+
+    pub fn expand(&self, values: &TemplateValues) -> Result<String, TemplateError> {
+        let mut url = String::with_capacity(self.source.len());
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => url.push_str(text),
+                Part::Identifier(Identifier::RepresentationID, _) => {
+                    let id = values.representation_id.as_deref().ok_or_else(|| {
+                        TemplateError::MissingValue(
+                            Identifier::RepresentationID,
+                            self.source.clone(),
+                        )
+                    })?;
+                    url.push_str(id);
+                }
+                Part::Identifier(identifier, width) => {
+                    let value = values.number_of(*identifier).ok_or_else(|| {
+                        TemplateError::MissingValue(*identifier, self.source.clone())
+                    })?;
+                    url.push_str(&format!("{:0width$}", value, width = width));
+                }
+            }
+        }
+        Ok(url)
+    }
+}
+
+/// Widest `%0<width>d` format tag, larger ones would make huge URLs.
+const MAX_WIDTH: usize = 32;
+
+/// Width of a `%0<width>d` format tag.
+fn parse_width(format: &str) -> Option<usize> {
+    let width = format.strip_prefix("%0")?.strip_suffix('d')?;
+    match width {
+        "" => Some(1),
+        width if width.chars().all(|char| char.is_ascii_digit()) => {
+            width.parse().ok().filter(|width| *width <= MAX_WIDTH)
+        }
+        _ => None,
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(source: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = source;
+        while let Some(start) = rest.find('$') {
+            literal.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let end = after.find('$').ok_or_else(|| {
+                TemplateError::UnterminatedIdentifier(
+                    source.len() - rest.len() + start,
+                    source.to_string(),
+                )
+            })?;
+            let identifier = &after[..end];
+            rest = &after[end + 1..];
+            if identifier.is_empty() {
+                literal.push('$');
+                continue;
+            }
+
+            let (name, format) = match identifier.find('%') {
+                Some(index) => (&identifier[..index], Some(&identifier[index..])),
+                None => (identifier, None),
+            };
+            let identifier = Identifier::from_name(name).ok_or_else(|| {
+                TemplateError::UnknownIdentifier(identifier.to_string(), source.to_string())
+            })?;
+            let width = match (identifier, format) {
+                (_, None) => 1,
+                (Identifier::RepresentationID, Some(_)) => {
+                    return Err(TemplateError::FormatNotAllowed(
+                        identifier,
+                        source.to_string(),
+                    ))
+                }
+                (_, Some(format)) => parse_width(format).ok_or_else(|| {
+                    TemplateError::InvalidFormat(format.to_string(), source.to_string())
+                })?,
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Identifier(identifier, width));
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Template {
+            source: source.to_string(),
+            parts,
+        })
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(template: &str, values: &TemplateValues) -> Result<String, TemplateError> {
+        template.parse::<Template>()?.expand(values)
+    }
+
+    #[test]
+    fn should_expand_every_identifier() {
+        let values = TemplateValues::new("video-720p", 3150000)
+            .with_number(3059199)
+            .with_time(1516128710400)
+            .with_sub_number(2);
+        assert_eq!(
+            expand("$RepresentationID$/segment_$Number$.m4s", &values).unwrap(),
+            "video-720p/segment_3059199.m4s"
+        );
+        assert_eq!(
+            expand("$Bandwidth$/$Time$-$SubNumber$.m4s", &values).unwrap(),
+            "3150000/1516128710400-2.m4s"
+        );
+        assert_eq!(
+            expand("price$$/$$$Number$$$", &values).unwrap(),
+            "price$/$3059199$"
+        );
+        assert_eq!(expand("init.mp4", &values).unwrap(), "init.mp4");
+    }
+
+    #[test]
+    fn should_pad_formatted_values_with_zeros() {
+        let values = TemplateValues::new("audio", 128000)
+            .with_number(42)
+            .with_time(7);
+        assert_eq!(
+            expand("seg_$Number%05d$_$Time%01d$.m4s", &values).unwrap(),
+            "seg_00042_7.m4s"
+        );
+        assert_eq!(
+            expand("$Bandwidth%03d$/$Number%0d$", &values).unwrap(),
+            "128000/42"
+        );
+    }
+
+    #[test]
+    fn should_tell_which_identifiers_are_used() {
+        let template: Template = "$RepresentationID$/$Time$.m4s".parse().unwrap();
+        assert!(template.uses(Identifier::Time));
+        assert!(!template.uses(Identifier::Number));
+        assert_eq!(template.to_string(), "$RepresentationID$/$Time$.m4s");
+    }
+
+    #[test]
+    fn should_fail_to_parse_malformed_identifiers() {
+        assert_eq!(
+            "segment_$Number.m4s".parse::<Template>().unwrap_err(),
+            TemplateError::UnterminatedIdentifier(8, "segment_$Number.m4s".to_string())
+        );
+        assert_eq!(
+            "$Index$.m4s".parse::<Template>().unwrap_err().to_string(),
+            "Invalid template: $Index$ is not an identifier > $Index$.m4s"
+        );
+        assert_eq!(
+            "$Number%5d$".parse::<Template>().unwrap_err(),
+            TemplateError::InvalidFormat("%5d".to_string(), "$Number%5d$".to_string())
+        );
+        assert!(matches!(
+            "$Number%05x$".parse::<Template>(),
+            Err(TemplateError::InvalidFormat(_, _))
+        ));
+        assert!("$Number%032d$".parse::<Template>().is_ok());
+        assert_eq!(
+            "$Number%033d$".parse::<Template>().unwrap_err(),
+            TemplateError::InvalidFormat("%033d".to_string(), "$Number%033d$".to_string())
+        );
+        assert!(matches!(
+            "$Time%099999999999d$".parse::<Template>(),
+            Err(TemplateError::InvalidFormat(_, _))
+        ));
+        assert_eq!(
+            "$RepresentationID%02d$".parse::<Template>().unwrap_err(),
+            TemplateError::FormatNotAllowed(
+                Identifier::RepresentationID,
+                "$RepresentationID%02d$".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn should_fail_to_expand_without_value() {
+        let values = TemplateValues::new("video", 800000);
+        assert_eq!(
+            expand("$Number$.m4s", &values).unwrap_err().to_string(),
+            "Can't expand template: no value for $Number$ > $Number$.m4s"
+        );
+    }
+}
@@ -0,0 +1,583 @@
+//! Cross-check of the attributes of the `Representation`s of a MPD with their
+//! initialization segments.
+use std::fmt;
+
+use crate::archive::range;
+use crate::base_url::{BaseURLError, ByteRange, ResolvedBaseURL, SegmentLocation};
+use crate::download::{DownloadError, SegmentDownloader};
+use crate::fetch::{FetchError, MPDFetcher};
+use crate::models::representations::{AdaptationSet, Representation};
+use crate::models::segments::SegmentInformation;
+use crate::models::utils::Descriptor;
+use crate::mp4::{self, FourCC, Movie, Mp4Box, Mp4Error};
+use crate::template::{TemplateError, TemplateValues};
+use crate::xlink::{XLinkError, XLinkResolver};
+
+#[derive(Debug)]
+pub enum ValidationError {
+    // Boxed, they are much larger than the other variants.
+    Fetch(Box<FetchError>),
+    XLink(Box<XLinkError>),
+    BaseURL(BaseURLError),
+    Template(TemplateError),
+    /// The `Representation` with this id has no initialization segment to check.
+    NoInitialization(String),
+    Download(DownloadError),
+    Mp4(Mp4Error),
+    /// The initialization segment of the `Representation` with this id has no `moov`
+    /// box.
+    NoMovie(String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Fetch(error) => error.fmt(f),
+            ValidationError::XLink(error) => error.fmt(f),
+            ValidationError::BaseURL(error) => error.fmt(f),
+            ValidationError::Template(error) => error.fmt(f),
+            ValidationError::NoInitialization(id) => {
+                write!(f, "No initialization segment for Representation {}", id)
+            }
+            ValidationError::Download(error) => error.fmt(f),
+            ValidationError::Mp4(error) => write!(f, "Invalid initialization segment: {}", error),
+            ValidationError::NoMovie(id) => write!(
+                f,
+                "No moov box in the initialization segment of Representation {}",
+                id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ValidationError::Fetch(error) => Some(error.as_ref()),
+            ValidationError::XLink(error) => Some(error.as_ref()),
+            ValidationError::BaseURL(error) => Some(error),
+            ValidationError::Template(error) => Some(error),
+            ValidationError::Download(error) => Some(error),
+            ValidationError::Mp4(error) => Some(error),
+            ValidationError::NoInitialization(_) | ValidationError::NoMovie(_) => None,
+        }
+    }
+}
+
+impl From<BaseURLError> for ValidationError {
+    fn from(error: BaseURLError) -> Self {
+        ValidationError::BaseURL(error)
+    }
+}
+
+impl From<TemplateError> for ValidationError {
+    fn from(error: TemplateError) -> Self {
+        ValidationError::Template(error)
+    }
+}
+
+/// Attribute of a `Representation`, inherited from its `AdaptationSet` or not, which
+/// its initialization segment contradicts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// `codecs`, and the formats of the sample entries, before encryption.
+    Codecs {
+        manifest: Vec<String>,
+        media: Vec<FourCC>,
+    },
+    /// `width` and `height`, and the coded size of the visual sample entry.
+    Dimensions {
+        manifest: (u64, u64),
+        media: (u16, u16),
+    },
+    /// `audioSamplingRate`, and the sample rate of the audio sample entry.
+    SamplingRate { manifest: u64, media: u32 },
+    /// Channels of the `AudioChannelConfiguration`, and of the audio sample entry.
+    ChannelCount { manifest: u32, media: u16 },
+    /// `timescale` of the segment information, and the timescales of the `mdhd` boxes.
+    Timescale { manifest: u64, media: Vec<u32> },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Codecs { manifest, media } => {
+                let media: Vec<_> = media.iter().map(FourCC::to_string).collect();
+                write!(
+                    f,
+                    "codecs {} but sample entries {}",
+                    manifest.join(","),
+                    media.join(",")
+                )
+            }
+            Mismatch::Dimensions { manifest, media } => write!(
+                f,
+                "{}x{} but {}x{} in the sample entry",
+                manifest.0, manifest.1, media.0, media.1
+            ),
+            Mismatch::SamplingRate { manifest, media } => write!(
+                f,
+                "audioSamplingRate {} but {} in the sample entry",
+                manifest, media
+            ),
+            Mismatch::ChannelCount { manifest, media } => {
+                write!(f, "{} channels but {} in the sample entry", manifest, media)
+            }
+            Mismatch::Timescale { manifest, media } => {
+                let media: Vec<_> = media.iter().map(u32::to_string).collect();
+                write!(f, "timescale {} but {} in mdhd", manifest, media.join(","))
+            }
+        }
+    }
+}
+
+/// Outcome of the check of one `Representation`.
+#[derive(Debug)]
+pub struct RepresentationCheck {
+    period: usize,
+    representation_id: String,
+    mismatches: Vec<Mismatch>,
+    error: Option<ValidationError>,
+}
+
+impl RepresentationCheck {
+    /// Index of the period of the `Representation`.
+    pub fn period(&self) -> usize {
+        self.period
+    }
+
+    pub fn representation_id(&self) -> &str {
+        &self.representation_id
+    }
+
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    /// Why the `Representation` couldn't be checked.
+    pub fn error(&self) -> Option<&ValidationError> {
+        self.error.as_ref()
+    }
+
+    /// Whether the `Representation` was checked and matches its initialization segment.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.mismatches.is_empty()
+    }
+}
+
+/// Downloads the initialization segment of each ISO BMFF `Representation` of a MPD and
+/// compares it with the attributes of the `Representation`: `codecs` with the sample
+/// entries, `width` and `height`, `audioSamplingRate`, the `AudioChannelConfiguration`
+/// and the `timescale` of the segment information with the `mdhd` boxes.
+///
+/// Attributes missing from the MPD aren't checked.
+#[derive(Debug, Clone, Default)]
+pub struct MediaValidator {
+    fetcher: MPDFetcher,
+    downloader: SegmentDownloader,
+    resolver: XLinkResolver,
+}
+
+impl MediaValidator {
+    pub fn new() -> Self {
+        MediaValidator::default()
+    }
+
+    pub fn with_fetcher(mut self, fetcher: MPDFetcher) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    pub fn with_downloader(mut self, downloader: SegmentDownloader) -> Self {
+        self.downloader = downloader;
+        self
+    }
+
+    pub fn with_xlink_resolver(mut self, resolver: XLinkResolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Checks the `Representation`s of the MPD at `url`, in document order. Those whose
+    /// `mimeType` isn't of ISO BMFF segments are skipped.
+    pub async fn validate(&self, url: &str) -> Result<Vec<RepresentationCheck>, ValidationError> {
+        let fetched = self
+            .fetcher
+            .fetch(url)
+            .await
+            .map_err(|error| ValidationError::Fetch(Box::new(error)))?;
+        let mpd = self
+            .resolver
+            .resolve(fetched.mpd(), fetched.url())
+            .await
+            .map_err(|error| ValidationError::XLink(Box::new(error)))?;
+
+        let mut checks = Vec::new();
+        let mut pending = Vec::new();
+        for (period_index, period) in mpd.periods().iter().enumerate() {
+            for adaptation in period.adaptations() {
+                for representation in adaptation.representations() {
+                    let mime_type = inherit(representation.mime_type(), adaptation.mime_type());
+                    if !mime_type.is_empty() && !mime_type.ends_with("/mp4") {
+                        continue;
+                    }
+                    let information = period.segment_information(adaptation, representation);
+                    let location = mpd
+                        .resolve_base_urls(fetched.url(), period, adaptation, representation)
+                        .map_err(ValidationError::from)
+                        .and_then(|mut bases| {
+                            initialization(&bases.swap_remove(0), representation, &information)
+                        });
+                    let mut check = RepresentationCheck {
+                        period: period_index,
+                        representation_id: representation.id().to_string(),
+                        mismatches: Vec::new(),
+                        error: None,
+                    };
+                    match location {
+                        Ok(location) => pending.push((
+                            checks.len(),
+                            location,
+                            Expected::new(adaptation, representation, information.as_ref()),
+                        )),
+                        Err(error) => check.error = Some(error),
+                    }
+                    checks.push(check);
+                }
+            }
+        }
+
+        let locations: Vec<_> = pending
+            .iter()
+            .map(|(_, location, _)| location.clone())
+            .collect();
+        let downloads = self.downloader.download_all(&locations).await;
+        for ((index, _, expected), download) in pending.into_iter().zip(downloads) {
+            let check = &mut checks[index];
+            let movie = download
+                .map_err(ValidationError::Download)
+                .and_then(|data| movie(&data, &check.representation_id));
+            match movie {
+                Ok(movie) => check.mismatches = expected.compare(&movie),
+                Err(error) => check.error = Some(error),
+            }
+        }
+        Ok(checks)
+    }
+}
+
+/// What the MPD says of a `Representation`.
+struct Expected {
+    codecs: Vec<String>,
+    dimensions: Option<(u64, u64)>,
+    sampling_rate: Option<u64>,
+    channel_count: Option<u32>,
+    timescale: Option<u64>,
+}
+
+impl Expected {
+    fn new(
+        adaptation: &AdaptationSet,
+        representation: &Representation,
+        information: Option<&SegmentInformation>,
+    ) -> Self {
+        let codecs = match representation.codecs() {
+            [] => adaptation.codecs(),
+            codecs => codecs,
+        };
+        let width = nonzero(representation.width()).or(nonzero(adaptation.width()));
+        let height = nonzero(representation.height()).or(nonzero(adaptation.height()));
+        // With a `SegmentBase` the times are given by the `sidx` boxes, and without a
+        // `timescale` the MPD says nothing to compare with.
+        let timescale = match information {
+            Some(SegmentInformation::List(_)) | Some(SegmentInformation::Template(_)) => {
+                information.and_then(SegmentInformation::declared_timescale)
+            }
+            _ => None,
+        };
+        Expected {
+            codecs: codecs.to_vec(),
+            dimensions: width.zip(height),
+            sampling_rate: inherit(
+                representation.audio_sampling_rate(),
+                adaptation.audio_sampling_rate(),
+            )
+            .split_whitespace()
+            .next()
+            .and_then(|rate| rate.parse().ok()),
+            channel_count: channel_count(representation.audio_channel_configuration())
+                .or_else(|| channel_count(adaptation.audio_channel_configuration())),
+            timescale,
+        }
+    }
+
+    fn compare(&self, movie: &Movie) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+        let entries: Vec<_> = movie
+            .tracks()
+            .iter()
+            .flat_map(|track| {
+                track
+                    .sample_entries()
+                    .iter()
+                    .map(move |entry| (track, entry))
+            })
+            .collect();
+
+        if !self.codecs.is_empty() {
+            let declared: Vec<_> = self
+                .codecs
+                .iter()
+                .map(|codec| codec.split('.').next().unwrap_or_default().trim())
+                .collect();
+            let media: Vec<_> = entries
+                .iter()
+                .map(|(_, entry)| entry.original_format())
+                .collect();
+            let matches = |format: &FourCC| declared.iter().any(|codec| *format == *codec);
+            let listed = declared.iter().all(|codec| {
+                entries
+                    .iter()
+                    .any(|(_, entry)| entry.original_format() == *codec || entry.format() == *codec)
+            });
+            if !listed || !media.iter().all(matches) {
+                mismatches.push(Mismatch::Codecs {
+                    manifest: self.codecs.clone(),
+                    media,
+                });
+            }
+        }
+
+        for (track, entry) in &entries {
+            if let (Some(manifest), Some(media)) = (self.dimensions, entry.dimensions()) {
+                let coded = (u64::from(media.0), u64::from(media.1));
+                let presentation = (
+                    track.header().width().round() as u64,
+                    track.header().height().round() as u64,
+                );
+                if manifest != coded && manifest != presentation {
+                    mismatches.push(Mismatch::Dimensions { manifest, media });
+                }
+            }
+            if let (Some(manifest), Some(media)) = (self.sampling_rate, entry.sample_rate()) {
+                if manifest != u64::from(media) {
+                    mismatches.push(Mismatch::SamplingRate { manifest, media });
+                }
+            }
+            // The channel count of the AC-3, E-AC-3 and AC-4 sample entries is always 2.
+            let format = entry.original_format();
+            let fixed_channels = format == "ac-3" || format == "ec-3" || format == "ac-4";
+            if let (Some(manifest), Some(media)) = (self.channel_count, entry.channel_count()) {
+                if !fixed_channels && manifest != u32::from(media) {
+                    mismatches.push(Mismatch::ChannelCount { manifest, media });
+                }
+            }
+        }
+
+        if let Some(manifest) = self.timescale {
+            let media: Vec<_> = movie
+                .tracks()
+                .iter()
+                .map(|track| track.media_header().timescale())
+                .collect();
+            if !media.is_empty() && !media.iter().any(|&media| u64::from(media) == manifest) {
+                mismatches.push(Mismatch::Timescale { manifest, media });
+            }
+        }
+        mismatches
+    }
+}
+
+fn inherit<'a>(value: &'a str, parent: &'a str) -> &'a str {
+    if value.is_empty() {
+        parent
+    } else {
+        value
+    }
+}
+
+fn nonzero(value: u64) -> Option<u64> {
+    (value != 0).then_some(value)
+}
+
+/// Channels of the first `AudioChannelConfiguration` of a known scheme.
+fn channel_count(descriptors: &[Descriptor]) -> Option<u32> {
+    // ChannelConfiguration of ISO/IEC 23091-3, 0 where reserved.
+    const CICP: [u32; 21] = [
+        0, 1, 2, 3, 4, 5, 6, 8, 0, 3, 4, 7, 8, 24, 8, 12, 10, 12, 14, 12, 14,
+    ];
+    descriptors
+        .iter()
+        .find_map(|descriptor| match descriptor.scheme_id_uri() {
+            "urn:mpeg:dash:23003:3:audio_channel_configuration:2011" => {
+                descriptor.value().trim().parse().ok()
+            }
+            "urn:mpeg:mpegB:cicp:ChannelConfiguration" => descriptor
+                .value()
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| CICP.get(index).copied())
+                .filter(|&count| count > 0),
+            _ => None,
+        })
+}
+
+/// Location of the initialization segment of a `Representation`. Without
+/// `Initialization`, that of a `SegmentBase` is taken to be the bytes before its index.
+fn initialization(
+    base: &ResolvedBaseURL,
+    representation: &Representation,
+    information: &Option<SegmentInformation>,
+) -> Result<SegmentLocation, ValidationError> {
+    let missing = || ValidationError::NoInitialization(representation.id().to_string());
+    let information = information.as_ref().ok_or_else(missing)?;
+    if let Some(template) = information.template() {
+        if !template.initialization_url().is_empty() {
+            let values = TemplateValues::new(representation.id(), representation.bandwidth());
+            return Ok(base.locate(&template.expand_initialization(&values)?, None)?);
+        }
+    }
+    if let Some(initialization) = information.initialization() {
+        return Ok(base.locate(initialization.source_url(), range(initialization.range())?)?);
+    }
+    match information.base().map(|base| range(base.index_range())) {
+        Some(Ok(Some(index))) if index.first() > 0 => {
+            Ok(base.locate("", Some(ByteRange::new(0, Some(index.first() - 1))))?)
+        }
+        Some(Err(error)) => Err(error.into()),
+        _ => Err(missing()),
+    }
+}
+
+/// `moov` box of an initialization segment.
+fn movie(data: &[u8], representation_id: &str) -> Result<Movie, ValidationError> {
+    mp4::parse(data)
+        .map_err(ValidationError::Mp4)?
+        .into_iter()
+        .find_map(|parsed| match parsed {
+            Mp4Box::Moov(movie) => Some(movie),
+            _ => None,
+        })
+        .ok_or_else(|| ValidationError::NoMovie(representation_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mp4::init_tests::{audio_entry, track, visual_entry};
+    use crate::mp4::tests::{boxed, ftyp};
+    use crate::test_server::{serve, Response};
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" type="static" profiles="urn:mpeg:dash:profile:isoff-live:2011" mediaPresentationDuration="PT8S" minBufferTime="PT2S">
+  <Period id="main">
+    <AdaptationSet mimeType="video/mp4" codecs="avc1.64001f">
+      <SegmentTemplate media="$RepresentationID$/$Number$.m4s" initialization="$RepresentationID$/init.mp4" duration="360000" timescale="90000"/>
+      <Representation id="good" bandwidth="1000000" width="1280" height="720"/>
+      <Representation id="bad" bandwidth="2000000" width="1920" height="1080" codecs="hvc1.1.6.L93.B0"/>
+      <Representation id="missing" bandwidth="3000000" width="1280" height="720"/>
+    </AdaptationSet>
+    <AdaptationSet mimeType="audio/mp4" codecs="mp4a.40.2" audioSamplingRate="44100">
+      <AudioChannelConfiguration schemeIdUri="urn:mpeg:mpegB:cicp:ChannelConfiguration" value="6"/>
+      <Representation id="audio" bandwidth="64000">
+        <SegmentTemplate media="audio/$Number$.m4s" initialization="audio/init.mp4" duration="4" timescale="1"/>
+      </Representation>
+    </AdaptationSet>
+    <AdaptationSet mimeType="text/vtt">
+      <Representation id="subtitles" bandwidth="1000"><BaseURL>subtitles.vtt</BaseURL></Representation>
+    </AdaptationSet>
+  </Period>
+</MPD>"#;
+
+    fn initialization(trak: Vec<u8>) -> Vec<u8> {
+        let mut data = ftyp(b"ftyp");
+        data.extend(boxed(b"moov", &trak));
+        data
+    }
+
+    #[tokio::test]
+    async fn should_report_mismatches_per_representation() {
+        let video = initialization(track(b"vide", boxed(b"avc1", &visual_entry(1280, 720))));
+        let audio = initialization(track(b"soun", boxed(b"mp4a", &audio_entry(2, 48000))));
+        let (root, _) = serve(move |request| match request.path.as_str() {
+            "/manifest.mpd" => Response::ok(MANIFEST),
+            "/good/init.mp4" | "/bad/init.mp4" => Response::ok(video.clone()),
+            "/audio/init.mp4" => Response::ok(audio.clone()),
+            _ => Response::not_found(),
+        })
+        .await;
+
+        let checks = MediaValidator::new()
+            .with_downloader(SegmentDownloader::new().with_max_retries(0))
+            .validate(root.join("manifest.mpd").unwrap().as_str())
+            .await
+            .unwrap();
+
+        let ids: Vec<_> = checks
+            .iter()
+            .map(RepresentationCheck::representation_id)
+            .collect();
+        assert_eq!(ids, ["good", "bad", "missing", "audio"]);
+        assert!(checks[0].is_valid());
+        assert_eq!(
+            checks[1].mismatches(),
+            [
+                Mismatch::Codecs {
+                    manifest: vec!["hvc1.1.6.L93.B0".to_string()],
+                    media: vec![FourCC::new(b"avc1")],
+                },
+                Mismatch::Dimensions {
+                    manifest: (1920, 1080),
+                    media: (1280, 720),
+                },
+            ]
+        );
+        assert!(matches!(
+            checks[2].error(),
+            Some(ValidationError::Download(_))
+        ));
+        assert_eq!(
+            checks[3].mismatches(),
+            [
+                Mismatch::SamplingRate {
+                    manifest: 44100,
+                    media: 48000,
+                },
+                Mismatch::ChannelCount {
+                    manifest: 6,
+                    media: 2,
+                },
+                Mismatch::Timescale {
+                    manifest: 1,
+                    media: vec![90000],
+                },
+            ]
+        );
+        assert_eq!(
+            checks[3].mismatches()[0].to_string(),
+            "audioSamplingRate 44100 but 48000 in the sample entry"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_compare_timescale_missing_from_the_manifest() {
+        let manifest = MANIFEST.replace(r#" timescale="1""#, "");
+        let audio = initialization(track(b"soun", boxed(b"mp4a", &audio_entry(6, 44100))));
+        let (root, _) = serve(move |request| match request.path.as_str() {
+            "/manifest.mpd" => Response::ok(manifest.clone()),
+            "/audio/init.mp4" => Response::ok(audio.clone()),
+            _ => Response::not_found(),
+        })
+        .await;
+
+        let checks = MediaValidator::new()
+            .with_downloader(SegmentDownloader::new().with_max_retries(0))
+            .validate(root.join("manifest.mpd").unwrap().as_str())
+            .await
+            .unwrap();
+
+        assert_eq!(checks[3].representation_id(), "audio");
+        assert!(checks[3].is_valid());
+    }
+}
@@ -0,0 +1,499 @@
+//! Refresh of dynamic MPDs (ISO/IEC 23009-1 5.4).
+use chrono::{DateTime, Local, Utc};
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant};
+use url::Url;
+
+use crate::fetch::{FetchError, FetchedMPD, MPDFetcher};
+use crate::models::mpd::MPD;
+use crate::models::segments::{MediaTime, TimelineSegment};
+use crate::period_timeline::PeriodTimeline;
+
+/// Changes buffered by [`MPDWatcher::watch`] until they are received.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Difference between two successive versions of a MPD.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MPDChange {
+    /// `publishTime` differs from the previous version, or appears in the first one.
+    PublishTime {
+        previous: Option<DateTime<Local>>,
+        current: Option<DateTime<Local>>,
+    },
+    /// Period whose id wasn't in the previous version.
+    NewPeriod { index: usize, id: String },
+    /// Segments listed after the last one of the previous version of the
+    /// `Representation`, by a `SegmentTimeline` or a `SegmentList`.
+    NewSegments {
+        period_id: String,
+        representation_id: String,
+        segments: Vec<TimelineSegment>,
+    },
+    /// The MPD advertised a new `Location`, fetched from then on.
+    Location(Url),
+}
+
+/// Follows a MPD: fetches it again every `minimumUpdatePeriod` while it is dynamic,
+/// from its `Location` when it gives one, or patches it from its `PatchLocation`.
+#[derive(Debug)]
+pub struct MPDWatcher {
+    fetcher: MPDFetcher,
+    url: String,
+    current: Option<FetchedMPD>,
+    next_fetch: Option<Instant>,
+    retry_delay: std::time::Duration,
+    ended: bool,
+}
+
+impl MPDWatcher {
+    pub fn new(url: &str) -> Self {
+        MPDWatcher {
+            fetcher: MPDFetcher::new(),
+            url: url.to_string(),
+            current: None,
+            next_fetch: None,
+            retry_delay: std::time::Duration::from_secs(1),
+            ended: false,
+        }
+    }
+
+    pub fn with_fetcher(mut self, fetcher: MPDFetcher) -> Self {
+        self.fetcher = fetcher;
+        self
+    }
+
+    /// Delay before fetching again after a failure, and between two fetches when
+    /// `minimumUpdatePeriod` is shorter.
+    pub fn with_retry_delay(mut self, delay: std::time::Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// URL of the next fetch.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Last version fetched.
+    pub fn mpd(&self) -> Option<&FetchedMPD> {
+        self.current.as_ref()
+    }
+
+    /// Waits for the next refresh and returns what changed since the previous version,
+    /// everything being new in the first one. `None` once the MPD is no longer to be
+    /// refreshed: it turned static or lost its `minimumUpdatePeriod`.
+    pub async fn next(&mut self) -> Option<Result<Vec<MPDChange>, FetchError>> {
+        if self.ended {
+            return None;
+        }
+        if let Some(next_fetch) = self.next_fetch {
+            time::sleep_until(next_fetch).await;
+        }
+        let fetched_at = Instant::now();
+        let result = match &self.current {
+            Some(current) => self.fetcher.refresh(&self.url, current).await,
+            None => self.fetcher.fetch(&self.url).await,
+        };
+        let fetched = match result {
+            Ok(fetched) => fetched,
+            Err(error) => {
+                self.next_fetch = Some(fetched_at + self.retry_delay);
+                return Some(Err(error));
+            }
+        };
+
+        let mut changes = diff(
+            self.current.as_ref().map(FetchedMPD::mpd),
+            fetched.mpd(),
+            Utc::now(),
+        );
+        let location = fetched
+            .mpd()
+            .locations()
+            .first()
+            .and_then(|location| fetched.url().join(location.url().trim()).ok());
+        if let Some(location) = location.filter(|location| location.as_str() != self.url) {
+            self.url = location.to_string();
+            changes.push(MPDChange::Location(location));
+        }
+        match fetched.mpd().refresh_period() {
+            Some(period) => {
+                let period = period.to_std().unwrap_or_default().max(self.retry_delay);
+                self.next_fetch = Some(fetched_at + period);
+            }
+            None => self.ended = true,
+        }
+        self.current = Some(fetched);
+        Some(Ok(changes))
+    }
+
+    /// Refreshes the MPD in a new task until it ends or the receiver is dropped.
+    pub fn watch(mut self) -> mpsc::Receiver<Result<MPDChange, FetchError>> {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        tokio::spawn(async move {
+            while let Some(result) = self.next().await {
+                let sent = match result {
+                    Ok(changes) => {
+                        let mut sent = true;
+                        for change in changes {
+                            sent = sent && sender.send(Ok(change)).await.is_ok();
+                        }
+                        sent
+                    }
+                    Err(error) => sender.send(Err(error)).await.is_ok(),
+                };
+                if !sent {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+}
+
+/// Changes from `previous` to `current`, the segments of open-ended timelines being
+/// listed up to the live edge at `now`.
+fn diff(previous: Option<&MPD>, current: &MPD, now: DateTime<Utc>) -> Vec<MPDChange> {
+    let mut changes = Vec::new();
+    if previous.is_none_or(|previous| previous.publish_time() != current.publish_time()) {
+        changes.push(MPDChange::PublishTime {
+            previous: previous.and_then(MPD::publish_time),
+            current: current.publish_time(),
+        });
+    }
+
+    let timeline = current.period_timeline();
+    let previous = previous.map(|previous| (previous, previous.period_timeline()));
+    for (index, period) in current.periods().iter().enumerate() {
+        let previous_period = previous.as_ref().and_then(|(previous, timeline)| {
+            previous
+                .periods()
+                .iter()
+                .position(|previous| previous.id() == period.id())
+                .map(|index| (*previous, timeline, index))
+        });
+        if previous_period.is_none() {
+            changes.push(MPDChange::NewPeriod {
+                index,
+                id: period.id().to_string(),
+            });
+        }
+        for (adaptation_index, adaptation) in period.adaptations().iter().enumerate() {
+            for (representation_index, representation) in
+                adaptation.representations().iter().enumerate()
+            {
+                let last = previous_period
+                    .and_then(|(previous_mpd, previous_timeline, previous_index)| {
+                        previous_mpd.periods()[previous_index]
+                            .adaptations()
+                            .iter()
+                            .enumerate()
+                            .find_map(|(adaptation_index, adaptation)| {
+                                adaptation
+                                    .representations()
+                                    .iter()
+                                    .position(|previous| previous.id() == representation.id())
+                                    .map(|index| {
+                                        listed_segments(
+                                            previous_mpd,
+                                            previous_timeline,
+                                            (previous_index, adaptation_index, index),
+                                            now,
+                                        )
+                                    })
+                            })
+                    })
+                    .and_then(|segments| segments.last().map(TimelineSegment::time));
+                let position = (index, adaptation_index, representation_index);
+                let segments: Vec<_> = listed_segments(current, &timeline, position, now)
+                    .into_iter()
+                    .filter(|segment| last.is_none_or(|last| segment.time() > last))
+                    .collect();
+                if !segments.is_empty() {
+                    changes.push(MPDChange::NewSegments {
+                        period_id: period.id().to_string(),
+                        representation_id: representation.id().to_string(),
+                        segments,
+                    });
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// Segments listed in the MPD for the `Representation` at `position`, the indices of
+/// its period, adaptation set and itself, `timeline` being the period timeline of `mpd`.
+/// An open-ended repeat of the last `S` of a timeline lasts until the live edge at
+/// `now`, or counts once when it is unknown.
+fn listed_segments(
+    mpd: &MPD,
+    timeline: &PeriodTimeline,
+    position: (usize, usize, usize),
+    now: DateTime<Utc>,
+) -> Vec<TimelineSegment> {
+    let (period_index, adaptation_index, representation_index) = position;
+    let period = &mpd.periods()[period_index];
+    let adaptation = &period.adaptations()[adaptation_index];
+    let representation = &adaptation.representations()[representation_index];
+    let information = match period.segment_information(adaptation, representation) {
+        Some(information) => information,
+        None => return Vec::new(),
+    };
+    let timescale = information.timescale();
+    let start_number = information.start_number();
+    let presentation_time_offset = information.presentation_time_offset();
+    // Rounded up: a segment starting within the last partial tick starts before the end.
+    let end = mpd
+        .period_duration_in(timeline, period_index)
+        .map(|duration| {
+            let end = MediaTime::from_duration(duration, timescale);
+            (end.ticks() + i64::from(end.to_duration() < duration)).max(0) as u64
+        });
+
+    match (information.segment_timeline(), information.list()) {
+        (Some(timeline), _) => {
+            let mut segments =
+                timeline.iter(timescale, start_number, presentation_time_offset, end);
+            let mut listed = Vec::new();
+            while let Some(segment) = segments.next() {
+                listed.push(segment);
+                if segments.is_open_ended() {
+                    // The next segments are implied by the clock.
+                    let live_edge = mpd
+                        .availability_window(
+                            period_index,
+                            adaptation_index,
+                            representation_index,
+                            now,
+                        )
+                        .map(|window| window.latest().end().ticks());
+                    listed.extend(segments.take_while(|segment| {
+                        live_edge.is_some_and(|live_edge| segment.end() <= live_edge)
+                    }));
+                    break;
+                }
+            }
+            listed
+        }
+        (None, Some(list)) if information.duration() > 0 => {
+            let duration = information.duration();
+            (0..list.segment_url().len() as u64)
+                .map(|index| {
+                    TimelineSegment::new(
+                        start_number + index,
+                        presentation_time_offset + index * duration,
+                        duration,
+                        timescale,
+                        presentation_time_offset,
+                    )
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn manifest(attributes: &str, publish_time: &str, periods: &str) -> String {
+        format!(
+            r#"<MPD profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z" publishTime="{}" {}>{}</MPD>"#,
+            publish_time, attributes, periods
+        )
+    }
+
+    fn period(id: &str, start: u32, timeline: &str) -> String {
+        format!(
+            r#"<Period id="{}" start="PT{}S"><AdaptationSet><SegmentTemplate timescale="10" media="$Time$.m4s"><SegmentTimeline>{}</SegmentTimeline></SegmentTemplate><Representation id="video" bandwidth="1"/></AdaptationSet></Period>"#,
+            id, start, timeline
+        )
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        format!("2024-01-01T{}Z", time).parse().unwrap()
+    }
+
+    fn times(change: &MPDChange) -> Vec<u64> {
+        match change {
+            MPDChange::NewSegments { segments, .. } => {
+                segments.iter().map(TimelineSegment::time).collect()
+            }
+            change => panic!("Unexpected change {:?}", change),
+        }
+    }
+
+    #[test]
+    fn should_list_new_segments_and_periods() {
+        let before: MPD = manifest(
+            r#"type="dynamic""#,
+            "2024-01-01T00:00:10Z",
+            &period("1", 0, r#"<S t="0" d="20" r="1"/>"#),
+        )
+        .parse()
+        .unwrap();
+        let after: MPD = manifest(
+            r#"type="dynamic""#,
+            "2024-01-01T00:00:12Z",
+            &(period("1", 0, r#"<S t="20" d="20" r="1"/>"#)
+                + &period("2", 10, r#"<S t="0" d="10"/>"#)),
+        )
+        .parse()
+        .unwrap();
+
+        let first = diff(None, &before, at("00:00:30"));
+        assert_eq!(first.len(), 3);
+        assert!(matches!(
+            first[0],
+            MPDChange::PublishTime {
+                previous: None,
+                current: Some(_)
+            }
+        ));
+        assert_eq!(
+            first[1],
+            MPDChange::NewPeriod {
+                index: 0,
+                id: "1".to_string()
+            }
+        );
+        assert_eq!(times(&first[2]), vec![0, 20]);
+
+        let changes = diff(Some(&before), &after, at("00:00:30"));
+        assert_eq!(changes.len(), 4);
+        assert_eq!(
+            changes[0],
+            MPDChange::PublishTime {
+                previous: before.publish_time(),
+                current: after.publish_time(),
+            }
+        );
+        assert_eq!(times(&changes[1]), vec![40]);
+        assert_eq!(
+            changes[2],
+            MPDChange::NewPeriod {
+                index: 1,
+                id: "2".to_string()
+            }
+        );
+        assert_eq!(times(&changes[3]), vec![0]);
+        assert!(diff(Some(&after), &after, at("00:00:30")).is_empty());
+    }
+
+    #[test]
+    fn should_list_open_ended_repeat_up_to_the_live_edge() {
+        let mpd: MPD = manifest(
+            r#"type="dynamic""#,
+            "2024-01-01T00:00:10Z",
+            &period("1", 0, r#"<S t="0" d="20" r="1"/><S d="10" r="-1"/>"#),
+        )
+        .parse()
+        .unwrap();
+        let changes = diff(None, &mpd, at("00:00:07"));
+        assert_eq!(times(&changes[2]), vec![0, 20, 40, 50, 60]);
+        // The first segment of the `S` is listed before it is available.
+        let changes = diff(None, &mpd, at("00:00:04.5"));
+        assert_eq!(times(&changes[2]), vec![0, 20, 40]);
+    }
+
+    #[test]
+    fn should_repeat_until_the_next_explicit_time() {
+        let mpd: MPD = manifest(
+            r#"type="dynamic""#,
+            "2024-01-01T00:00:10Z",
+            &period("1", 0, r#"<S t="0" d="20" r="-1"/><S t="100" d="20"/>"#),
+        )
+        .parse()
+        .unwrap();
+        let changes = diff(None, &mpd, at("00:00:30"));
+        assert_eq!(times(&changes[2]), vec![0, 20, 40, 60, 80, 100]);
+    }
+
+    #[tokio::test]
+    async fn should_refresh_until_static_following_location() {
+        let served = AtomicUsize::new(0);
+        let (root, requests) = serve(move |request| {
+            let body = match request.path.as_str() {
+                "/live.mpd" if served.fetch_add(1, Ordering::SeqCst) == 0 => manifest(
+                    r#"type="dynamic" minimumUpdatePeriod="PT0.05S""#,
+                    "2024-01-01T00:00:10Z",
+                    &period("1", 0, r#"<S t="0" d="20"/>"#),
+                ),
+                "/live.mpd" => manifest(
+                    r#"type="dynamic" minimumUpdatePeriod="PT0.05S""#,
+                    "2024-01-01T00:00:12Z",
+                    &format!(
+                        "<Location>next/live.mpd</Location>{}",
+                        period("1", 0, r#"<S t="0" d="20" r="1"/>"#)
+                    ),
+                ),
+                "/next/live.mpd" => manifest(
+                    r#"type="static" mediaPresentationDuration="PT4S""#,
+                    "2024-01-01T00:00:12Z",
+                    &period("1", 0, r#"<S t="0" d="20" r="1"/>"#),
+                ),
+                _ => return Response::not_found(),
+            };
+            Response::ok(body)
+        })
+        .await;
+
+        let mut watcher = MPDWatcher::new(root.join("live.mpd").unwrap().as_str())
+            .with_retry_delay(std::time::Duration::from_millis(10));
+        assert_eq!(watcher.next().await.unwrap().unwrap().len(), 3);
+        let changes = watcher.next().await.unwrap().unwrap();
+        assert_eq!(times(&changes[1]), vec![20]);
+        assert_eq!(
+            changes[2],
+            MPDChange::Location(root.join("next/live.mpd").unwrap())
+        );
+        assert!(watcher.next().await.unwrap().unwrap().is_empty());
+        assert!(watcher.next().await.is_none());
+        assert!(watcher.mpd().unwrap().mpd().is_static());
+
+        let paths: Vec<_> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.path.clone())
+            .collect();
+        assert_eq!(paths, vec!["/live.mpd", "/live.mpd", "/next/live.mpd"]);
+    }
+
+    #[tokio::test]
+    async fn should_stream_changes_and_retry_failures() {
+        let (root, _) = serve(|request| match request.path.as_str() {
+            "/static.mpd" => Response::ok(manifest(
+                r#"type="static" mediaPresentationDuration="PT2S""#,
+                "2024-01-01T00:00:10Z",
+                &period("1", 0, r#"<S t="0" d="20"/>"#),
+            )),
+            _ => Response::not_found(),
+        })
+        .await;
+
+        let mut changes = MPDWatcher::new(root.join("static.mpd").unwrap().as_str()).watch();
+        assert!(matches!(
+            changes.recv().await,
+            Some(Ok(MPDChange::PublishTime { .. }))
+        ));
+        assert!(matches!(
+            changes.recv().await,
+            Some(Ok(MPDChange::NewPeriod { .. }))
+        ));
+        assert!(matches!(
+            changes.recv().await,
+            Some(Ok(MPDChange::NewSegments { .. }))
+        ));
+        assert!(changes.recv().await.is_none());
+
+        let mut watcher = MPDWatcher::new(root.join("missing.mpd").unwrap().as_str())
+            .with_retry_delay(std::time::Duration::from_millis(10));
+        assert!(watcher.next().await.unwrap().is_err());
+        assert!(watcher.next().await.unwrap().is_err());
+    }
+}
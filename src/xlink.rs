@@ -0,0 +1,527 @@
+//! Resolution of the remote elements of a MPD (ISO/IEC 23009-1 5.5).
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+use reqwest::Client;
+use url::Url;
+
+use crate::error::Error;
+use crate::fetch::MPDFetcher;
+use crate::models::mpd::MPD;
+use crate::xml::{split_name, Element, Node};
+
+/// `xlink:href` removing the referencing element without any request.
+pub const RESOLVE_TO_ZERO: &str = "urn:mpeg:dash:resolve-to-zero:2013";
+
+/// Elements which may reference a remote element.
+const REMOTE_ELEMENTS: [&str; 4] = ["Period", "AdaptationSet", "EventStream", "SegmentList"];
+
+/// Remote elements referencing other remote elements followed before giving up.
+const DEFAULT_MAX_DEPTH: usize = 5;
+
+#[derive(Debug)]
+pub enum XLinkError {
+    InvalidUrl(String),
+    Request(String, reqwest::Error),
+    /// The remote element entity isn't well-formed XML.
+    Parse(String, Error),
+    /// The remote element entity holds an element of another type than the
+    /// referencing one.
+    UnexpectedElement {
+        url: String,
+        expected: String,
+        found: String,
+    },
+    /// The remote element references itself, directly or not, or the references are
+    /// nested deeper than the limit.
+    Recursion(String),
+    /// The resolved MPD can't be read.
+    InvalidMPD(Error),
+}
+
+impl fmt::Display for XLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XLinkError::InvalidUrl(url) => write!(f, "Invalid xlink:href: {}", url),
+            XLinkError::Request(url, error) => write!(f, "Can't fetch {}: {}", url, error),
+            XLinkError::Parse(url, error) => {
+                write!(f, "Invalid remote element at {}: {}", url, error)
+            }
+            XLinkError::UnexpectedElement {
+                url,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Expected {} elements at {}, found {}",
+                expected, url, found
+            ),
+            XLinkError::Recursion(url) => write!(f, "Recursive xlink:href: {}", url),
+            XLinkError::InvalidMPD(error) => write!(f, "Invalid resolved MPD: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for XLinkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XLinkError::Request(_, error) => Some(error),
+            XLinkError::Parse(_, error) | XLinkError::InvalidMPD(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+type Resolution<'a, T> = Pin<Box<dyn Future<Output = Result<T, XLinkError>> + Send + 'a>>;
+
+/// Replaces the `Period`, `AdaptationSet`, `EventStream` and `SegmentList` elements
+/// having a `xlink:href` with the zero or more elements it references.
+///
+/// Elements to actuate `onLoad` are resolved with the MPD, those to actuate `onRequest`
+/// when asked for, e.g. with [`resolve_period`](Self::resolve_period) before playing it.
+#[derive(Debug, Clone)]
+pub struct XLinkResolver {
+    client: Client,
+    max_depth: usize,
+    on_request: bool,
+}
+
+impl Default for XLinkResolver {
+    fn default() -> Self {
+        XLinkResolver {
+            client: MPDFetcher::new().client().clone(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            on_request: false,
+        }
+    }
+}
+
+impl XLinkResolver {
+    pub fn new() -> Self {
+        XLinkResolver::default()
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Remote elements referencing other remote elements followed before giving up,
+    /// 5 by default.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Makes [`resolve`](Self::resolve) resolve the elements to actuate `onRequest` as
+    /// well, e.g. to keep a copy of the whole presentation.
+    pub fn with_on_request(mut self, on_request: bool) -> Self {
+        self.on_request = on_request;
+        self
+    }
+
+    /// Resolves the elements to actuate `onLoad`, `manifest_url` being the URL the MPD
+    /// was fetched from.
+    pub async fn resolve(&self, mpd: &MPD, manifest_url: &Url) -> Result<MPD, XLinkError> {
+        let mut root = to_element(mpd).map_err(XLinkError::InvalidMPD)?;
+        self.resolve_children(&mut root, manifest_url, Vec::new())
+            .await?;
+        from_element(&root).map_err(XLinkError::InvalidMPD)
+    }
+
+    /// Resolves the `Period` at `index` whatever its `xlink:actuate`, with the elements
+    /// to actuate `onLoad` it references.
+    pub async fn resolve_period(
+        &self,
+        mpd: &MPD,
+        manifest_url: &Url,
+        index: usize,
+    ) -> Result<MPD, XLinkError> {
+        let mut root = to_element(mpd).map_err(XLinkError::InvalidMPD)?;
+        self.resolve_nth(&mut root, "Period", index, manifest_url)
+            .await?;
+        from_element(&root).map_err(XLinkError::InvalidMPD)
+    }
+
+    /// Resolves the `AdaptationSet` at `adaptation_index` in the `Period` at
+    /// `period_index` whatever its `xlink:actuate`.
+    pub async fn resolve_adaptation_set(
+        &self,
+        mpd: &MPD,
+        manifest_url: &Url,
+        period_index: usize,
+        adaptation_index: usize,
+    ) -> Result<MPD, XLinkError> {
+        let mut root = to_element(mpd).map_err(XLinkError::InvalidMPD)?;
+        let period = root
+            .children
+            .iter_mut()
+            .filter_map(|node| match node {
+                Node::Element(element) if element.name == "Period" => Some(element),
+                _ => None,
+            })
+            .nth(period_index);
+        if let Some(period) = period {
+            self.resolve_nth(period, "AdaptationSet", adaptation_index, manifest_url)
+                .await?;
+        }
+        from_element(&root).map_err(XLinkError::InvalidMPD)
+    }
+
+    /// Resolves the `index`-th child of `parent` named `name`, if it has a `xlink:href`.
+    async fn resolve_nth(
+        &self,
+        parent: &mut Element,
+        name: &str,
+        index: usize,
+        base: &Url,
+    ) -> Result<(), XLinkError> {
+        let position = parent
+            .children
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| matches!(node, Node::Element(element) if element.name == name))
+            .nth(index)
+            .map(|(position, _)| position);
+        let position = match position {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+        let (name, href) = match &parent.children[position] {
+            Node::Element(element) => match element.attribute("xlink:href") {
+                Some(href) => (element.name.clone(), href.to_string()),
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let resolved = self.dereference(&name, &href, base, Vec::new()).await?;
+        parent
+            .children
+            .splice(position..=position, resolved.into_iter().map(Node::Element));
+        Ok(())
+    }
+
+    /// Resolves the descendants of `element` to actuate `onLoad`.
+    fn resolve_children<'a>(
+        &'a self,
+        element: &'a mut Element,
+        base: &'a Url,
+        chain: Vec<Url>,
+    ) -> Resolution<'a, ()> {
+        Box::pin(async move {
+            let mut index = 0;
+            while index < element.children.len() {
+                let child = match &mut element.children[index] {
+                    Node::Element(child) => child,
+                    _ => {
+                        index += 1;
+                        continue;
+                    }
+                };
+                let href = child
+                    .attribute("xlink:href")
+                    .filter(|_| REMOTE_ELEMENTS.contains(&child.name.as_str()))
+                    .filter(|_| {
+                        self.on_request || child.attribute("xlink:actuate") == Some("onLoad")
+                    })
+                    .map(str::to_string);
+                match href {
+                    Some(href) => {
+                        let resolved = self
+                            .dereference(&child.name.clone(), &href, base, chain.clone())
+                            .await?;
+                        let count = resolved.len();
+                        element
+                            .children
+                            .splice(index..=index, resolved.into_iter().map(Node::Element));
+                        index += count;
+                    }
+                    None => {
+                        self.resolve_children(child, base, chain.clone()).await?;
+                        index += 1;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Elements referenced by `href`, which should be named `name`.
+    fn dereference<'a>(
+        &'a self,
+        name: &'a str,
+        href: &'a str,
+        base: &'a Url,
+        mut chain: Vec<Url>,
+    ) -> Resolution<'a, Vec<Element>> {
+        Box::pin(async move {
+            let href = href.trim();
+            if href == RESOLVE_TO_ZERO {
+                return Ok(Vec::new());
+            }
+            let url = base
+                .join(href)
+                .map_err(|_| XLinkError::InvalidUrl(href.to_string()))?;
+            if chain.contains(&url) || chain.len() >= self.max_depth {
+                return Err(XLinkError::Recursion(url.to_string()));
+            }
+
+            let failed = |error| XLinkError::Request(url.to_string(), error);
+            let body = self
+                .client
+                .get(url.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(failed)?
+                .text()
+                .await
+                .map_err(failed)?;
+            let mut entity =
+                parse_entity(&body).map_err(|error| XLinkError::Parse(url.to_string(), error))?;
+            if let Some(found) = entity
+                .elements()
+                .find(|element| split_name(&element.name).1 != split_name(name).1)
+            {
+                return Err(XLinkError::UnexpectedElement {
+                    url: url.to_string(),
+                    expected: name.to_string(),
+                    found: found.name.clone(),
+                });
+            }
+
+            // The resolved elements may reference remote elements in turn.
+            chain.push(url.clone());
+            self.resolve_children(&mut entity, &url, chain).await?;
+            Ok(entity
+                .children
+                .into_iter()
+                .filter_map(|node| match node {
+                    Node::Element(element) => Some(element),
+                    _ => None,
+                })
+                .collect())
+        })
+    }
+}
+
+/// Reads a remote element entity, zero or more elements, as the children of an element.
+fn parse_entity(body: &str) -> Result<Element, Error> {
+    let mut body = body.trim_start_matches('\u{feff}').trim_start();
+    if body.starts_with("<?xml") {
+        body = body.find("?>").map_or(body, |end| &body[end + 2..]);
+    }
+    Element::parse(&format!("<RemoteElements>{}</RemoteElements>", body))
+}
+
+fn to_element(mpd: &MPD) -> Result<Element, Error> {
+    Element::parse(&mpd.to_xml()?)
+}
+
+fn from_element(root: &Element) -> Result<MPD, Error> {
+    let mut root = root.clone();
+    let declarations: Vec<_> = root
+        .attributes
+        .iter()
+        .filter(|attribute| {
+            split_name(&attribute.name) == ("", "xmlns") || split_name(&attribute.name).0 == "xmlns"
+        })
+        .map(|attribute| (attribute.name.clone(), attribute.value.clone()))
+        .collect();
+    for node in &mut root.children {
+        if let Node::Element(element) = node {
+            undeclare(element, &declarations);
+        }
+    }
+    let mut source = String::new();
+    root.write(&mut source, 0);
+    source.parse()
+}
+
+/// Removes the namespace declarations of the remote elements already made by the MPD.
+fn undeclare(element: &mut Element, declarations: &[(String, String)]) {
+    element.attributes.retain(|attribute| {
+        !declarations
+            .iter()
+            .any(|(name, value)| attribute.name == *name && attribute.value == *value)
+    });
+    for node in &mut element.children {
+        if let Node::Element(child) = node {
+            undeclare(child, declarations);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{serve, Response};
+
+    fn mpd(periods: &str) -> MPD {
+        format!(
+            r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" xmlns:xlink="http://www.w3.org/1999/xlink" type="dynamic" profiles="urn:mpeg:dash:profile:isoff-live:2011" availabilityStartTime="2024-01-01T00:00:00Z">{}</MPD>"#,
+            periods
+        )
+        .parse()
+        .unwrap()
+    }
+
+    fn ids(mpd: &MPD) -> Vec<&str> {
+        mpd.periods().iter().map(|period| period.id()).collect()
+    }
+
+    async fn ad_server() -> Url {
+        let (root, _) = serve(|request| match request.path.as_str() {
+            "/ads/break-1" => Response::ok(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<Period xmlns="urn:mpeg:dash:schema:mpd:2011" id="ad-1" duration="PT15S"/>
+<Period xmlns="urn:mpeg:dash:schema:mpd:2011" id="ad-2" duration="PT15S"/>"#,
+            ),
+            "/ads/empty" => Response::ok(""),
+            "/ads/wrapper" => Response::ok(
+                r#"<Period xmlns:xlink="http://www.w3.org/1999/xlink" xlink:href="break-1" xlink:actuate="onLoad"/>"#,
+            ),
+            "/ads/loop" => Response::ok(
+                r#"<Period xmlns:xlink="http://www.w3.org/1999/xlink" xlink:href="loop" xlink:actuate="onLoad"/>"#,
+            ),
+            "/ads/audio" => Response::ok(
+                r#"<AdaptationSet id="9" contentType="audio"/>"#,
+            ),
+            _ => Response::not_found(),
+        })
+        .await;
+        root.join("live/manifest.mpd").unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_replace_elements_to_actuate_on_load() {
+        let url = ad_server().await;
+        let mpd = mpd(r#"<Period id="main" duration="PT30S"/>
+            <Period xlink:href="../ads/break-1" xlink:actuate="onLoad" duration="PT30S"/>
+            <Period xlink:href="urn:mpeg:dash:resolve-to-zero:2013" xlink:actuate="onLoad"/>
+            <Period xlink:href="/ads/empty" xlink:actuate="onLoad"/>
+            <Period xlink:href="/ads/wrapper" xlink:actuate="onLoad"/>
+            <Period xlink:href="/ads/break-1" id="later"/>"#);
+        let resolved = XLinkResolver::new().resolve(&mpd, &url).await.unwrap();
+        assert_eq!(
+            ids(&resolved),
+            vec!["main", "ad-1", "ad-2", "ad-1", "ad-2", "later"]
+        );
+        assert_eq!(resolved.periods()[1].href(), "");
+        assert_eq!(resolved.periods()[5].href(), "/ads/break-1");
+
+        let requested = XLinkResolver::new()
+            .resolve_period(&resolved, &url, 5)
+            .await
+            .unwrap();
+        assert_eq!(requested.periods().len(), 7);
+        assert_eq!(ids(&requested)[5..], ["ad-1", "ad-2"]);
+
+        let everything = XLinkResolver::new()
+            .with_on_request(true)
+            .resolve(&mpd, &url)
+            .await
+            .unwrap();
+        assert_eq!(ids(&everything), ids(&requested));
+    }
+
+    #[tokio::test]
+    async fn should_resolve_adaptation_set_on_request() {
+        let url = ad_server().await;
+        let mpd = mpd(
+            r#"<Period id="main"><AdaptationSet id="1"/><AdaptationSet xlink:href="/ads/audio"/></Period>"#,
+        );
+        let resolved = XLinkResolver::new()
+            .resolve_adaptation_set(&mpd, &url, 0, 1)
+            .await
+            .unwrap();
+        let adaptations = resolved.periods()[0].adaptations();
+        assert_eq!(adaptations.len(), 2);
+        assert_eq!(adaptations[1].content_type(), "audio");
+    }
+
+    #[tokio::test]
+    async fn should_reject_recursive_and_invalid_references() {
+        let url = ad_server().await;
+        let resolver = XLinkResolver::new();
+        let resolve = |href: &str| {
+            let mpd = mpd(&format!(
+                r#"<Period xlink:href="{}" xlink:actuate="onLoad"/>"#,
+                href
+            ));
+            let resolver = resolver.clone();
+            let url = url.clone();
+            async move { resolver.resolve(&mpd, &url).await }
+        };
+        assert!(matches!(
+            resolve("/ads/loop").await,
+            Err(XLinkError::Recursion(_))
+        ));
+        assert!(matches!(
+            resolve("/ads/audio").await,
+            Err(XLinkError::UnexpectedElement { .. })
+        ));
+        assert!(matches!(
+            resolve("/ads/missing").await,
+            Err(XLinkError::Request(_, _))
+        ));
+        assert!(matches!(
+            XLinkResolver::new()
+                .with_max_depth(1)
+                .resolve(
+                    &mpd(r#"<Period xlink:href="/ads/wrapper" xlink:actuate="onLoad"/>"#),
+                    &url
+                )
+                .await,
+            Err(XLinkError::Recursion(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_resolve_event_streams_and_segment_lists() {
+        let (root, _) = serve(|request| match request.path.as_str() {
+            "/events" => Response::ok(
+                r#"<EventStream schemeIdUri="urn:example:ads" value="1"><Event id="1" presentationTime="10"/></EventStream>
+<EventStream schemeIdUri="urn:example:ads" value="2"/>"#,
+            ),
+            "/list" => Response::ok(
+                r#"<SegmentList duration="2"><SegmentURL media="1.m4s"/><SegmentURL media="2.m4s"/></SegmentList>"#,
+            ),
+            _ => Response::not_found(),
+        })
+        .await;
+        let url = root.join("live/manifest.mpd").unwrap();
+        let mpd = mpd(r#"<Period id="main">
+            <EventStream schemeIdUri="urn:example:ads" xlink:href="/events" xlink:actuate="onLoad"/>
+            <AdaptationSet id="1">
+                <Representation id="video" bandwidth="1">
+                    <SegmentList xlink:href="/list" xlink:actuate="onLoad"/>
+                </Representation>
+                <Representation id="audio" bandwidth="1">
+                    <SegmentList xlink:href="/list"/>
+                </Representation>
+            </AdaptationSet>
+        </Period>"#);
+
+        let resolved = XLinkResolver::new().resolve(&mpd, &url).await.unwrap();
+
+        let period = &resolved.periods()[0];
+        let values: Vec<_> = period
+            .event_stream()
+            .iter()
+            .map(|stream| stream.value())
+            .collect();
+        assert_eq!(values, ["1", "2"]);
+        assert_eq!(period.event_stream()[0].events().len(), 1);
+        let representations = period.adaptations()[0].representations();
+        let list = representations[0].segment_list().unwrap();
+        assert_eq!(list.href(), "");
+        assert_eq!(list.duration(), 2);
+        let media: Vec<_> = list.segment_url().iter().map(|url| url.media()).collect();
+        assert_eq!(media, ["1.m4s", "2.m4s"]);
+        let requested = representations[1].segment_list().unwrap();
+        assert_eq!(requested.href(), "/list");
+        assert!(requested.segment_url().is_empty());
+    }
+}
@@ -0,0 +1,712 @@
+use serde::de::{self, DeserializeSeed, Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec;
+
+use super::element::{Attribute, Element};
+use crate::error::{Error, ErrorKind};
+
+/// Deserializes `T` from the root element of a document.
+pub(crate) fn from_element<'de, T: de::Deserialize<'de>>(root: &'de Element) -> Result<T, Error> {
+    T::deserialize(ElementDeserializer {
+        element: root,
+        label: root.name.clone(),
+    })
+}
+
+/// Name used to point to an element in error paths: `Period[id=3]`, `AdaptationSet[2]`
+/// (1-based, only when it has siblings of the same name) or just `SegmentTemplate`.
+fn label(element: &Element, index: usize, count: usize) -> String {
+    match element.attribute("id") {
+        Some(id) if !id.is_empty() => format!("{}[id={}]", element.name, id),
+        _ if count > 1 => format!("{}[{}]", element.name, index + 1),
+        _ => element.name.clone(),
+    }
+}
+
+/// Prefix declared by a `xmlns` attribute, empty for the default namespace.
+fn namespace_prefix(name: &str) -> Option<&str> {
+    match name {
+        "xmlns" => Some(""),
+        _ => name.strip_prefix("xmlns:"),
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $ty:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let value = self.text.trim();
+                match value.parse::<$ty>() {
+                    Ok(parsed) => visitor.$visit(parsed),
+                    Err(error) => Err(Error::new(format!(
+                        "invalid {} `{}`: {}",
+                        stringify!($ty),
+                        value,
+                        error
+                    ))),
+                }
+            }
+        )*
+    };
+}
+
+/// Leaf deserializer: value of an attribute or text content of an element.
+struct TextDeserializer<'a> {
+    text: &'a str,
+}
+
+impl<'de, 'a> Deserializer<'de> for TextDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str(self.text)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.text.trim() {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            value => Err(Error::new(format!("invalid bool `{}`", value))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Lists (`xs:list`) are whitespace separated.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(de::value::SeqDeserializer::new(
+            self.text
+                .split_whitespace()
+                .map(|text| TextDeserializer { text }),
+        ))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.text.trim().into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 str string bytes byte_buf unit_struct tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, Error> for TextDeserializer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! forward_to_first {
+    ($($method:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match children_seq(self.children).next() {
+                    Some(first) => first.$method(visitor),
+                    None => visitor.visit_unit(),
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! forward_to_text {
+    ($($method:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let text = self.element.text();
+                TextDeserializer { text: &text }.$method(visitor)
+            }
+        )*
+    };
+}
+
+struct ElementDeserializer<'a> {
+    element: &'a Element,
+    label: String,
+}
+
+impl<'de> ElementDeserializer<'de> {
+    fn locate<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        result.map_err(|error| error.within(self.label.clone(), self.element.position))
+    }
+
+    fn map(&self, fields: &[&str]) -> ElementMap<'de> {
+        let element = self.element;
+        let has_namespaces = fields.contains(&"$namespaces");
+        let has_extensions = fields.contains(&"$extensions");
+        let mut entries = Vec::new();
+        let mut unknown_attributes = Vec::new();
+        for attribute in &element.attributes {
+            if has_namespaces && namespace_prefix(&attribute.name).is_some() {
+                continue;
+            }
+            if has_extensions && !fields.contains(&attribute.name.as_str()) {
+                unknown_attributes.push(attribute);
+            } else {
+                entries.push(Entry::Attribute(attribute));
+            }
+        }
+        let has_value = fields.contains(&"$value");
+
+        let mut groups: Vec<(&str, Vec<&Element>)> = Vec::new();
+        let mut unclaimed = Vec::new();
+        let unknown_elements = Rc::new(RefCell::new(Vec::new()));
+        for child in element.elements() {
+            if has_value && !fields.contains(&child.name.as_str()) {
+                unclaimed.push(child);
+                continue;
+            }
+            if has_extensions && !fields.contains(&child.name.as_str()) {
+                unknown_elements.borrow_mut().push(child);
+                continue;
+            }
+            match groups.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, children)) => children.push(child),
+                None => groups.push((&child.name, vec![child])),
+            }
+        }
+        entries.extend(
+            groups
+                .into_iter()
+                .map(|(name, children)| Entry::Children(name, children)),
+        );
+        if has_value {
+            // With extensions, the children the value doesn't know are kept aside for them.
+            let skipped = has_extensions.then(|| unknown_elements.clone());
+            entries.push(Entry::Value(element, unclaimed, skipped));
+        }
+        if has_namespaces {
+            entries.push(Entry::Namespaces(element));
+        }
+        if has_extensions {
+            entries.push(Entry::Extensions(unknown_attributes, unknown_elements));
+        }
+
+        ElementMap {
+            entries: entries.into_iter(),
+            pending: None,
+        }
+    }
+
+    fn text(&self) -> String {
+        self.element.text()
+    }
+}
+
+macro_rules! deserialize_text {
+    ($($method:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let text = self.text();
+                let result = TextDeserializer { text: &text }.$method(visitor);
+                self.locate(result)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ElementDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.element.attributes.is_empty() && self.element.elements().next().is_none() {
+            self.deserialize_string(visitor)
+        } else {
+            self.deserialize_map(visitor)
+        }
+    }
+
+    deserialize_text! {
+        deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32, deserialize_i64,
+        deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64, deserialize_f32,
+        deserialize_f64, deserialize_char, deserialize_seq
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let result = visitor.visit_string(self.text());
+        self.locate(result)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let result = visitor.visit_map(self.map(&[]));
+        self.locate(result)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let result = visitor.visit_map(self.map(fields));
+        self.locate(result)
+    }
+
+    /// The variant is given by the name of the element, as in `<cenc:pssh>...</cenc:pssh>`.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let element = self.element;
+        let label = self.label.clone();
+        let result = visitor.visit_enum(ElementEnum { element, label });
+        self.locate(result)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit_struct tuple tuple_struct identifier
+    }
+}
+
+struct ElementEnum<'a> {
+    element: &'a Element,
+    label: String,
+}
+
+impl<'de> de::EnumAccess<'de> for ElementEnum<'de> {
+    type Error = Error;
+    type Variant = ElementDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Error> {
+        let name: &str = &self.element.name;
+        let name: de::value::StrDeserializer<Error> = name.into_deserializer();
+        let variant = seed.deserialize(name)?;
+        Ok((
+            variant,
+            ElementDeserializer {
+                element: self.element,
+                label: self.label,
+            },
+        ))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ElementDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_struct("", fields, visitor)
+    }
+}
+
+enum Entry<'a> {
+    Attribute(&'a Attribute),
+    /// Children sharing the same name, deserialized as a sequence or as a single value.
+    Children(&'a str, Vec<&'a Element>),
+    /// `$value` field: text content, or the children no other field claims.
+    Value(&'a Element, Vec<&'a Element>, Option<Skipped<'a>>),
+    /// `$namespaces` field: the `xmlns` declarations of the element, by prefix.
+    Namespaces(&'a Element),
+    /// `$extensions` field: the attributes and children the model doesn't know.
+    Extensions(Vec<&'a Attribute>, Skipped<'a>),
+}
+
+/// Children left out of a field, shared with the `$extensions` entry read after it.
+type Skipped<'a> = Rc<RefCell<Vec<&'a Element>>>;
+
+struct ElementMap<'a> {
+    entries: vec::IntoIter<Entry<'a>>,
+    pending: Option<Entry<'a>>,
+}
+
+impl<'de> de::MapAccess<'de> for ElementMap<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let entry = match self.entries.next() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let key: &str = match &entry {
+            Entry::Attribute(attribute) => &attribute.name,
+            Entry::Children(name, _) => name,
+            Entry::Value(_, _, _) => "$value",
+            Entry::Namespaces(_) => "$namespaces",
+            Entry::Extensions(_, _) => "$extensions",
+        };
+        let key: de::value::StrDeserializer<Error> = key.into_deserializer();
+        let key = seed.deserialize(key)?;
+        self.pending = Some(entry);
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.pending.take() {
+            Some(Entry::Attribute(attribute)) => seed
+                .deserialize(TextDeserializer {
+                    text: &attribute.value,
+                })
+                .map_err(|error| error.at_attribute(&attribute.name, attribute.position)),
+            Some(Entry::Children(_, children)) => {
+                seed.deserialize(ChildrenDeserializer { children })
+            }
+            Some(Entry::Value(element, unclaimed, skipped)) => {
+                seed.deserialize(ValueDeserializer {
+                    element,
+                    unclaimed,
+                    skipped,
+                })
+            }
+            Some(Entry::Namespaces(element)) => {
+                let namespaces = element.attributes.iter().filter_map(|attribute| {
+                    namespace_prefix(&attribute.name)
+                        .map(|prefix| (prefix, attribute.value.as_str()))
+                });
+                seed.deserialize(de::value::MapDeserializer::new(namespaces))
+            }
+            Some(Entry::Extensions(attributes, elements)) => {
+                let attributes: Vec<Vec<String>> = attributes
+                    .into_iter()
+                    .map(|attribute| {
+                        vec![
+                            attribute.name.clone(),
+                            attribute.value.clone(),
+                            attribute.namespace.clone().unwrap_or_default(),
+                        ]
+                    })
+                    .collect();
+                let elements: Vec<Vec<String>> = elements
+                    .borrow()
+                    .iter()
+                    .map(|element| {
+                        vec![
+                            element.name.clone(),
+                            element.namespace.clone().unwrap_or_default(),
+                            element.to_detached_xml(),
+                        ]
+                    })
+                    .collect();
+                seed.deserialize(de::value::MapDeserializer::new(
+                    vec![("attributes", attributes), ("elements", elements)].into_iter(),
+                ))
+            }
+            None => Err(Error::new("value requested before its key")),
+        }
+    }
+}
+
+fn children_seq<'a>(children: Vec<&'a Element>) -> impl Iterator<Item = ElementDeserializer<'a>> {
+    let count = children.len();
+    children
+        .into_iter()
+        .enumerate()
+        .map(move |(index, element)| ElementDeserializer {
+            element,
+            label: label(element, index, count),
+        })
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ElementDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct ChildrenDeserializer<'a> {
+    children: Vec<&'a Element>,
+}
+
+impl<'de> Deserializer<'de> for ChildrenDeserializer<'de> {
+    type Error = Error;
+
+    /// Anything but a sequence is read from the first child.
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match children_seq(self.children).next() {
+            Some(first) => first.deserialize_any(visitor),
+            None => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(de::value::SeqDeserializer::new(children_seq(self.children)))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_first! {
+        deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32, deserialize_i64,
+        deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64, deserialize_f32,
+        deserialize_f64, deserialize_char, deserialize_str, deserialize_string,
+        deserialize_map, deserialize_unit
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match children_seq(self.children).next() {
+            Some(first) => first.deserialize_newtype_struct(name, visitor),
+            None => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match children_seq(self.children).next() {
+            Some(first) => first.deserialize_struct(name, fields, visitor),
+            None => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match children_seq(self.children).next() {
+            Some(first) => first.deserialize_enum(name, variants, visitor),
+            None => visitor.visit_unit(),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit_struct tuple tuple_struct identifier
+    }
+}
+
+struct ValueDeserializer<'a> {
+    element: &'a Element,
+    unclaimed: Vec<&'a Element>,
+    skipped: Option<Skipped<'a>>,
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.element.text())
+    }
+
+    /// A sequence holds the unclaimed children, e.g. the `cenc:pssh` of a `ContentProtection`.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.skipped {
+            Some(skipped) => {
+                let count = self.unclaimed.len();
+                visitor.visit_seq(KnownChildren {
+                    remaining: Rc::new(RefCell::new(
+                        self.unclaimed.into_iter().enumerate().collect(),
+                    )),
+                    count,
+                    skipped,
+                })
+            }
+            None => visitor.visit_seq(de::value::SeqDeserializer::new(children_seq(
+                self.unclaimed,
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_text! {
+        deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32, deserialize_i64,
+        deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64, deserialize_f32,
+        deserialize_f64, deserialize_char, deserialize_str, deserialize_string, deserialize_unit
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let text = self.element.text();
+        TextDeserializer { text: &text }.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 bytes byte_buf unit_struct newtype_struct tuple tuple_struct map struct
+        identifier
+    }
+}
+
+/// Children of a `$value` sequence of enums alongside `$extensions`: the children whose
+/// name isn't a variant of the enum are skipped and kept for the extensions.
+struct KnownChildren<'a> {
+    remaining: Rc<RefCell<VecDeque<(usize, &'a Element)>>>,
+    count: usize,
+    skipped: Skipped<'a>,
+}
+
+impl<'de> de::SeqAccess<'de> for KnownChildren<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.remaining.borrow().is_empty() {
+            return Ok(None);
+        }
+        let child = KnownChild {
+            remaining: self.remaining.clone(),
+            count: self.count,
+            skipped: self.skipped.clone(),
+        };
+        match seed.deserialize(child) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if matches!(error.kind(), ErrorKind::NoKnownChild) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+struct KnownChild<'a> {
+    remaining: Rc<RefCell<VecDeque<(usize, &'a Element)>>>,
+    count: usize,
+    skipped: Skipped<'a>,
+}
+
+impl<'a> KnownChild<'a> {
+    /// Next child accepted by `known`, the ones before it are skipped.
+    fn next(&self, known: impl Fn(&Element) -> bool) -> Result<ElementDeserializer<'a>, Error> {
+        loop {
+            let next = self.remaining.borrow_mut().pop_front();
+            match next {
+                Some((index, element)) if known(element) => {
+                    return Ok(ElementDeserializer {
+                        element,
+                        label: label(element, index, self.count),
+                    })
+                }
+                Some((_, element)) => self.skipped.borrow_mut().push(element),
+                // Ends the sequence, see `KnownChildren`.
+                None => return Err(Error::no_known_child()),
+            }
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for KnownChild<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.next(|_| true)?.deserialize_any(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.next(|element| variants.contains(&element.name.as_str()))?
+            .deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
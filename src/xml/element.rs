@@ -0,0 +1,419 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::error::{Error, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Attribute {
+    pub(crate) name: String,
+    pub(crate) value: String,
+    /// Namespace URI of a prefixed attribute, resolved while parsing.
+    pub(crate) namespace: Option<String>,
+    /// Position in the parsed source, `None` for nodes built in memory.
+    pub(crate) position: Option<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Node {
+    Element(Element),
+    Text(String),
+    CData(String),
+    Comment(String),
+}
+
+/// Lightweight DOM of a XML document, the model is deserialized from it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<Attribute>,
+    pub(crate) children: Vec<Node>,
+    /// Namespace URI of the element, resolved while parsing.
+    pub(crate) namespace: Option<String>,
+    pub(crate) position: Option<Position>,
+}
+
+/// Converts byte offsets of the source into lines and columns.
+struct Lines<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Lines { source, starts }
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let start = self.starts[line];
+        let column = self
+            .source
+            .get(start..offset)
+            .map_or(offset - start, |text| text.chars().count());
+        Position::new(line + 1, column + 1)
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<String, Error> {
+    String::from_utf8(bytes.to_vec()).map_err(|error| Error::new(error.to_string()))
+}
+
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Splits a qualified name into its prefix, empty when there is none, and local name.
+pub(crate) fn split_name(name: &str) -> (&str, &str) {
+    name.split_once(':').unwrap_or(("", name))
+}
+
+/// Namespace bound to `prefix` by the innermost declaration in scope.
+fn resolve(declarations: &[(String, String)], prefix: &str) -> Option<String> {
+    if prefix == "xml" {
+        return Some(XML_NAMESPACE.to_string());
+    }
+    declarations
+        .iter()
+        .rev()
+        .find(|(declared, _)| declared == prefix)
+        .map(|(_, uri)| uri.clone())
+        .filter(|uri| !uri.is_empty())
+}
+
+/// Appends `node` to the element being read, nodes outside of the root are dropped.
+fn push(stack: &mut [Element], node: Node) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+impl Element {
+    /// Parses the root element of `source`. Declaration, processing instructions,
+    /// doctype and comments around the root element are skipped.
+    pub(crate) fn parse(source: &str) -> Result<Element, Error> {
+        let lines = Lines::new(source);
+        let mut reader = Reader::from_str(source);
+        reader.check_end_names(true);
+        let mut stack: Vec<Element> = Vec::new();
+        // Namespace declarations in scope, and how many each open element added.
+        let mut declarations: Vec<(String, String)> = Vec::new();
+        let mut declared: Vec<usize> = Vec::new();
+        let mut root = None;
+        let mut buf = Vec::new();
+
+        loop {
+            let offset = reader.buffer_position();
+            let event = reader.read_event(&mut buf).map_err(|error| {
+                Error::new(error.to_string())
+                    .with_path(stack.iter().map(|element| element.name.clone()).collect())
+                    .with_position(lines.position(reader.buffer_position()))
+            })?;
+            let located = |error: Error, stack: &[Element]| {
+                error
+                    .with_path(stack.iter().map(|element| element.name.clone()).collect())
+                    .with_position(lines.position(offset))
+            };
+            match event {
+                Event::Start(start) => {
+                    let mut element =
+                        Element::open(&start, offset, &lines).map_err(|e| located(e, &stack))?;
+                    declared.push(element.declare(&mut declarations));
+                    stack.push(element);
+                }
+                Event::Empty(start) => {
+                    let mut element =
+                        Element::open(&start, offset, &lines).map_err(|e| located(e, &stack))?;
+                    let count = element.declare(&mut declarations);
+                    declarations.truncate(declarations.len() - count);
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(Node::Element(element)),
+                        None => root = Some(element),
+                    }
+                }
+                Event::End(_) => {
+                    let element = stack.pop().ok_or_else(|| {
+                        Error::new("unexpected closing tag").with_position(lines.position(offset))
+                    })?;
+                    let count = declared.pop().unwrap_or(0);
+                    declarations.truncate(declarations.len() - count);
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(Node::Element(element)),
+                        None => root = Some(element),
+                    }
+                }
+                Event::Text(text) => {
+                    if stack.is_empty() {
+                        if !text.escaped().iter().all(u8::is_ascii_whitespace) {
+                            return Err(Error::new("text outside of the root element")
+                                .with_position(lines.position(offset)));
+                        }
+                    } else {
+                        let text = text
+                            .unescaped()
+                            .map_err(|error| Error::new(error.to_string()))
+                            .and_then(|text| decode(&text))
+                            .map_err(|e| located(e, &stack))?;
+                        push(&mut stack, Node::Text(text));
+                    }
+                }
+                Event::CData(data) => {
+                    // quick-xml stores CDATA sections escaped
+                    let data = data
+                        .unescaped()
+                        .map_err(|error| Error::new(error.to_string()))
+                        .and_then(|data| decode(&data))
+                        .map_err(|e| located(e, &stack))?;
+                    push(&mut stack, Node::CData(data));
+                }
+                Event::Comment(comment) => {
+                    let comment = decode(comment.escaped()).map_err(|e| located(e, &stack))?;
+                    push(&mut stack, Node::Comment(comment));
+                }
+                Event::Eof => break,
+                Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+            }
+            buf.clear();
+        }
+
+        if let Some(element) = stack.pop() {
+            return Err(Error::new(format!("unclosed element {}", element.name))
+                .with_position(lines.position(source.len())));
+        }
+        root.ok_or_else(|| Error::new("missing root element").with_position(Position::new(1, 1)))
+    }
+
+    fn open(start: &BytesStart, offset: usize, lines: &Lines) -> Result<Element, Error> {
+        let content: &[u8] = start;
+        let mut attributes = Vec::new();
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(|error| Error::new(error.to_string()))?;
+            // The key borrows the buffer of the tag, which starts right after `<`.
+            let key_offset = attribute.key.as_ptr() as usize - content.as_ptr() as usize;
+            let value = attribute
+                .unescaped_value()
+                .map_err(|error| Error::new(error.to_string()))?;
+            attributes.push(Attribute {
+                name: decode(attribute.key)?,
+                value: decode(&value)?,
+                namespace: None,
+                position: Some(lines.position(offset + 1 + key_offset)),
+            });
+        }
+
+        Ok(Element {
+            name: decode(start.name())?,
+            attributes,
+            children: Vec::new(),
+            namespace: None,
+            position: Some(lines.position(offset)),
+        })
+    }
+
+    /// Adds the `xmlns` declarations of the element to the scope and resolves the
+    /// namespaces of the element and its attributes, returns the number of declarations.
+    fn declare(&mut self, declarations: &mut Vec<(String, String)>) -> usize {
+        let before = declarations.len();
+        for attribute in &self.attributes {
+            match split_name(&attribute.name) {
+                ("", "xmlns") => declarations.push((String::new(), attribute.value.clone())),
+                ("xmlns", prefix) => {
+                    declarations.push((prefix.to_string(), attribute.value.clone()))
+                }
+                _ => {}
+            }
+        }
+        self.namespace = resolve(declarations, split_name(&self.name).0);
+        for attribute in &mut self.attributes {
+            attribute.namespace = match split_name(&attribute.name) {
+                ("", _) | ("xmlns", _) => None,
+                (prefix, _) => resolve(declarations, prefix),
+            };
+        }
+        declarations.len() - before
+    }
+
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    pub(crate) fn set_attribute(&mut self, name: &str, value: &str) {
+        match self
+            .attributes
+            .iter_mut()
+            .find(|attribute| attribute.name == name)
+        {
+            Some(attribute) => attribute.value = value.to_string(),
+            None => self.attributes.push(Attribute {
+                name: name.to_string(),
+                value: value.to_string(),
+                namespace: None,
+                position: None,
+            }),
+        }
+    }
+
+    pub(crate) fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Text content of the element, without leading and trailing whitespace.
+    pub(crate) fn text(&self) -> String {
+        let text: String = self
+            .children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) | Node::CData(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        text.trim().to_string()
+    }
+
+    /// Writes the element on its own, declaring the namespaces of the prefixes it uses
+    /// which were declared by one of its ancestors.
+    pub(crate) fn to_detached_xml(&self) -> String {
+        let mut used = Vec::new();
+        self.used_namespaces(&mut used);
+        let mut element = self.clone();
+        for (prefix, uri) in used {
+            let name = format!("xmlns:{}", prefix);
+            if element.attribute(&name).is_none() {
+                element.attributes.push(Attribute {
+                    name,
+                    value: uri,
+                    namespace: None,
+                    position: None,
+                });
+            }
+        }
+        let mut out = String::new();
+        element.write(&mut out, 0);
+        out
+    }
+
+    /// Prefixes used by the element or its descendants and not declared within it.
+    fn used_namespaces(&self, used: &mut Vec<(String, String)>) {
+        let names = std::iter::once((&self.name, &self.namespace)).chain(
+            self.attributes
+                .iter()
+                .map(|attribute| (&attribute.name, &attribute.namespace)),
+        );
+        for (name, namespace) in names {
+            let prefix = split_name(name).0;
+            if let Some(uri) = namespace {
+                if !matches!(prefix, "" | "xml" | "xmlns")
+                    && self.attribute(&format!("xmlns:{}", prefix)).is_none()
+                    && !used.iter().any(|(declared, _)| declared == prefix)
+                {
+                    used.push((prefix.to_string(), uri.clone()));
+                }
+            }
+        }
+        for child in self.elements() {
+            let mut nested = Vec::new();
+            child.used_namespaces(&mut nested);
+            for (prefix, uri) in nested {
+                if self.attribute(&format!("xmlns:{}", prefix)).is_none()
+                    && !used.iter().any(|(declared, _)| *declared == prefix)
+                {
+                    used.push((prefix, uri));
+                }
+            }
+        }
+    }
+
+    /// Writes the element, its children indented by `depth` levels. Elements holding
+    /// text are written as is, so their content is kept byte for byte.
+    pub(crate) fn write(&self, out: &mut String, depth: usize) {
+        out.push('<');
+        out.push_str(&self.name);
+        for attribute in &self.attributes {
+            out.push(' ');
+            out.push_str(&attribute.name);
+            out.push_str("=\"");
+            escape(out, &attribute.value, true);
+            out.push('"');
+        }
+        if self.children.is_empty() {
+            out.push_str("/>");
+            return;
+        }
+        out.push('>');
+
+        let has_text = self.children.iter().any(|node| match node {
+            Node::Text(text) => !text.trim().is_empty(),
+            Node::CData(_) => true,
+            _ => false,
+        });
+        if has_text {
+            for node in &self.children {
+                node.write(out, depth);
+            }
+        } else {
+            for node in &self.children {
+                if let Node::Text(_) = node {
+                    continue;
+                }
+                indent(out, depth + 1);
+                node.write(out, depth + 1);
+            }
+            indent(out, depth);
+        }
+        out.push_str("</");
+        out.push_str(&self.name);
+        out.push('>');
+    }
+}
+
+impl Node {
+    fn write(&self, out: &mut String, depth: usize) {
+        match self {
+            Node::Element(element) => element.write(out, depth),
+            Node::Text(text) => escape(out, text, false),
+            Node::CData(data) => {
+                out.push_str("<![CDATA[");
+                out.push_str(data);
+                out.push_str("]]>");
+            }
+            Node::Comment(comment) => {
+                out.push_str("<!--");
+                out.push_str(comment);
+                out.push_str("-->");
+            }
+        }
+    }
+}
+
+fn indent(out: &mut String, depth: usize) {
+    out.push('\n');
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+/// Escapes markup characters, and whitespace the parser would normalize in attributes.
+fn escape(out: &mut String, text: &str, attribute: bool) {
+    for char in text.chars() {
+        match char {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            '\n' if attribute => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            '\t' if attribute => out.push_str("&#9;"),
+            char => out.push(char),
+        }
+    }
+}
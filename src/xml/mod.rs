@@ -0,0 +1,59 @@
+mod de;
+mod element;
+mod ser;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{Error, Position};
+pub(crate) use element::{split_name, Element, Node};
+pub(crate) use ser::to_element;
+
+const UTF8_BOM: &str = "\u{feff}";
+
+/// Parses `source` and deserializes `T` from its root element.
+pub(crate) fn from_str<T: DeserializeOwned>(source: &str) -> Result<T, Error> {
+    let root = Element::parse(source.strip_prefix(UTF8_BOM).unwrap_or(source))?;
+    de::from_element(&root)
+}
+
+/// Writes `value` as a XML document, with its declaration and two spaces indentation.
+pub(crate) fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
+    let root = ser::to_element(value)?;
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    root.write(&mut out, 0);
+    out.push('\n');
+    Ok(out)
+}
+
+/// Same as [`from_str`] for a raw document: UTF-8, or UTF-16 when it starts with a BOM.
+pub(crate) fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    match bytes {
+        [0xfe, 0xff, rest @ ..] => from_str(&decode_utf16(rest, u16::from_be_bytes)?),
+        [0xff, 0xfe, rest @ ..] => from_str(&decode_utf16(rest, u16::from_le_bytes)?),
+        _ => match std::str::from_utf8(bytes) {
+            Ok(source) => from_str(source),
+            Err(error) => {
+                let valid = String::from_utf8_lossy(&bytes[..error.valid_up_to()]);
+                let line = valid.matches('\n').count() + 1;
+                let column = valid
+                    .rsplit('\n')
+                    .next()
+                    .map_or(0, |text| text.chars().count())
+                    + 1;
+                Err(Error::new(error.to_string()).with_position(Position::new(line, column)))
+            }
+        },
+    }
+}
+
+fn decode_utf16(bytes: &[u8], decode: fn([u8; 2]) -> u16) -> Result<String, Error> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(Error::new("invalid UTF-16 document: odd number of bytes"));
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|unit| decode([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|error| Error::new(error.to_string()))
+}
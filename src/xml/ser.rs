@@ -0,0 +1,401 @@
+use serde::ser::{self, Serialize};
+
+use super::element::{Attribute, Element, Node};
+use crate::error::Error;
+
+/// Serializes `value` into the element it would be read from.
+pub(crate) fn to_element<T: Serialize>(value: &T) -> Result<Element, Error> {
+    match value.serialize(ValueSerializer)? {
+        Value::Element(element) => Ok(element),
+        _ => Err(Error::new(
+            "only a struct can be serialized as a XML document",
+        )),
+    }
+}
+
+/// Intermediate result of the serialization of a field, its parent decides whether it
+/// becomes an attribute, a child element or the text of the element.
+enum Value {
+    None,
+    Text(String),
+    Element(Element),
+    Seq(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Element {
+            name: name.to_string(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+            namespace: None,
+            position: None,
+        }
+    }
+
+    fn push_attribute(&mut self, name: String, value: String) {
+        self.attributes.push(Attribute {
+            name,
+            value,
+            namespace: None,
+            position: None,
+        });
+    }
+
+    /// Adds a serialized field to the element:
+    /// - `$value` is written as text, or as children for a sequence of elements,
+    /// - `$namespaces` holds the `xmlns` declarations, by prefix,
+    /// - `$extensions` holds the attributes and the raw children the model doesn't know,
+    /// - text and lists of text are written as attributes,
+    /// - structures are written as children named after the field.
+    fn push_field(&mut self, key: &str, value: Value) -> Result<(), Error> {
+        match (key, value) {
+            (_, Value::None) => {}
+            ("$value", Value::Text(text)) => {
+                if !text.is_empty() {
+                    self.children.push(Node::Text(text));
+                }
+            }
+            ("$value", Value::Element(element)) => self.children.push(Node::Element(element)),
+            ("$value", Value::Seq(items)) => {
+                for item in items {
+                    self.push_field("$value", item)?;
+                }
+            }
+            ("$namespaces", Value::Map(entries)) => {
+                for (prefix, uri) in entries {
+                    if let Value::Text(uri) = uri {
+                        let name = match prefix.as_str() {
+                            "" => "xmlns".to_string(),
+                            prefix => format!("xmlns:{}", prefix),
+                        };
+                        self.push_attribute(name, uri);
+                    }
+                }
+            }
+            ("$extensions", Value::Map(entries)) => {
+                for (kind, rows) in entries {
+                    let rows = match rows {
+                        Value::Seq(rows) => rows,
+                        _ => continue,
+                    };
+                    for row in rows {
+                        let mut row = match row {
+                            Value::Seq(row) => row.into_iter().filter_map(|value| match value {
+                                Value::Text(text) => Some(text),
+                                _ => None,
+                            }),
+                            _ => continue,
+                        };
+                        match (kind.as_str(), row.next(), row.next(), row.next()) {
+                            ("attributes", Some(name), Some(value), _) => {
+                                self.push_attribute(name, value)
+                            }
+                            ("elements", _, _, Some(xml)) => {
+                                self.children.push(Node::Element(Element::parse(&xml)?))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            (_, Value::Text(text)) => self.push_attribute(key.to_string(), text),
+            (_, Value::Element(mut element)) => {
+                element.name = key.to_string();
+                self.children.push(Node::Element(element));
+            }
+            (_, Value::Seq(items)) => {
+                if items.iter().all(|item| matches!(item, Value::Text(_))) {
+                    if !items.is_empty() {
+                        let texts: Vec<String> = items
+                            .into_iter()
+                            .filter_map(|item| match item {
+                                Value::Text(text) => Some(text),
+                                _ => None,
+                            })
+                            .collect();
+                        self.push_attribute(key.to_string(), texts.join(" "));
+                    }
+                } else {
+                    for item in items {
+                        self.push_field(key, item)?;
+                    }
+                }
+            }
+            (_, Value::Map(_)) => {
+                return Err(Error::new(format!(
+                    "map field `{}` can't be serialized",
+                    key
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::new(msg.to_string())
+    }
+}
+
+struct ValueSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<Value, Error> {
+                Ok(Value::Text(value.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = ser::Impossible<Value, Error>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = StructSerializer;
+
+    serialize_display! {
+        serialize_bool: bool, serialize_i8: i8, serialize_i16: i16, serialize_i32: i32,
+        serialize_i64: i64, serialize_u8: u8, serialize_u16: u16, serialize_u32: u32,
+        serialize_u64: u64, serialize_f32: f32, serialize_f64: f64, serialize_char: char,
+        serialize_str: &str
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Value, Error> {
+        Err(Error::new("bytes can't be serialized"))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::Text(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    /// Written as an element named after the variant, as in `<cenc:pssh>...</cenc:pssh>`.
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        let mut element = match value.serialize(ValueSerializer)? {
+            Value::Element(element) => element,
+            value => {
+                let mut element = Element::new(variant);
+                element.push_field("$value", value)?;
+                element
+            }
+        };
+        element.name = variant.to_string();
+        Ok(Value::Element(element))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::new(format!(
+            "tuple variant `{}` can't be serialized",
+            variant
+        )))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer {
+            element: Element::new(name),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer, Error> {
+        Ok(StructSerializer {
+            element: Element::new(variant),
+        })
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Seq(self.items))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct MapSerializer {
+    entries: Vec<(String, Value)>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Value::Text(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(Error::new("map keys should be text")),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::new("map value serialized before its key"))?;
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Map(self.entries))
+    }
+}
+
+struct StructSerializer {
+    element: Element,
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = value.serialize(ValueSerializer)?;
+        self.element.push_field(key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Element(self.element))
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeStruct::end(self)
+    }
+}